@@ -0,0 +1,314 @@
+//! CPI helpers for on-chain programs that invoke Pact.
+//!
+//! Each function assembles the instruction with the account ordering the
+//! handlers in `instructions_v2.rs` expect, so callers never hardcode indices.
+//! Pass `&[]` as `signers` for a plain `invoke`, or the caller's PDA seeds when
+//! one of the party accounts is a PDA owned by the calling program.
+//!
+//! `remaining` is appended after the fixed accounts, in order and with each
+//! account's own signer/writable flags: the fee recipient of an escrow with a
+//! platform fee, pooled funders on a refund, token accounts, and so on. Pass
+//! `&[]` when the escrow needs none.
+
+use pinocchio::{
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction, Signer},
+    program::invoke_signed_with_bounds,
+    program_error::ProgramError,
+    ProgramResult,
+};
+
+use crate::{
+    IX_ACCEPT_DELIVERY, IX_ARBITRATE, IX_CREATE_ESCROW, IX_DISPUTE, IX_MARK_DELIVERED,
+    IX_REFUND, IX_RELEASE,
+};
+
+// Most accounts a helper forwards through `remaining`
+const MAX_REMAINING_ACCOUNTS: usize = 16;
+// The widest fixed account list (CreateEscrowV2) plus the remaining ones
+const MAX_CPI_ACCOUNTS: usize = 5 + MAX_REMAINING_ACCOUNTS;
+
+// Invokes Pact with the fixed `accounts` and their `infos`, in handler order,
+// then `remaining`
+fn invoke_pact(
+    accounts: &[AccountMeta],
+    infos: &[&AccountInfo],
+    remaining: &[&AccountInfo],
+    data: &[u8],
+    signers: &[Signer],
+) -> ProgramResult {
+    if remaining.len() > MAX_REMAINING_ACCOUNTS {
+        return Err(ProgramError::InvalidArgument);
+    }
+    let fixed = accounts.len();
+    let count = fixed + remaining.len();
+    // Slots past `count` are never read; they only fill the arrays
+    let metas: [AccountMeta; MAX_CPI_ACCOUNTS] = core::array::from_fn(|i| match i {
+        _ if i < fixed => accounts[i].clone(),
+        _ if i < count => AccountMeta::from(remaining[i - fixed]),
+        _ => AccountMeta::readonly(&crate::ID),
+    });
+    let all_infos: [&AccountInfo; MAX_CPI_ACCOUNTS] = core::array::from_fn(|i| match i {
+        _ if i < fixed => infos[i],
+        _ if i < count => remaining[i - fixed],
+        _ => infos[0],
+    });
+
+    let ix = Instruction {
+        program_id: &crate::ID,
+        accounts: &metas[..count],
+        data,
+    };
+
+    invoke_signed_with_bounds::<MAX_CPI_ACCOUNTS>(&ix, &all_infos[..count], signers)
+}
+
+// ============================================================================
+// CreateEscrowV2
+// ============================================================================
+
+pub struct CreateEscrowV2Accounts<'a> {
+    pub buyer: &'a AccountInfo,
+    pub seller: &'a AccountInfo,
+    pub arbitrator: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+}
+
+pub fn create_escrow_v2(
+    ctx: &CreateEscrowV2Accounts,
+    amount: u64,
+    seed: u64,
+    timeout_seconds: u64,
+    terms_hash: &[u8; 32],
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    // [disc(1)] [amount(8)] [seed(8)] [timeout_seconds(8)] [terms_hash(32)]
+    let mut data = [0u8; 57];
+    data[0] = IX_CREATE_ESCROW;
+    data[1..9].copy_from_slice(&amount.to_le_bytes());
+    data[9..17].copy_from_slice(&seed.to_le_bytes());
+    data[17..25].copy_from_slice(&timeout_seconds.to_le_bytes());
+    data[25..57].copy_from_slice(terms_hash);
+
+    let accounts = [
+        AccountMeta::writable_signer(ctx.buyer.key()),
+        AccountMeta::readonly(ctx.seller.key()),
+        AccountMeta::readonly(ctx.arbitrator.key()),
+        AccountMeta::writable(ctx.escrow.key()),
+        AccountMeta::readonly(ctx.system_program.key()),
+    ];
+
+    invoke_pact(
+        &accounts,
+        &[ctx.buyer, ctx.seller, ctx.arbitrator, ctx.escrow, ctx.system_program],
+        remaining,
+        &data,
+        signers,
+    )
+}
+
+// ============================================================================
+// MarkDelivered
+// ============================================================================
+
+pub struct MarkDeliveredAccounts<'a> {
+    pub seller: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+}
+
+/// `delivery_proof`: hash of the delivery evidence, required when the escrow
+/// was created with `require_delivery_proof`.
+pub fn mark_delivered(
+    ctx: &MarkDeliveredAccounts,
+    delivery_proof: Option<&[u8; 32]>,
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    // [disc(1)] [delivery_proof(32)]?
+    let mut data = [0u8; 33];
+    data[0] = IX_MARK_DELIVERED;
+    let data_len = match delivery_proof {
+        Some(hash) => {
+            data[1..33].copy_from_slice(hash);
+            33
+        }
+        None => 1,
+    };
+
+    let accounts = [
+        AccountMeta::readonly_signer(ctx.seller.key()),
+        AccountMeta::writable(ctx.escrow.key()),
+    ];
+
+    invoke_pact(&accounts, &[ctx.seller, ctx.escrow], remaining, &data[..data_len], signers)
+}
+
+// ============================================================================
+// AcceptDelivery / ReleaseV2
+// ============================================================================
+
+pub struct SettleAccounts<'a> {
+    pub buyer: &'a AccountInfo,
+    pub seller: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+}
+
+pub fn accept_delivery(
+    ctx: &SettleAccounts,
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    invoke_settle(ctx, IX_ACCEPT_DELIVERY, signers, remaining)
+}
+
+// `ctx.buyer` may be the escrow's releaser, signing in the buyer's place
+pub fn release_v2(
+    ctx: &SettleAccounts,
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    invoke_settle(ctx, IX_RELEASE, signers, remaining)
+}
+
+fn invoke_settle(
+    ctx: &SettleAccounts,
+    discriminator: u8,
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    // AcceptDelivery pays any late-delivery penalty back to the buyer
+    let buyer = if discriminator == IX_ACCEPT_DELIVERY {
+        AccountMeta::writable_signer(ctx.buyer.key())
+    } else {
+        AccountMeta::readonly_signer(ctx.buyer.key())
+    };
+    let accounts = [
+        buyer,
+        AccountMeta::writable(ctx.seller.key()),
+        AccountMeta::writable(ctx.escrow.key()),
+    ];
+
+    invoke_pact(&accounts, &[ctx.buyer, ctx.seller, ctx.escrow], remaining, &[discriminator], signers)
+}
+
+// ============================================================================
+// RefundV2
+// ============================================================================
+
+pub struct RefundV2Accounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub buyer: &'a AccountInfo,
+    pub seller: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+}
+
+pub fn refund_v2(
+    ctx: &RefundV2Accounts,
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    let accounts = [
+        AccountMeta::readonly_signer(ctx.authority.key()),
+        AccountMeta::writable(ctx.buyer.key()),
+        AccountMeta::readonly(ctx.seller.key()),
+        AccountMeta::writable(ctx.escrow.key()),
+    ];
+
+    invoke_pact(
+        &accounts,
+        &[ctx.authority, ctx.buyer, ctx.seller, ctx.escrow],
+        remaining,
+        &[IX_REFUND],
+        signers,
+    )
+}
+
+// ============================================================================
+// Dispute
+// ============================================================================
+
+pub struct DisputeAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+}
+
+pub fn dispute(
+    ctx: &DisputeAccounts,
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    let accounts = [
+        AccountMeta::readonly_signer(ctx.authority.key()),
+        AccountMeta::writable(ctx.escrow.key()),
+    ];
+
+    invoke_pact(&accounts, &[ctx.authority, ctx.escrow], remaining, &[IX_DISPUTE], signers)
+}
+
+// ============================================================================
+// Arbitrate
+// ============================================================================
+
+pub struct ArbitrateAccounts<'a> {
+    pub arbitrator: &'a AccountInfo,
+    pub buyer: &'a AccountInfo,
+    pub seller: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+}
+
+/// `decision`: 0 = refund to buyer, 1 = release to seller.
+/// `rationale_hash`: hash of the off-chain ruling rationale, zeroes = none.
+pub fn arbitrate(
+    ctx: &ArbitrateAccounts,
+    decision: u8,
+    rationale_hash: &[u8; 32],
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    invoke_arbitrate(ctx, &[decision], rationale_hash, signers, remaining)
+}
+
+/// Rules a split: `seller_bps` of the amount after the arbitrator's fee goes
+/// to the seller, the rest back to the buyer. Native SOL escrows only.
+pub fn arbitrate_split(
+    ctx: &ArbitrateAccounts,
+    seller_bps: u16,
+    rationale_hash: &[u8; 32],
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    invoke_arbitrate(ctx, &seller_bps.to_le_bytes(), rationale_hash, signers, remaining)
+}
+
+// `ruling` is decision(1) or seller_bps(2), as Arbitrate parses it
+fn invoke_arbitrate(
+    ctx: &ArbitrateAccounts,
+    ruling: &[u8],
+    rationale_hash: &[u8; 32],
+    signers: &[Signer],
+    remaining: &[&AccountInfo],
+) -> ProgramResult {
+    let accounts = [
+        AccountMeta::writable_signer(ctx.arbitrator.key()),
+        AccountMeta::writable(ctx.buyer.key()),
+        AccountMeta::writable(ctx.seller.key()),
+        AccountMeta::writable(ctx.escrow.key()),
+    ];
+
+    // [disc(1)] [decision(1) | seller_bps(2)] [rationale_hash(32)]
+    let mut data = [0u8; 35];
+    data[0] = IX_ARBITRATE;
+    let ruling_end = 1 + ruling.len();
+    data[1..ruling_end].copy_from_slice(ruling);
+    data[ruling_end..ruling_end + 32].copy_from_slice(rationale_hash);
+
+    invoke_pact(
+        &accounts,
+        &[ctx.arbitrator, ctx.buyer, ctx.seller, ctx.escrow],
+        remaining,
+        &data[..ruling_end + 32],
+        signers,
+    )
+}
@@ -0,0 +1,134 @@
+//! Pact error codes.
+//!
+//! Every variant has a fixed code. Codes are part of the client interface and
+//! never change once published: new errors take the next free number, and a
+//! retired error keeps its number reserved.
+//!
+//! | Code | Error                    | Meaning                                                  |
+//! |------|--------------------------|----------------------------------------------------------|
+//! | 0    | `InvalidRecipient`       | Payout recipient is the System Program or Pact itself    |
+//! | 1    | `EscrowFrozen`           | Escrow is frozen after timeout and awaits the arbitrator |
+//! | 2    | `MissingDiscriminator`   | Instruction data is empty                                |
+//! | 3    | `ContributionMismatch`   | Funder contributions don't add up to the amount          |
+//! | 4    | `TooManyFunders`         | Escrow already tracks the maximum number of funders      |
+//! | 5    | `MissingDeliveryProof`   | Escrow requires a delivery proof hash                    |
+//! | 6    | `DuplicateAccount`       | Two accounts that must be distinct are the same          |
+//! | 7    | `PriceBelowThreshold`    | Oracle price is below the release threshold              |
+//! | 8    | `OraclePriceUnavailable` | Oracle is not reporting a tradable price                 |
+//! | 9    | `SettlementPending`      | A settlement proposal is still within its ratify window  |
+//! | 10   | `NothingToDispute`       | Escrow holds no funds                                    |
+//! | 11   | `SellerNotAllowed`       | Seller is not on the seller allowlist                    |
+//! | 12   | `BalanceMismatch`        | Recorded amount exceeds the balance above rent           |
+//! | 13   | `RecipientNotRentExempt` | Payout would leave a new recipient below rent exemption  |
+//! | 14   | `InvalidEscrowSize`      | Escrow account isn't exactly the current layout's size   |
+//! | 15   | `MissingRationale`       | Escrow requires a rationale hash with each ruling        |
+//! | 16   | `DisputeFlagsMissing`    | Escrow is Disputed but records no disputing party        |
+//! | 17   | `EscrowNotSystemOwned`   | Create target is already owned by a program              |
+//! | 18   | `ArbitratorSelfPayout`   | Ruling would pay the arbitrator who made it              |
+//! | 19   | `PartialPayoutDisabled`  | Escrow settles all-or-nothing                            |
+//! | 20   | `AlreadySettled`         | Escrow was already released or refunded                  |
+//! | 21   | `TokenEscrowUnsupported` | Instruction only settles native SOL escrows              |
+//! | 22   | `EscrowNotRentExempt`    | Payout would leave the escrow below rent exemption       |
+//! | 23   | `AlreadyDelivered`       | Seller has delivered; no cancelling or reassigning       |
+//! | 24   | `DisputeResponsePending` | The other party can still respond to the dispute         |
+//! | 25   | `StaleOraclePrice`       | Oracle price was published too many slots ago            |
+
+use pinocchio::program_error::ProgramError;
+
+/// Pact-specific errors, returned to clients as `ProgramError::Custom(code)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum PactError {
+    /// Payout recipient is the System Program or the Pact program itself
+    InvalidRecipient = 0,
+    /// Escrow is frozen after timeout and awaits the arbitrator
+    EscrowFrozen = 1,
+    /// Instruction data is empty, so there is no discriminator to dispatch on
+    MissingDiscriminator = 2,
+    /// Recorded funder contributions don't add up to the escrowed amount
+    ContributionMismatch = 3,
+    /// The escrow already tracks the maximum number of funders
+    TooManyFunders = 4,
+    /// The escrow requires a delivery proof hash and MarkDelivered had none
+    MissingDeliveryProof = 5,
+    /// Two accounts that must be distinct share the same pubkey
+    DuplicateAccount = 6,
+    /// The oracle price is below the escrow's release threshold
+    PriceBelowThreshold = 7,
+    /// The oracle account is not reporting a tradable price
+    OraclePriceUnavailable = 8,
+    /// A settlement proposal is still within its ratify window
+    SettlementPending = 9,
+    /// The escrow holds no funds, so there is nothing to dispute
+    NothingToDispute = 10,
+    /// The seller is not on the operator's seller allowlist
+    SellerNotAllowed = 11,
+    /// The recorded amount exceeds the escrow's balance above rent
+    BalanceMismatch = 12,
+    /// The payout recipient doesn't exist and the payout can't make it rent-exempt
+    RecipientNotRentExempt = 13,
+    /// The escrow account's data length isn't the current layout's size; run ResizeEscrow
+    InvalidEscrowSize = 14,
+    /// The escrow requires a rationale hash and Arbitrate had none
+    MissingRationale = 15,
+    /// The escrow is Disputed but neither dispute flag is set
+    DisputeFlagsMissing = 16,
+    /// CreateEscrowV2's escrow account isn't owned by the System Program
+    EscrowNotSystemOwned = 17,
+    /// The ruling's payout recipient is the ruling arbitrator
+    ArbitratorSelfPayout = 18,
+    /// The escrow was created with disallow_partial and settles all-or-nothing
+    PartialPayoutDisabled = 19,
+    /// The escrow was already released or refunded, e.g. by a racing cancel or ruling
+    AlreadySettled = 20,
+    /// The escrow holds SPL tokens and this instruction only moves lamports
+    TokenEscrowUnsupported = 21,
+    /// A payout would take the escrow below its rent-exempt minimum
+    EscrowNotRentExempt = 22,
+    /// The seller has delivered, so the escrow can no longer be cancelled or reassigned
+    AlreadyDelivered = 23,
+    /// The dispute's response window is still open, so the arbitrator can't rule yet
+    DisputeResponsePending = 24,
+    /// The oracle's aggregate price was published more than MAX_ORACLE_AGE_SLOTS ago
+    StaleOraclePrice = 25,
+}
+
+impl From<PactError> for ProgramError {
+    fn from(e: PactError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+/// Human-readable text for a `ProgramError::Custom` code returned by Pact.
+#[cfg(feature = "client")]
+pub fn error_message(code: u32) -> &'static str {
+    match code {
+        0 => "Payout recipient is the System Program or the Pact program itself",
+        1 => "Escrow is frozen after timeout and awaits the arbitrator",
+        2 => "Instruction data is empty",
+        3 => "Funder contributions don't add up to the escrowed amount",
+        4 => "The escrow already tracks the maximum number of funders",
+        5 => "The escrow requires a delivery proof hash",
+        6 => "Two accounts that must be distinct share the same pubkey",
+        7 => "The oracle price is below the release threshold",
+        8 => "The oracle is not reporting a tradable price",
+        9 => "A settlement proposal is still within its ratify window",
+        10 => "The escrow holds no funds, so there is nothing to dispute",
+        11 => "The seller is not on the seller allowlist",
+        12 => "The recorded amount exceeds the escrow's balance above rent",
+        13 => "The payout recipient doesn't exist and the payout can't make it rent-exempt",
+        14 => "The escrow account isn't the current layout's size; run ResizeEscrow",
+        15 => "The escrow requires a rationale hash with each arbitration ruling",
+        16 => "The escrow is marked Disputed but records no disputing party",
+        17 => "The escrow address is already owned by a program; it can't be created",
+        18 => "An arbitrator can't rule a payout to themselves",
+        19 => "The escrow only settles in full; partial payouts are disabled",
+        20 => "The escrow was already released or refunded",
+        21 => "This instruction only settles native SOL escrows",
+        22 => "The payout would leave the escrow below rent exemption",
+        23 => "The seller has delivered; the escrow can no longer be cancelled or reassigned",
+        24 => "The other party can still respond to the dispute; the arbitrator must wait",
+        25 => "The oracle price is too old; wait for a fresh publish",
+        _ => "Unknown Pact error",
+    }
+}
@@ -0,0 +1,50 @@
+//! Parser for `ExportState` return data (`client` feature).
+//!
+//! Each call returns one chunk of the raw escrow account behind a small
+//! header. Clients check the schema version, copy `bytes` into their buffer
+//! at `offset`, and call again at `next_offset` until it returns `None`.
+
+use pinocchio::program_error::ProgramError;
+
+use crate::instructions_v2::{EXPORT_HEADER_SIZE, EXPORT_SCHEMA_VERSION};
+
+/// One chunk of an `ExportState` snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExportChunk<'a> {
+    /// Layout version of the exported account
+    pub schema_version: u8,
+    /// Size of the whole account
+    pub total_len: usize,
+    /// Where `bytes` starts in the account
+    pub offset: usize,
+    /// The account bytes at `offset..offset + bytes.len()`
+    pub bytes: &'a [u8],
+}
+
+impl<'a> ExportChunk<'a> {
+    /// Parses one `ExportState` return. Fails with `InvalidAccountData` if
+    /// the export comes from a different layout version than this parser's,
+    /// rather than letting it be read at the wrong offsets.
+    pub fn parse(return_data: &'a [u8]) -> Result<Self, ProgramError> {
+        if return_data.len() < EXPORT_HEADER_SIZE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let schema_version = return_data[0];
+        if schema_version != EXPORT_SCHEMA_VERSION {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let total_len = u16::from_le_bytes([return_data[1], return_data[2]]) as usize;
+        let offset = u16::from_le_bytes([return_data[3], return_data[4]]) as usize;
+        let bytes = &return_data[EXPORT_HEADER_SIZE..];
+        if bytes.is_empty() || offset + bytes.len() > total_len {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(ExportChunk { schema_version, total_len, offset, bytes })
+    }
+
+    /// The offset to request next, or `None` once this chunk ends the account.
+    pub fn next_offset(&self) -> Option<u16> {
+        let end = self.offset + self.bytes.len();
+        (end < self.total_len).then_some(end as u16)
+    }
+}
@@ -3,11 +3,14 @@ use pinocchio::{
     instruction::{AccountMeta, Instruction, Seed, Signer},
     program::invoke_signed,
     program_error::ProgramError,
-    pubkey::{find_program_address, Pubkey},
+    pubkey::Pubkey,
     sysvars::{rent::Rent, Sysvar},
     ProgramResult,
 };
 
+use crate::error::PactError;
+use crate::instructions_v2::derive_escrow;
+
 // System Program ID
 const SYSTEM_PROGRAM_ID: Pubkey = [0u8; 32];
 
@@ -24,19 +27,6 @@ const STATUS_ACTIVE: u8 = 0;
 const STATUS_RELEASED: u8 = 1;
 const STATUS_REFUNDED: u8 = 2;
 
-/// Derive escrow PDA from buyer + seller + seed
-fn derive_escrow(buyer: &Pubkey, seller: &Pubkey, seed: u64, program_id: &Pubkey) -> (Pubkey, u8) {
-    find_program_address(
-        &[
-            b"escrow",
-            buyer,
-            seller,
-            &seed.to_le_bytes(),
-        ],
-        program_id,
-    )
-}
-
 // ============================================================================
 // CreateEscrow - Buyer creates and funds an escrow
 // ============================================================================
@@ -71,6 +61,11 @@ impl<'a> CreateEscrow<'a> {
             return Err(ProgramError::InvalidInstructionData);
         }
 
+        // A buyer can't escrow funds to themselves
+        if buyer.key() == seller.key() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
         // Get program ID from escrow's owner (before creation, it should be system program)
         let program_id = crate::ID;
 
@@ -225,6 +220,12 @@ impl<'a> Release<'a> {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        // Same guard as the v2 payouts: never credit the System Program or
+        // Pact itself
+        if seller.key() == &SYSTEM_PROGRAM_ID || seller.key() == &crate::ID {
+            return Err(PactError::InvalidRecipient.into());
+        }
+
         // Update status to released
         let mut escrow_data = escrow.try_borrow_mut_data()?;
         escrow_data[80] = STATUS_RELEASED;
@@ -313,6 +314,12 @@ impl<'a> Refund<'a> {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        // Same guard as the v2 payouts: never credit the System Program or
+        // Pact itself
+        if buyer.key() == &SYSTEM_PROGRAM_ID || buyer.key() == &crate::ID {
+            return Err(PactError::InvalidRecipient.into());
+        }
+
         // Update status to refunded
         let mut escrow_data = escrow.try_borrow_mut_data()?;
         escrow_data[80] = STATUS_REFUNDED;
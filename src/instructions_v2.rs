@@ -1,48 +1,294 @@
 use pinocchio::{
     account_info::AccountInfo,
     instruction::{AccountMeta, Instruction, Seed, Signer},
-    program::invoke_signed,
+    log::{sol_log, sol_log_64},
+    msg,
+    program::{invoke_signed, set_return_data, MAX_RETURN_DATA},
     program_error::ProgramError,
-    pubkey::{find_program_address, Pubkey},
+    pubkey::{log as log_pubkey, Pubkey},
     sysvars::{clock::Clock, rent::Rent, Sysvar},
     ProgramResult,
 };
 
+use crate::error::PactError;
+use crate::status::{can_transition, EscrowStatus};
+
+#[cfg(target_os = "solana")]
+use pinocchio::pubkey::find_program_address;
+
 // ============================================================================
 // Constants
 // ============================================================================
 
 const SYSTEM_PROGRAM_ID: Pubkey = [0u8; 32];
 
+// SPL Token programs a token escrow may use, and the Associated Token Account
+// program its vault address derives under
+const TOKEN_PROGRAM_ID: Pubkey = [
+    6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133, 237,
+    95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+];
+const TOKEN_2022_PROGRAM_ID: Pubkey = [
+    6, 221, 246, 225, 238, 117, 143, 222, 24, 66, 93, 188, 228, 108, 205, 218, 182, 26, 252, 77,
+    131, 185, 13, 39, 254, 189, 249, 40, 216, 161, 139, 252,
+];
+const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = [
+    140, 151, 37, 143, 78, 36, 137, 241, 187, 61, 16, 41, 20, 142, 13, 131, 11, 90, 19, 153, 218,
+    255, 16, 132, 4, 142, 123, 216, 219, 233, 248, 89,
+];
+
 // Escrow discriminator: "PACTESCR" as u64 LE
 const ESCROW_DISC: u64 = 0x5041435445534352;
 
 // Escrow account size (v2)
 // discriminator(8) + buyer(32) + seller(32) + arbitrator(32) + mint(32) + 
 // amount(8) + created_at(8) + timeout_seconds(8) + terms_hash(32) + 
-// status(1) + flags(1) + bump(1) = 195 bytes
-const ESCROW_SIZE: usize = 195;
-
-// Status values
-const STATUS_ACTIVE: u8 = 0;
-const STATUS_DELIVERED: u8 = 1;
-const STATUS_ACCEPTED: u8 = 2;
-const STATUS_DISPUTED: u8 = 3;
-const STATUS_RELEASED: u8 = 4;
-const STATUS_REFUNDED: u8 = 5;
-
-// Flag bits
-const FLAG_SELLER_DELIVERED: u8 = 1 << 0;
-const FLAG_BUYER_ACCEPTED: u8 = 1 << 1;
-const FLAG_BUYER_DISPUTED: u8 = 1 << 2;
-const FLAG_SELLER_DISPUTED: u8 = 1 << 3;
+// status(1) + flags(1) + bump(1) + appeal_arbitrator(32) + ruling(1) +
+// ruled_at(8) + delivered_at(8) + min_dispute_delay(8) + timeout_mode(1) +
+// funder_count(1) + funders(8 * 40) + delivery_proof(32) + price_oracle(32) +
+// price_threshold(8) + funded_amount(8) + proposed_buyer_bps(2) +
+// proposed_at(8) + quote_mint(32) + quote_amount(8) + disputed_at(8) + seed(8) +
+// milestone_count(1) + milestone_delivered(1) + milestone_settled(1) +
+// milestone_amounts(8 * 8) + callback_program(32) +
+// acceptance_window_seconds(8) + periods_remaining(2) + period_amount(8) +
+// cancel_fee_cap(8) + cancel_fee_ramp_seconds(8) + require_rationale(1) +
+// rationale_hash(32) + notify(1) + seller_threshold(1) +
+// seller_signer_count(1) + seller_signers(5 * 32) + strict_acceptance(1) +
+// rent_paid(8) + updated_at(8) + original_seller(32) + delivery_deadline(8) +
+// late_penalty_bps(2) + mint_decimals(1) + policy(1) + rent_recipient(1) +
+// rent_treasury(32) + releaser(32) + arbitrator_fee(8) + fee_recipient(32) +
+// platform_fee_bps(2) + arbitration_window(8) = 1225 bytes
+//
+// `amount` is what the escrow still holds: it grows with AddFunds and drops to
+// zero once the escrow is released or refunded. `funded_amount` keeps the
+// total ever deposited so settled escrows still show the deal size.
+const ESCROW_SIZE: usize = 1225;
+
+// The v1 layout, which MigrateV1ToV2 converts: disc(8) + buyer(32) +
+// seller(32) + amount(8) + status(1) = 81 bytes. Buyer and seller sit at the
+// same offsets as in v2; amount and status don't.
+const V1_ESCROW_SIZE: usize = 81;
+const V1_OFF_BUYER: usize = 8;
+const V1_OFF_SELLER: usize = 40;
+const V1_OFF_AMOUNT: usize = 72;
+const V1_OFF_STATUS: usize = 80;
+const V1_STATUS_ACTIVE: u8 = 0;
+const V1_STATUS_RELEASED: u8 = 1;
+const V1_STATUS_REFUNDED: u8 = 2;
+
+// The flags byte. Every bit is defined, so any stored byte is a valid set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Flags(u8);
+
+impl Flags {
+    pub const NONE: Flags = Flags(0);
+    pub const SELLER_DELIVERED: Flags = Flags(1 << 0);
+    pub const BUYER_ACCEPTED: Flags = Flags(1 << 1);
+    pub const BUYER_DISPUTED: Flags = Flags(1 << 2);
+    pub const SELLER_DISPUTED: Flags = Flags(1 << 3);
+    pub const FROZEN: Flags = Flags(1 << 4);
+    pub const REQUIRE_DELIVERY_PROOF: Flags = Flags(1 << 5);
+    pub const REFUND_REQUIRES_NO_DELIVERY: Flags = Flags(1 << 6);
+    // timeout_seconds holds an absolute unix deadline instead of an offset from created_at
+    pub const ABSOLUTE_DEADLINE: Flags = Flags(1 << 7);
+    // Either party disputed
+    pub const DISPUTED: Flags = Flags::BUYER_DISPUTED.union(Flags::SELLER_DISPUTED);
+
+    pub const fn from_u8(byte: u8) -> Self {
+        Flags(byte)
+    }
+
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+
+    pub const fn union(self, other: Flags) -> Flags {
+        Flags(self.0 | other.0)
+    }
+
+    pub const fn intersection(self, other: Flags) -> Flags {
+        Flags(self.0 & other.0)
+    }
+
+    // Any bit of `other` is set
+    pub const fn intersects(self, other: Flags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn insert(&mut self, other: Flags) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Flags) {
+        self.0 &= !other.0;
+    }
+}
+
+// What happens once `timeout_seconds` elapses. These two are the only modes;
+// create rejects any other value.
+const TIMEOUT_MODE_REFUND: u8 = 0; // buyer may refund
+const TIMEOUT_MODE_FREEZE: u8 = 2; // escrow freezes for arbitrator review
+
+// Pending arbitration ruling (only used when an appeal arbitrator is set)
+const RULING_NONE: u8 = 0;
+const RULING_REFUND: u8 = 1;
+const RULING_RELEASE: u8 = 2;
+
+// Arbitrate's decision as reported in its return data and log: 0 = refund,
+// 1 = release, or a split between the two
+const DECISION_SPLIT: u8 = 2;
+
+// Notify byte: per-party "unseen" events for off-chain alerting. Handlers only
+// set them on escrows created with notifications on; MarkSeen clears them.
+const NOTIFY_ENABLED: u8 = 1 << 7;
+const UNSEEN_BUYER_DELIVERY: u8 = 1 << 0; // seller delivered, buyer to accept
+const UNSEEN_BUYER_DISPUTE: u8 = 1 << 1; // seller disputed
+const UNSEEN_SELLER_DISPUTE: u8 = 1 << 2; // buyer disputed
+const UNSEEN_BUYER_RULING: u8 = 1 << 3;
+const UNSEEN_SELLER_RULING: u8 = 1 << 4;
+const UNSEEN_BUYER_MASK: u8 = UNSEEN_BUYER_DELIVERY | UNSEEN_BUYER_DISPUTE | UNSEEN_BUYER_RULING;
+const UNSEEN_SELLER_MASK: u8 = UNSEEN_SELLER_DISPUTE | UNSEEN_SELLER_RULING;
+
+// How long the losing party has to appeal a ruling: 2 days
+const APPEAL_WINDOW_SECONDS: u64 = 172_800;
+
+// How long both parties have to ratify a proposed settlement before the
+// arbitrator may rule unilaterally: 3 days
+const SETTLEMENT_RATIFY_WINDOW_SECONDS: u64 = 259_200;
+
+// How long the other party has to respond to a dispute, filing their own side
+// with Dispute, before the arbitrator may rule: 1 day
+const DISPUTE_RESPONSE_WINDOW_SECONDS: u64 = 86_400;
+const BPS_DENOMINATOR: u64 = 10_000;
+
+// Longest acceptance window an escrow can be created with: 30 days
+const MAX_ACCEPTANCE_WINDOW_SECONDS: u64 = 2_592_000;
+
+// Highest platform fee an escrow can be created with: 10%
+const MAX_PLATFORM_FEE_BPS: u16 = 1_000;
+
+// Decimals of the escrowed asset, stored so clients can format amounts
+// without fetching the mint. Native SOL escrows count in lamports.
+const NATIVE_DECIMALS: u8 = 9;
+
+// SPL mint and token account layouts, as far as Pact reads them. Token-2022
+// accounts may be longer (extensions), never shorter.
+const MINT_SIZE: usize = 82;
+const MINT_OFF_DECIMALS: usize = 44;
+const MINT_OFF_INITIALIZED: usize = 45;
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+const TOKEN_OFF_MINT: usize = 0;
+const TOKEN_OFF_OWNER: usize = 32;
+const TOKEN_OFF_AMOUNT: usize = 64;
+const TOKEN_OFF_STATE: usize = 108;
+const TOKEN_STATE_INITIALIZED: u8 = 1;
+// SPL Token TransferChecked: tag(1) + amount(8) + decimals(1)
+const TOKEN_IX_TRANSFER_CHECKED: u8 = 12;
+// SPL Token CloseAccount: tag(1)
+const TOKEN_IX_CLOSE_ACCOUNT: u8 = 9;
+
+// Per-escrow settlement policy bits, set at creation
+const POLICY_DISALLOW_PARTIAL: u8 = 1 << 0;
+// Not a policy: set only while funds are leaving the escrow or a CPI made on
+// its behalf is running, and cleared before the instruction returns. It
+// lives here because every bit of the flags byte is taken.
+const POLICY_PROCESSING: u8 = 1 << 7;
+
+// Who receives the rent when a settled escrow is closed
+const RENT_TO_BUYER: u8 = 0;
+const RENT_TO_SELLER: u8 = 1;
+const RENT_TO_TREASURY: u8 = 2;
+
+// Paid out of the reclaimed rent to a keeper who closes someone else's
+// settled escrow (`closer-reward` builds only)
+const CLOSER_REWARD_LAMPORTS: u64 = if cfg!(feature = "closer-reward") { 10_000 } else { 0 };
+
+// Staged delivery: up to 8 milestones, each released on its own
+const MAX_MILESTONES: usize = 8;
+
+// Multisig seller: up to 5 member keys, any `threshold` of which act as the
+// seller. The stored seller key still receives the payouts.
+const MAX_SELLER_SIGNERS: usize = 5;
+
+// Settlement callback instruction data: "PACTSETL" + status(1) + funded_amount(8)
+const CALLBACK_DISC: u64 = 0x504143545345544C;
+
+// Pooled funding: each entry is funder(32) + contributed amount(8)
+const MAX_FUNDERS: usize = 8;
+const FUNDER_ENTRY_SIZE: usize = 40;
+
+// Pyth v2 price account layout, as read by ReleaseIfPrice
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_ATYPE_PRICE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+const PYTH_OFF_MAGIC: usize = 0;
+const PYTH_OFF_ATYPE: usize = 8;
+const PYTH_OFF_AGG_PRICE: usize = 208;
+const PYTH_OFF_AGG_STATUS: usize = 224;
+const PYTH_OFF_AGG_PUB_SLOT: usize = 232;
+const PYTH_PRICE_ACCOUNT_MIN_LEN: usize = 240;
+// Oldest aggregate price ReleaseIfPrice accepts, in slots behind the
+// current one (about 20 seconds)
+const MAX_ORACLE_AGE_SLOTS: u64 = 50;
+
+// Seller allowlist PDA (`seller-allowlist` builds only). Layout:
+// discriminator(8) + authority(32) + bump(1) + count(1) + sellers(32 * 32)
+#[cfg(feature = "seller-allowlist")]
+const ALLOWLIST_DISC: u64 = 0x50414354414C5354; // "PACTALST"
+#[cfg(feature = "seller-allowlist")]
+const MAX_ALLOWED_SELLERS: usize = 32;
+#[cfg(feature = "seller-allowlist")]
+const ALLOWLIST_OFF_AUTHORITY: usize = 8;
+#[cfg(feature = "seller-allowlist")]
+const ALLOWLIST_OFF_BUMP: usize = 40;
+#[cfg(feature = "seller-allowlist")]
+const ALLOWLIST_OFF_COUNT: usize = 41;
+#[cfg(feature = "seller-allowlist")]
+const ALLOWLIST_OFF_SELLERS: usize = 42;
+#[cfg(feature = "seller-allowlist")]
+const ALLOWLIST_SIZE: usize = ALLOWLIST_OFF_SELLERS + MAX_ALLOWED_SELLERS * 32;
+
+// Seller reputation PDA (`seller-reputation` builds only), one per seller.
+// Layout: discriminator(8) + seller(32) + bump(1) + completed_count(8) +
+// disputed_against(8)
+#[cfg(feature = "seller-reputation")]
+const REPUTATION_DISC: u64 = 0x5041435452455055; // "PACTREPU"
+#[cfg(feature = "seller-reputation")]
+const REPUTATION_OFF_SELLER: usize = 8;
+#[cfg(feature = "seller-reputation")]
+const REPUTATION_OFF_BUMP: usize = 40;
+#[cfg(feature = "seller-reputation")]
+const REPUTATION_OFF_COMPLETED: usize = 41;
+#[cfg(feature = "seller-reputation")]
+const REPUTATION_OFF_DISPUTED_AGAINST: usize = 49;
+#[cfg(feature = "seller-reputation")]
+const REPUTATION_SIZE: usize = 57;
+
+// LookupEscrow return data when no initialized escrow exists at the address
+pub const LOOKUP_NOT_FOUND: u8 = 0xFF;
+
+// InspectEscrow return data: one bit per invariant that holds
+pub const INVARIANT_DISCRIMINATOR: u8 = 1 << 0; // Pact-owned, "PACTESCR" discriminator
+pub const INVARIANT_SIZE: u8 = 1 << 1; // current layout size
+pub const INVARIANT_STATUS: u8 = 1 << 2; // known status value
+pub const INVARIANT_FLAGS: u8 = 1 << 3; // flags agree with status
+pub const INVARIANT_AMOUNT: u8 = 1 << 4; // amount covered by balance, 0 once settled
+pub const INVARIANT_BUMP: u8 = 1 << 5; // address and bump are canonical for the stored seeds
+pub const INVARIANTS_ALL: u8 = (1 << 6) - 1;
+
+// ExportState return data: schema_version(1) + total_len(2) + offset(2),
+// then up to EXPORT_CHUNK_SIZE bytes of the account from `offset`. Bump the
+// version whenever the escrow layout changes.
+pub const EXPORT_SCHEMA_VERSION: u8 = 5;
+pub const EXPORT_HEADER_SIZE: usize = 5;
+pub const EXPORT_CHUNK_SIZE: usize = MAX_RETURN_DATA - EXPORT_HEADER_SIZE;
 
 // Account layout offsets
 const OFF_DISC: usize = 0;
 const OFF_BUYER: usize = 8;
 const OFF_SELLER: usize = 40;
 const OFF_ARBITRATOR: usize = 72;
-const OFF_MINT: usize = 104;
+const OFF_MINT: usize = 104; // zero = native SOL
 const OFF_AMOUNT: usize = 136;
 const OFF_CREATED_AT: usize = 144;
 const OFF_TIMEOUT: usize = 152;
@@ -50,16 +296,113 @@ const OFF_TERMS_HASH: usize = 160;
 const OFF_STATUS: usize = 192;
 const OFF_FLAGS: usize = 193;
 const OFF_BUMP: usize = 194;
+const OFF_APPEAL_ARBITRATOR: usize = 195;
+const OFF_RULING: usize = 227;
+const OFF_RULED_AT: usize = 228;
+const OFF_DELIVERED_AT: usize = 236;
+const OFF_MIN_DISPUTE_DELAY: usize = 244;
+const OFF_TIMEOUT_MODE: usize = 252;
+const OFF_FUNDER_COUNT: usize = 253;
+const OFF_FUNDERS: usize = 254;
+const OFF_DELIVERY_PROOF: usize = 574;
+const OFF_PRICE_ORACLE: usize = 606;
+const OFF_PRICE_THRESHOLD: usize = 638;
+const OFF_FUNDED_AMOUNT: usize = 646;
+const OFF_PROPOSED_BUYER_BPS: usize = 654;
+const OFF_PROPOSED_AT: usize = 656;
+const OFF_QUOTE_MINT: usize = 664;
+const OFF_QUOTE_AMOUNT: usize = 696;
+const OFF_DISPUTED_AT: usize = 704;
+const OFF_SEED: usize = 712;
+const OFF_MILESTONE_COUNT: usize = 720;
+const OFF_MILESTONE_DELIVERED: usize = 721; // bitmask by milestone index
+const OFF_MILESTONE_SETTLED: usize = 722; // bitmask by milestone index
+const OFF_MILESTONE_AMOUNTS: usize = 723;
+const OFF_CALLBACK_PROGRAM: usize = 787;
+const OFF_ACCEPTANCE_WINDOW: usize = 819;
+const OFF_PERIODS_REMAINING: usize = 827; // u16
+const OFF_PERIOD_AMOUNT: usize = 829;
+const OFF_CANCEL_FEE_CAP: usize = 837;
+const OFF_CANCEL_FEE_RAMP: usize = 845;
+const OFF_REQUIRE_RATIONALE: usize = 853;
+const OFF_RATIONALE_HASH: usize = 854;
+const OFF_NOTIFY: usize = 886;
+const OFF_SELLER_THRESHOLD: usize = 887;
+const OFF_SELLER_SIGNER_COUNT: usize = 888;
+const OFF_SELLER_SIGNERS: usize = 889; // 5 * 32
+const OFF_STRICT_ACCEPTANCE: usize = 1049;
+const OFF_RENT_PAID: usize = 1050;
+const OFF_UPDATED_AT: usize = 1058;
+const OFF_ORIGINAL_SELLER: usize = 1066; // PDA seed once reassigned, else zero
+const OFF_DELIVERY_DEADLINE: usize = 1098;
+const OFF_LATE_PENALTY_BPS: usize = 1106; // u16
+const OFF_MINT_DECIMALS: usize = 1108;
+const OFF_POLICY: usize = 1109;
+const OFF_RENT_RECIPIENT: usize = 1110;
+const OFF_RENT_TREASURY: usize = 1111; // zero unless rent goes to a treasury
+const OFF_RELEASER: usize = 1143; // zero = only the buyer releases
+const OFF_ARBITRATOR_FEE: usize = 1175; // paid to the arbitrator on a final Arbitrate
+const OFF_FEE_RECIPIENT: usize = 1183;
+const OFF_PLATFORM_FEE_BPS: usize = 1215; // u16, taken from what the seller is paid
+const OFF_ARBITRATION_WINDOW: usize = 1217; // zero = the arbitrator fee is flat
 
 // ============================================================================
 // Helpers
 // ============================================================================
 
-fn derive_escrow(buyer: &Pubkey, seller: &Pubkey, seed: u64, program_id: &Pubkey) -> (Pubkey, u8) {
-    find_program_address(
-        &[b"escrow", buyer, seller, &seed.to_le_bytes()],
-        program_id,
-    )
+// pinocchio derives PDAs through a syscall, so only on Solana targets. Host
+// builds (tests, clients) run the runtime's search themselves: from bump 255
+// down, the first SHA-256 of seeds, bump, program id and the PDA marker that
+// isn't an ed25519 point is the address.
+#[cfg(not(target_os = "solana"))]
+fn find_program_address(seeds: &[&[u8]], program_id: &Pubkey) -> (Pubkey, u8) {
+    use curve25519_dalek::edwards::CompressedEdwardsY;
+    use sha2::{Digest, Sha256};
+
+    for bump in (1..=u8::MAX).rev() {
+        let mut hasher = Sha256::new();
+        for seed in seeds {
+            hasher.update(seed);
+        }
+        hasher.update([bump]);
+        hasher.update(program_id);
+        hasher.update(b"ProgramDerivedAddress");
+        let address: Pubkey = hasher.finalize().into();
+        if CompressedEdwardsY(address).decompress().is_none() {
+            return (address, bump);
+        }
+    }
+    panic!("Unable to find a viable program address bump seed");
+}
+
+// Always returns the canonical bump, which creation stores at OFF_BUMP. Every
+// create path (CreateEscrow, CreateEscrowV2, SplitEscrow) derives it here
+// rather than taking a bump from the client, so an escrow address is unique
+// per (buyer, seller, seed). A path that ever accepts a client bump must
+// compare it to this one.
+pub(crate) fn derive_escrow(buyer: &Pubkey, seller: &Pubkey, seed: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(&escrow_seeds(buyer, seller, &seed.to_le_bytes()), program_id)
+}
+
+/// Seeds of the escrow PDA for (buyer, seller, seed), with `seed` as its
+/// little-endian bytes. Off-chain SDKs derive the address from these.
+pub fn escrow_seeds<'a>(buyer: &'a Pubkey, seller: &'a Pubkey, seed: &'a [u8; 8]) -> [&'a [u8]; 4] {
+    [b"escrow", buyer, seller, seed]
+}
+
+/// The escrow PDA and its canonical bump, exactly as every create path
+/// derives it, for v1 and v2 escrows alike.
+#[cfg(feature = "client")]
+pub fn escrow_pda(buyer: &Pubkey, seller: &Pubkey, seed: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    derive_escrow(buyer, seller, seed, program_id)
+}
+
+// Compares the key stored at `offset` with `key` in place. read_pubkey copies
+// all 32 bytes onto the stack before a compare, a cost every handler paid
+// several times over just to check its parties; this is one memcmp against
+// the account data. Use read_pubkey only where the key itself is needed.
+fn pubkey_eq(data: &[u8], offset: usize, key: &Pubkey) -> bool {
+    data[offset..offset + 32] == key[..]
 }
 
 fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
@@ -80,243 +423,4728 @@ fn write_u64(data: &mut [u8], offset: usize, val: u64) {
     data[offset..offset + 8].copy_from_slice(&val.to_le_bytes());
 }
 
-// ============================================================================
-// CreateEscrowV2
-// ============================================================================
+// Contributions recorded by CreateEscrowV2 (entry 0, the buyer) and AddFunds
+struct Funders {
+    count: usize,
+    keys: [Pubkey; MAX_FUNDERS],
+    amounts: [u64; MAX_FUNDERS],
+}
 
-pub struct CreateEscrowV2;
+fn read_funders(data: &[u8]) -> Funders {
+    let mut funders = Funders {
+        count: (data[OFF_FUNDER_COUNT] as usize).min(MAX_FUNDERS),
+        keys: [[0u8; 32]; MAX_FUNDERS],
+        amounts: [0u64; MAX_FUNDERS],
+    };
+    for i in 0..funders.count {
+        let offset = OFF_FUNDERS + i * FUNDER_ENTRY_SIZE;
+        funders.keys[i] = read_pubkey(data, offset);
+        funders.amounts[i] = read_u64(data, offset + 32);
+    }
+    funders
+}
 
-impl CreateEscrowV2 {
-    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-        // Accounts: buyer, seller, arbitrator, escrow, system_program
-        if accounts.len() < 5 {
-            return Err(ProgramError::NotEnoughAccountKeys);
+// Rejects any two identical keys among semantically distinct accounts. The
+// colliding positions are logged so the client can tell which pair it was.
+fn assert_distinct(keys: &[&Pubkey]) -> ProgramResult {
+    for (i, a) in keys.iter().enumerate() {
+        for (j, b) in keys.iter().enumerate().skip(i + 1) {
+            if a == b {
+                msg!("Duplicate account at positions");
+                sol_log_64(i as u64, j as u64, 0, 0, 0);
+                return Err(PactError::DuplicateAccount.into());
+            }
         }
+    }
+    Ok(())
+}
 
-        let buyer = &accounts[0];
-        let seller = &accounts[1];
-        let arbitrator = &accounts[2];
-        let escrow = &accounts[3];
-        let system_program = &accounts[4];
+// The cluster's unix time as the u64 every stored timestamp uses. The
+// runtime's i64 should never be negative, but a plain cast would turn one
+// into a far-future time that passes every deadline, so it clamps to 0.
+fn unix_now(clock: &Clock) -> u64 {
+    clock.unix_timestamp.max(0) as u64
+}
 
-        // Parse instruction data: amount(8) + seed(8) + timeout_seconds(8) + terms_hash(32) = 56 bytes
-        if data.len() < 56 {
-            return Err(ProgramError::InvalidInstructionData);
-        }
+// Seconds from `since` to `now`, 0 if the clock reads earlier than `since`
+// (skew at creation, or a timestamp written by a faster validator)
+fn elapsed_since(now: u64, since: u64) -> u64 {
+    now.saturating_sub(since)
+}
 
-        let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
-        let seed = u64::from_le_bytes(data[8..16].try_into().unwrap());
-        let timeout_seconds = u64::from_le_bytes(data[16..24].try_into().unwrap());
-        let mut terms_hash = [0u8; 32];
-        terms_hash.copy_from_slice(&data[24..56]);
+// Whether the escrow's timeout has passed. OFF_TIMEOUT is an offset from
+// created_at, or an absolute deadline with Flags::ABSOLUTE_DEADLINE; 0 = never.
+fn timeout_reached(escrow_data: &[u8], now: u64) -> bool {
+    let timeout = read_u64(escrow_data, OFF_TIMEOUT);
+    if timeout == 0 {
+        return false;
+    }
+    if read_flags(escrow_data).intersects(Flags::ABSOLUTE_DEADLINE) {
+        return now >= timeout;
+    }
+    elapsed_since(now, read_u64(escrow_data, OFF_CREATED_AT)) >= timeout
+}
 
-        // Validate
-        if !buyer.is_signer() {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
-        if amount == 0 {
-            return Err(ProgramError::InvalidInstructionData);
-        }
+// A relative timeout has to end at a representable time: created_at +
+// timeout_seconds near u64::MAX would wrap for anyone adding the two.
+// timeout_reached itself compares elapsed time and can't wrap.
+fn assert_timeout_fits(timeout_seconds: u64) -> ProgramResult {
+    if unix_now(&Clock::get()?).checked_add(timeout_seconds).is_none() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
 
-        // Derive and validate PDA
-        let (expected_pda, bump) = derive_escrow(buyer.key(), seller.key(), seed, program_id);
-        if escrow.key() != &expected_pda {
-            return Err(ProgramError::InvalidSeeds);
+// Every handler except ResizeEscrow requires the exact current layout, so a
+// legacy, truncated or over-allocated account fails up front with a clear
+// error instead of being read at the wrong offsets. The layout carries no
+// version byte; ESCROW_SIZE is what pins it.
+fn assert_escrow_size(escrow_data: &[u8]) -> ProgramResult {
+    if escrow_data.len() != ESCROW_SIZE {
+        return Err(PactError::InvalidEscrowSize.into());
+    }
+    Ok(())
+}
+
+// Re-entry guard: an escrow mid-payout refuses every handler until the payout
+// returns. The runtime already rejects a CPI back into Pact from another
+// program, so this only backs that up.
+fn assert_not_processing(escrow_data: &[u8]) -> ProgramResult {
+    if escrow_data[OFF_POLICY] & POLICY_PROCESSING != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+// Runs `f` with POLICY_PROCESSING set on the escrow. A failure aborts the
+// whole transaction, so the bit only needs clearing on success.
+fn while_processing(escrow: &AccountInfo, f: impl FnOnce() -> ProgramResult) -> ProgramResult {
+    escrow.try_borrow_mut_data()?[OFF_POLICY] |= POLICY_PROCESSING;
+    f()?;
+    escrow.try_borrow_mut_data()?[OFF_POLICY] &= !POLICY_PROCESSING;
+    Ok(())
+}
+
+// Escrow state is only trusted from an account this program owns: anyone can
+// create an account elsewhere holding a copied discriminator and keys
+fn assert_escrow_owner(escrow: &AccountInfo, program_id: &Pubkey) -> ProgramResult {
+    if escrow.owner() != program_id {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+    Ok(())
+}
+
+// The escrow's status. Bytes outside the known states fail with
+// InvalidAccountData rather than matching none of a handler's checks.
+fn read_status(escrow_data: &[u8]) -> Result<EscrowStatus, ProgramError> {
+    EscrowStatus::from_u8(escrow_data[OFF_STATUS])
+}
+
+// Moves the escrow to `status`, failing with InvalidAccountData if the state
+// machine doesn't allow it from the current status. Handlers call this for
+// every status change; only account initialization writes the byte directly.
+fn set_status(escrow_data: &mut [u8], status: EscrowStatus) -> ProgramResult {
+    if !can_transition(read_status(escrow_data)?, status) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    escrow_data[OFF_STATUS] = status.as_u8();
+    Ok(())
+}
+
+fn read_flags(escrow_data: &[u8]) -> Flags {
+    Flags::from_u8(escrow_data[OFF_FLAGS])
+}
+
+fn insert_flags(escrow_data: &mut [u8], flags: Flags) {
+    escrow_data[OFF_FLAGS] |= flags.as_u8();
+}
+
+fn remove_flags(escrow_data: &mut [u8], flags: Flags) {
+    escrow_data[OFF_FLAGS] &= !flags.as_u8();
+}
+
+// Resolution paths can race: a cancel, a ratified settlement and a ruling may
+// all land in the same slot. Whichever loses sees a terminal status and gets
+// a specific error instead of a generic InvalidAccountData.
+fn assert_not_settled(escrow_data: &[u8]) -> ProgramResult {
+    let status = read_status(escrow_data)?;
+    if status.is_terminal() {
+        return Err(PactError::AlreadySettled.into());
+    }
+    Ok(())
+}
+
+// Checks that `signer` both signed and is the key stored for its role. The
+// two checks sit together so a handler never trusts a signature from an
+// account it hasn't matched against the escrow.
+fn assert_signer_role(signer: &AccountInfo, stored: &Pubkey) -> ProgramResult {
+    if !signer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if signer.key() != stored {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+// assert_signer_role against the key stored at `offset`, without copying it
+fn assert_signer_at(signer: &AccountInfo, data: &[u8], offset: usize) -> ProgramResult {
+    if !signer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !pubkey_eq(data, offset, signer.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+// Seller authority: the seller's own signature, or on a multisig-seller
+// escrow, signatures from at least `threshold` distinct members anywhere in
+// the account list. `seller` must still be the stored seller key.
+fn assert_seller_authority(
+    seller: &AccountInfo,
+    escrow_data: &[u8],
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let count = escrow_data[OFF_SELLER_SIGNER_COUNT] as usize;
+    if count == 0 {
+        return assert_signer_at(seller, escrow_data, OFF_SELLER);
+    }
+    if !pubkey_eq(escrow_data, OFF_SELLER, seller.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // Members are distinct, so each one counts at most once
+    let mut approvals: u8 = 0;
+    for i in 0..count.min(MAX_SELLER_SIGNERS) {
+        let offset = OFF_SELLER_SIGNERS + i * 32;
+        if accounts.iter().any(|a| pubkey_eq(escrow_data, offset, a.key()) && a.is_signer()) {
+            approvals += 1;
         }
+    }
+    if approvals < escrow_data[OFF_SELLER_THRESHOLD] {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
 
-        // Get current timestamp
-        let clock = Clock::get()?;
-        let created_at = clock.unix_timestamp as u64;
+// After a partial payout: exactly `paid` lamports left the escrow and the
+// balance still covers the `remaining` stored amount on top of rent, so
+// OFF_AMOUNT can't drift from what the escrow really holds
+fn assert_partial_payout(
+    escrow: &AccountInfo,
+    lamports_before: u64,
+    paid: u64,
+    remaining: u64,
+) -> ProgramResult {
+    if lamports_before.checked_sub(escrow.lamports()) != Some(paid) {
+        return Err(PactError::BalanceMismatch.into());
+    }
+    assert_balance_covers(escrow, &escrow.try_borrow_data()?, remaining)
+}
 
-        // Calculate rent
-        let rent = Rent::get()?;
-        let lamports = rent.minimum_balance(ESCROW_SIZE);
+// The rent reserve an escrow keeps: the lamports recorded as rent when it was
+// created or last resized, so payouts don't shift if the rent schedule
+// changes. Falls back to today's minimum if nothing was recorded.
+fn rent_reserve(escrow: &AccountInfo, escrow_data: &[u8]) -> Result<u64, ProgramError> {
+    let rent_paid = read_u64(escrow_data, OFF_RENT_PAID);
+    if rent_paid != 0 {
+        return Ok(rent_paid);
+    }
+    Ok(Rent::get()?.minimum_balance(escrow.data_len()))
+}
 
-        // Create account
-        let bump_bytes = [bump];
-        let seed_bytes = seed.to_le_bytes();
-        let signer_seeds = [
-            Seed::from(b"escrow".as_slice()),
-            Seed::from(buyer.key().as_ref()),
-            Seed::from(seller.key().as_ref()),
-            Seed::from(seed_bytes.as_ref()),
-            Seed::from(bump_bytes.as_ref()),
-        ];
-        let signer = Signer::from(&signer_seeds);
+// Fails with BalanceMismatch when the recorded amount is more than the escrow
+// can pay out while staying rent-exempt, instead of a bare underflow mid-payout.
+// A token escrow's amount sits in its vault, so every lamport payout path
+// that checks the balance here turns token escrows away.
+fn assert_balance_covers(escrow: &AccountInfo, escrow_data: &[u8], amount: u64) -> ProgramResult {
+    if is_token_escrow(escrow_data) {
+        return Err(PactError::TokenEscrowUnsupported.into());
+    }
+    let spendable = escrow
+        .lamports()
+        .saturating_sub(rent_reserve(escrow, escrow_data)?);
+    if amount > spendable {
+        return Err(PactError::BalanceMismatch.into());
+    }
+    Ok(())
+}
 
-        let mut create_data = [0u8; 52];
-        create_data[0..4].copy_from_slice(&0u32.to_le_bytes());
-        create_data[4..12].copy_from_slice(&lamports.to_le_bytes());
-        create_data[12..20].copy_from_slice(&(ESCROW_SIZE as u64).to_le_bytes());
-        create_data[20..52].copy_from_slice(program_id);
+// Whether the flag bits are possible for the status: delivery and dispute
+// flags are set by the same transitions that move the status
+fn flags_consistent(status: EscrowStatus, flags: Flags) -> bool {
+    let delivered = flags.intersects(Flags::SELLER_DELIVERED);
+    let disputed = flags.intersects(Flags::DISPUTED);
+    match status {
+        EscrowStatus::Active => !delivered && !disputed,
+        EscrowStatus::Delivered => delivered && !disputed,
+        EscrowStatus::Disputed | EscrowStatus::Appealed => disputed,
+        _ => true,
+    }
+}
 
-        let create_accounts = [
-            AccountMeta::writable_signer(buyer.key()),
-            AccountMeta::writable_signer(escrow.key()),
-        ];
+// Check-effect-check around a terminal payout: re-reads the escrow right
+// before lamports move and insists the settlement is already recorded, so a
+// second payout in the same transaction fails the initial status check. If a
+// refactor ever moved the status write after the transfer, this catches it.
+fn assert_settled(escrow: &AccountInfo) -> ProgramResult {
+    let escrow_data = escrow.try_borrow_data()?;
+    let status = read_status(&escrow_data)?;
+    if !status.is_terminal()
+        || read_u64(&escrow_data, OFF_AMOUNT) != 0
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
 
-        let create_ix = Instruction {
-            program_id: system_program.key(),
-            accounts: &create_accounts,
-            data: &create_data,
-        };
+// Tells the escrow's callback program, if it has one, that the escrow settled.
+// The callback program must be among the instruction's accounts (after any
+// funder accounts). A failing callback fails the settlement with it: Solana
+// aborts the whole transaction on a failed CPI, so it can't be made optional.
+fn notify_callback(accounts: &[AccountInfo], escrow: &AccountInfo) -> ProgramResult {
+    let escrow_data = escrow.try_borrow_data()?;
+    let callback = read_pubkey(&escrow_data, OFF_CALLBACK_PROGRAM);
+    let status = read_status(&escrow_data)?;
+    if callback == [0u8; 32] || !status.is_terminal() {
+        return Ok(());
+    }
 
-        invoke_signed(&create_ix, &[buyer, escrow], &[signer])?;
+    let mut data = [0u8; 17];
+    data[0..8].copy_from_slice(&CALLBACK_DISC.to_le_bytes());
+    data[8] = status.as_u8();
+    data[9..17].copy_from_slice(&escrow_data[OFF_FUNDED_AMOUNT..OFF_FUNDED_AMOUNT + 8]);
+    drop(escrow_data);
 
-        // Initialize escrow data
-        let mut escrow_data = escrow.try_borrow_mut_data()?;
-        
-        write_u64(&mut escrow_data, OFF_DISC, ESCROW_DISC);
-        write_pubkey(&mut escrow_data, OFF_BUYER, buyer.key());
-        write_pubkey(&mut escrow_data, OFF_SELLER, seller.key());
-        write_pubkey(&mut escrow_data, OFF_ARBITRATOR, arbitrator.key());
-        // For v2 native SOL, we just use zeroes for mint
-        escrow_data[OFF_MINT..OFF_MINT + 32].copy_from_slice(&[0u8; 32]);
-        write_u64(&mut escrow_data, OFF_AMOUNT, amount);
-        write_u64(&mut escrow_data, OFF_CREATED_AT, created_at);
-        write_u64(&mut escrow_data, OFF_TIMEOUT, timeout_seconds);
-        escrow_data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&terms_hash);
-        escrow_data[OFF_STATUS] = STATUS_ACTIVE;
-        escrow_data[OFF_FLAGS] = 0;
-        escrow_data[OFF_BUMP] = bump;
-        
-        drop(escrow_data);
+    if !accounts.iter().any(|a| a.key() == &callback) {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
 
-        // Transfer funds to escrow
-        let mut transfer_data = [0u8; 12];
-        transfer_data[0..4].copy_from_slice(&2u32.to_le_bytes());
-        transfer_data[4..12].copy_from_slice(&amount.to_le_bytes());
+    let callback_accounts = [AccountMeta::readonly(escrow.key())];
 
-        let transfer_accounts = [
-            AccountMeta::writable_signer(buyer.key()),
-            AccountMeta::writable(escrow.key()),
-        ];
+    let callback_ix = Instruction {
+        program_id: &callback,
+        accounts: &callback_accounts,
+        data: &data,
+    };
 
-        let transfer_ix = Instruction {
-            program_id: system_program.key(),
-            accounts: &transfer_accounts,
-            data: &transfer_data,
-        };
+    while_processing(escrow, || invoke_signed(&callback_ix, &[escrow], &[]))
+}
+
+// Lifecycle log for indexers, stable format: the event name ("pact:<event>"),
+// the escrow address, then the event's fields as one 64-bit log line. Fields
+// only ever get appended into unused slots, so existing readers keep working.
+fn log_event(event: &str, escrow: &Pubkey, fields: [u64; 5]) {
+    sol_log(event);
+    log_pubkey(escrow);
+    sol_log_64(fields[0], fields[1], fields[2], fields[3], fields[4]);
+}
+
+fn system_transfer(
+    from: &AccountInfo,
+    to: &AccountInfo,
+    system_program: &AccountInfo,
+    lamports: u64,
+) -> ProgramResult {
+    let mut transfer_data = [0u8; 12];
+    transfer_data[0..4].copy_from_slice(&2u32.to_le_bytes());
+    transfer_data[4..12].copy_from_slice(&lamports.to_le_bytes());
+
+    let transfer_accounts = [
+        AccountMeta::writable_signer(from.key()),
+        AccountMeta::writable(to.key()),
+    ];
+
+    let transfer_ix = Instruction {
+        program_id: system_program.key(),
+        accounts: &transfer_accounts,
+        data: &transfer_data,
+    };
+
+    invoke_signed::<2>(&transfer_ix, &[from, to], &[])
+}
+
+// Every lamport payout out of an escrow goes through here. The escrowed
+// amount sits on top of the rent deposit, so a payout must never eat into the
+// deposit: if an amount ever included rent, or the recorded reserve fell
+// behind today's rent, the escrow would be left below rent exemption. The
+// check uses today's minimum, as the runtime does, and fails with
+// EscrowNotRentExempt instead of a transaction the runtime rejects at the end.
+fn transfer_from_escrow(escrow: &AccountInfo, dest: &AccountInfo, amount: u64) -> ProgramResult {
+    sweep_from_escrow(escrow, dest, amount)?;
+    if escrow.lamports() < Rent::get()?.minimum_balance(escrow.data_len()) {
+        return Err(PactError::EscrowNotRentExempt.into());
+    }
+    Ok(())
+}
+
+// transfer_from_escrow without the rent check, for an escrow being closed in
+// the same instruction: its rent is what's being handed out
+fn sweep_from_escrow(escrow: &AccountInfo, dest: &AccountInfo, amount: u64) -> ProgramResult {
+    // Never credit a program account: a payout to the System Program or to
+    // Pact itself is a malformed account list, not a real recipient
+    if dest.key() == &SYSTEM_PROGRAM_ID || dest.key() == &crate::ID {
+        return Err(PactError::InvalidRecipient.into());
+    }
 
-        invoke_signed::<2>(&transfer_ix, &[buyer, escrow], &[])?;
+    // A recipient with no lamports doesn't exist yet. Crediting it less than
+    // the rent-exempt minimum would leave an account the runtime rejects, so
+    // such a recipient has to be funded first.
+    if dest.lamports() == 0 && amount > 0 && amount < Rent::get()?.minimum_balance(dest.data_len()) {
+        return Err(PactError::RecipientNotRentExempt.into());
+    }
 
+    while_processing(escrow, || unsafe {
+        let escrow_lamports = escrow.borrow_mut_lamports_unchecked();
+        let dest_lamports = dest.borrow_mut_lamports_unchecked();
+        *dest_lamports = dest_lamports.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
+        *escrow_lamports = escrow_lamports.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
         Ok(())
+    })
+}
+
+// Flags an event for a party's notification badge, if the escrow opted in
+fn mark_unseen(escrow_data: &mut [u8], bits: u8) {
+    if escrow_data[OFF_NOTIFY] & NOTIFY_ENABLED != 0 {
+        escrow_data[OFF_NOTIFY] |= bits;
     }
 }
 
-// ============================================================================
-// MarkDelivered
+// Wipes a settled escrow and sends everything left in it, rent included, to
+// the rent recipient chosen at creation: the buyer who paid it unless the
+// escrow says the seller or a treasury. Like the callback program, the
+// recipient is found among the instruction's accounts by its stored key. The
+// PDA ends empty and System-owned, so its seed can be reused. A token
+// escrow's vault is closed to the same recipient when it and its token
+// program are among the accounts too.
+fn close_to_rent_recipient(accounts: &[AccountInfo], escrow: &AccountInfo) -> ProgramResult {
+    let escrow_data = escrow.try_borrow_data()?;
+    let recipient_key = match escrow_data[OFF_RENT_RECIPIENT] {
+        RENT_TO_SELLER => read_pubkey(&escrow_data, OFF_SELLER),
+        RENT_TO_TREASURY => read_pubkey(&escrow_data, OFF_RENT_TREASURY),
+        _ => read_pubkey(&escrow_data, OFF_BUYER),
+    };
+    let recipient = accounts
+        .iter()
+        .find(|a| a.key() == &recipient_key)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    let token_escrow = is_token_escrow(&escrow_data);
+    drop(escrow_data);
+    if token_escrow {
+        close_vault(accounts, escrow, recipient)?;
+    }
+
+    let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+    // Wipe the data so nothing stale survives if the runtime keeps the buffer
+    escrow_data.fill(0);
+    drop(escrow_data);
+    sweep_from_escrow(escrow, recipient, escrow.lamports())?;
+
+    // Zeroes lamports, data length and owner (the System Program)
+    escrow.close()
+}
+
+// The seller's share when the buyer cancels: linear in the time since
+// created_at up to `cap` at `ramp` seconds, and never more than `amount`
+fn vested_cancel_fee(cap: u64, ramp: u64, elapsed: u64, amount: u64) -> u64 {
+    let vested = if ramp == 0 || elapsed >= ramp {
+        cap
+    } else {
+        (cap as u128 * elapsed as u128 / ramp as u128) as u64
+    };
+    vested.min(amount)
+}
+
+// The one place basis points turn into lamports: `amount * bps / 10_000`,
+// rounded down. Any bps-based share or fee must go through here and give the
+// remainder (`amount - apply_bps(..)`) to the principal recipient, so the
+// parts always reconcile to `amount`. bps above 10_000 is capped, which keeps
+// the result <= amount; the u128 product can't overflow.
+fn apply_bps(amount: u64, bps: u16) -> u64 {
+    let bps = (bps as u64).min(BPS_DENOMINATOR);
+    (amount as u128)
+        .checked_mul(bps as u128)
+        .and_then(|v| v.checked_div(BPS_DENOMINATOR as u128))
+        .unwrap_or(0) as u64
+}
+
+// SHA-256 of `data`, through the runtime's hashing syscall on-chain and the
+// sha2 crate in host builds
+fn sha256(data: &[u8]) -> [u8; 32] {
+    #[cfg(target_os = "solana")]
+    {
+        let mut hash = [0u8; 32];
+        let vals: [&[u8]; 1] = [data];
+        // SAFETY: `vals` is a valid slice of byte slices and `hash` has room
+        // for the 32-byte result
+        unsafe {
+            pinocchio::syscalls::sol_sha256(
+                vals.as_ptr() as *const u8,
+                vals.len() as u64,
+                hash.as_mut_ptr(),
+            );
+        }
+        hash
+    }
+
+    #[cfg(not(target_os = "solana"))]
+    {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(data).into()
+    }
+}
+
+// Opt-in proof that the deal being paid out is the one agreed: when the
+// settling party passes the raw terms, their SHA-256 must equal the stored
+// terms_hash. No terms, no check.
+fn assert_terms(escrow_data: &[u8], terms: &[u8]) -> ProgramResult {
+    if !terms.is_empty() && sha256(terms) != read_pubkey(escrow_data, OFF_TERMS_HASH) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    Ok(())
+}
+
+// Splits `amount` by the buyer's basis points. The buyer share rounds down and
+// the seller takes the remainder, so the two always add up to `amount`.
+// A ratified settlement withholds no fee. The platform fee only ever comes
+// out of what the seller is paid, never a refund; the arbitrator fee is the
+// one charge both sides bear, taken off the top of a ruling before it is
+// divided (see ruling_payout).
+fn split_amount(amount: u64, buyer_bps: u16) -> (u64, u64) {
+    let buyer_share = apply_bps(amount, buyer_bps);
+    (buyer_share, amount - buyer_share)
+}
+
+// Reads an arbitration ruling as the seller's basis points. The data is
+// either decision(1), 0 = refund and anything else = release, or
+// seller_bps(2) for a split, optionally followed by rationale_hash(32) in
+// both forms. Shared by Arbitrate and SimulateArbitrate.
+fn parse_ruling(data: &[u8]) -> Result<(u16, [u8; 32]), ProgramError> {
+    let (seller_bps, rest) = match data.len() {
+        2 | 34 => {
+            let bps = u16::from_le_bytes([data[0], data[1]]);
+            if bps as u64 > BPS_DENOMINATOR {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            (bps, &data[2..])
+        }
+        0 => return Err(ProgramError::InvalidInstructionData),
+        _ if data[0] == 0 => (0, &data[1..]),
+        _ => (BPS_DENOMINATOR as u16, &data[1..]),
+    };
+
+    // Absent or zeroes = no rationale
+    let mut rationale_hash = [0u8; 32];
+    if rest.len() >= 32 {
+        rationale_hash.copy_from_slice(&rest[..32]);
+    }
+    Ok((seller_bps, rationale_hash))
+}
+
+// The decision a ruling's seller_bps amounts to: 0 = refund, 1 = release,
+// DECISION_SPLIT otherwise
+fn ruling_decision(seller_bps: u16) -> u8 {
+    match seller_bps as u64 {
+        0 => 0,
+        BPS_DENOMINATOR => 1,
+        _ => DECISION_SPLIT,
+    }
+}
+
+// What an arbitration ruling pays out of `amount`, as (buyer side, seller,
+// arbitrator). The arbitrator fee comes off the top, capped at what is left,
+// and the seller takes `seller_bps` of the rest, rounded down, with the
+// remainder refunded. Pact holds no bond. Shared by Arbitrate and
+// SimulateArbitrate.
+fn ruling_payout(amount: u64, seller_bps: u16, arbitrator_fee: u64) -> (u64, u64, u64) {
+    let fee = arbitrator_fee.min(amount);
+    let seller_share = apply_bps(amount - fee, seller_bps);
+    (amount - fee - seller_share, seller_share, fee)
+}
+
+// The part of the stored arbitrator fee a ruling at `now` has earned. With an
+// arbitration window the fee decays linearly from the moment the arbitrator
+// may first rule (disputed_at + DISPUTE_RESPONSE_WINDOW_SECONDS) to the
+// arbitration deadline one window later:
+//
+//   earned = fee * (window - min(now - opens_at, window)) / window, rounded down
+//
+// A ruling as soon as it is allowed earns the whole fee; one at or after the
+// deadline earns nothing. The unearned rest stays in what ruling_payout
+// divides, so it goes to the side the ruling favours. Without a window, or
+// on a frozen escrow nobody disputed, the whole fee is due.
+fn earned_arbitrator_fee(escrow_data: &[u8], now: u64) -> u64 {
+    let fee = read_u64(escrow_data, OFF_ARBITRATOR_FEE);
+    let window = read_u64(escrow_data, OFF_ARBITRATION_WINDOW);
+    let disputed_at = read_u64(escrow_data, OFF_DISPUTED_AT);
+    if window == 0 || disputed_at == 0 {
+        return fee;
+    }
+    let opens_at = disputed_at.saturating_add(DISPUTE_RESPONSE_WINDOW_SECONDS);
+    let remaining = window - now.saturating_sub(opens_at).min(window);
+    (fee as u128 * remaining as u128 / window as u128) as u64
+}
+
+// Pays the seller `amount` less the escrow's platform fee, which goes to the
+// fee recipient, found among `accounts` by key. Returns the fee. Every
+// release the buyer (or its timeout) grants goes through here; refunds and
+// arbitration rulings don't, so they are never charged.
+fn pay_seller(
+    accounts: &[AccountInfo],
+    escrow: &AccountInfo,
+    seller: &AccountInfo,
+    amount: u64,
+) -> Result<u64, ProgramError> {
+    let escrow_data = escrow.try_borrow_data()?;
+    let fee_recipient = read_pubkey(&escrow_data, OFF_FEE_RECIPIENT);
+    let bps = u16::from_le_bytes([
+        escrow_data[OFF_PLATFORM_FEE_BPS],
+        escrow_data[OFF_PLATFORM_FEE_BPS + 1],
+    ]);
+    drop(escrow_data);
+
+    let fee = apply_bps(amount, bps);
+    if fee > 0 {
+        let recipient = accounts
+            .iter()
+            .find(|a| a.key() == &fee_recipient)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        transfer_from_escrow(escrow, recipient, fee)?;
+    }
+    transfer_from_escrow(escrow, seller, amount - fee)?;
+    Ok(fee)
+}
+
+// Refunds `refund` out of the escrowed `amount` to whoever put it in.
+// Single-funder escrows pay the buyer; pooled escrows return each funder's
+// pro-rata share (the whole contribution on a full refund) from the extra
+// `funder_accounts`, in list order (entry 0 is the buyer and is not repeated).
+// Rounding dust goes to the buyer.
+fn refund_funders(
+    escrow: &AccountInfo,
+    buyer: &AccountInfo,
+    funder_accounts: &[AccountInfo],
+    funders: &Funders,
+    amount: u64,
+    refund: u64,
+) -> ProgramResult {
+    if funders.count <= 1 || amount == 0 {
+        return transfer_from_escrow(escrow, buyer, refund);
+    }
+
+    let mut total: u64 = 0;
+    for contribution in &funders.amounts[..funders.count] {
+        total = total.checked_add(*contribution).ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+    if total != amount {
+        return Err(PactError::ContributionMismatch.into());
+    }
+
+    let mut paid: u64 = 0;
+    let entries = funders.keys.iter().zip(funders.amounts.iter()).take(funders.count);
+    for (i, (key, contribution)) in entries.enumerate().skip(1) {
+        let funder = funder_accounts.get(i - 1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if funder.key() != key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let share = (*contribution as u128 * refund as u128 / amount as u128) as u64;
+        transfer_from_escrow(escrow, funder, share)?;
+        paid += share;
+    }
+
+    transfer_from_escrow(escrow, buyer, refund - paid)
+}
+
+// ============================================================================
+// Token escrows
+// ============================================================================
+
+// A token escrow holds SPL tokens in a vault, the escrow PDA's associated
+// token account for the stored mint, instead of lamports in the escrow
+// itself. The escrow account then only carries its rent.
+fn is_token_escrow(escrow_data: &[u8]) -> bool {
+    escrow_data[OFF_MINT..OFF_MINT + 32] != [0u8; 32]
+}
+
+fn is_token_program(key: &Pubkey) -> bool {
+    key == &TOKEN_PROGRAM_ID || key == &TOKEN_2022_PROGRAM_ID
+}
+
+// The decimals of an initialized mint owned by `token_program`
+fn read_mint_decimals(mint: &AccountInfo, token_program: &Pubkey) -> Result<u8, ProgramError> {
+    if mint.owner() != token_program || mint.data_len() < MINT_SIZE {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data = mint.try_borrow_data()?;
+    if data[MINT_OFF_INITIALIZED] == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(data[MINT_OFF_DECIMALS])
+}
+
+// The balance of an initialized, unfrozen token account, after checking it
+// belongs to `token_program` and holds `mint` for `owner`
+fn token_account_balance(
+    account: &AccountInfo,
+    token_program: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<u64, ProgramError> {
+    if account.owner() != token_program || account.data_len() < TOKEN_ACCOUNT_SIZE {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let data = account.try_borrow_data()?;
+    if data[TOKEN_OFF_STATE] != TOKEN_STATE_INITIALIZED
+        || !pubkey_eq(&data, TOKEN_OFF_MINT, mint)
+        || !pubkey_eq(&data, TOKEN_OFF_OWNER, owner)
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(read_u64(&data, TOKEN_OFF_AMOUNT))
+}
+
+// The escrow's vault: its associated token account for `mint`
+fn derive_vault(escrow: &Pubkey, token_program: &Pubkey, mint: &Pubkey) -> Pubkey {
+    find_program_address(&[escrow, token_program, mint], &ASSOCIATED_TOKEN_PROGRAM_ID).0
+}
+
+// TransferChecked over `[source, mint, dest, authority]`
+fn transfer_checked(
+    accounts: [&AccountInfo; 4],
+    token_program: &Pubkey,
+    amount: u64,
+    decimals: u8,
+    signers: &[Signer],
+) -> ProgramResult {
+    let [source, mint, dest, authority] = accounts;
+
+    let mut transfer_data = [0u8; 10];
+    transfer_data[0] = TOKEN_IX_TRANSFER_CHECKED;
+    transfer_data[1..9].copy_from_slice(&amount.to_le_bytes());
+    transfer_data[9] = decimals;
+
+    let transfer_accounts = [
+        AccountMeta::writable(source.key()),
+        AccountMeta::readonly(mint.key()),
+        AccountMeta::writable(dest.key()),
+        AccountMeta::readonly_signer(authority.key()),
+    ];
+
+    let transfer_ix = Instruction {
+        program_id: token_program,
+        accounts: &transfer_accounts,
+        data: &transfer_data,
+    };
+
+    invoke_signed(&transfer_ix, &[source, mint, dest, authority], signers)
+}
+
+// A token payout out of the vault, checked before the handler writes the
+// settlement. The payout accounts are mint, vault, destination token account,
+// token program, starting at `start`; the destination must belong to
+// `recipient`.
+struct TokenPayout<'a> {
+    mint: &'a AccountInfo,
+    vault: &'a AccountInfo,
+    dest: &'a AccountInfo,
+    token_program: &'a Pubkey,
+    decimals: u8,
+    seeds: EscrowSeeds,
+}
+
+// The seeds the escrow PDA signs its token CPIs with. A reassigned escrow
+// still derives from its first seller.
+struct EscrowSeeds {
+    buyer: Pubkey,
+    seller: Pubkey,
+    seed: [u8; 8],
+    bump: [u8; 1],
+}
+
+impl EscrowSeeds {
+    fn read(escrow_data: &[u8]) -> Self {
+        let original_seller = read_pubkey(escrow_data, OFF_ORIGINAL_SELLER);
+        let seller = if original_seller != [0u8; 32] {
+            original_seller
+        } else {
+            read_pubkey(escrow_data, OFF_SELLER)
+        };
+        Self {
+            buyer: read_pubkey(escrow_data, OFF_BUYER),
+            seller,
+            seed: escrow_data[OFF_SEED..OFF_SEED + 8].try_into().unwrap(),
+            bump: [escrow_data[OFF_BUMP]],
+        }
+    }
+
+    fn as_seeds(&self) -> [Seed<'_>; 5] {
+        [
+            Seed::from(b"escrow".as_slice()),
+            Seed::from(self.buyer.as_ref()),
+            Seed::from(self.seller.as_ref()),
+            Seed::from(self.seed.as_ref()),
+            Seed::from(self.bump.as_ref()),
+        ]
+    }
+}
+
+impl<'a> TokenPayout<'a> {
+    fn parse(
+        accounts: &'a [AccountInfo],
+        start: usize,
+        escrow: &AccountInfo,
+        escrow_data: &[u8],
+        recipient: &Pubkey,
+        amount: u64,
+    ) -> Result<Self, ProgramError> {
+        let [mint, vault, dest, token_program] = match accounts.get(start..start + 4) {
+            Some([mint, vault, dest, token_program]) => [mint, vault, dest, token_program],
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+
+        let token_program = token_program.key();
+        if !is_token_program(token_program) || mint.owner() != token_program {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if !pubkey_eq(escrow_data, OFF_MINT, mint.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if vault.key() != &derive_vault(escrow.key(), token_program, mint.key()) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if token_account_balance(vault, token_program, mint.key(), escrow.key())? < amount {
+            return Err(PactError::BalanceMismatch.into());
+        }
+        token_account_balance(dest, token_program, mint.key(), recipient)?;
+
+        Ok(Self {
+            mint,
+            vault,
+            dest,
+            token_program,
+            decimals: escrow_data[OFF_MINT_DECIMALS],
+            seeds: EscrowSeeds::read(escrow_data),
+        })
+    }
+
+    // Moves `amount` from the vault to the destination, signed by the escrow PDA
+    fn pay(&self, escrow: &AccountInfo, amount: u64) -> ProgramResult {
+        let signer_seeds = self.seeds.as_seeds();
+        while_processing(escrow, || {
+            transfer_checked(
+                [self.vault, self.mint, self.dest, escrow],
+                self.token_program,
+                amount,
+                self.decimals,
+                &[Signer::from(&signer_seeds)],
+            )
+        })
+    }
+}
+
+// Closes a settled token escrow's vault, sending its rent to `recipient`.
+// The vault and its token program are found among `accounts` by key, and
+// both are required: once the escrow PDA is gone nothing can sign for the
+// vault, so closing the escrow alone would strand the vault's rent. A vault
+// holding stray tokens fails CloseAccount and keeps its escrow open with it.
+fn close_vault(accounts: &[AccountInfo], escrow: &AccountInfo, recipient: &AccountInfo) -> ProgramResult {
+    let token_program = accounts
+        .iter()
+        .find(|a| is_token_program(a.key()))
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let escrow_data = escrow.try_borrow_data()?;
+    let seeds = EscrowSeeds::read(&escrow_data);
+    let mint = read_pubkey(&escrow_data, OFF_MINT);
+    drop(escrow_data);
+
+    let vault_key = derive_vault(escrow.key(), token_program.key(), &mint);
+    let vault = accounts
+        .iter()
+        .find(|a| a.key() == &vault_key)
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+    let close_accounts = [
+        AccountMeta::writable(vault.key()),
+        AccountMeta::writable(recipient.key()),
+        AccountMeta::readonly_signer(escrow.key()),
+    ];
+    let close_ix = Instruction {
+        program_id: token_program.key(),
+        accounts: &close_accounts,
+        data: &[TOKEN_IX_CLOSE_ACCOUNT],
+    };
+
+    let signer_seeds = seeds.as_seeds();
+    while_processing(escrow, || {
+        invoke_signed(&close_ix, &[vault, recipient, escrow], &[Signer::from(&signer_seeds)])
+    })
+}
+
+// ============================================================================
+// CreateEscrowV2
+// ============================================================================
+
+// Parsed CreateEscrowV2 instruction data, after validation
+struct CreateParams {
+    amount: u64,
+    seed: u64,
+    timeout_seconds: u64,
+    terms_hash: [u8; 32],
+    appeal_arbitrator: Pubkey,
+    min_dispute_delay: u64,
+    timeout_mode: u8,
+    require_delivery_proof: bool,
+    price_oracle: Pubkey,
+    price_threshold: i64,
+    quote_mint: Pubkey,
+    quote_amount: u64,
+    milestone_count: u8,
+    milestone_amounts: [u64; MAX_MILESTONES],
+    callback_program: Pubkey,
+    refund_requires_no_delivery: bool,
+    acceptance_window_seconds: u64,
+    absolute_deadline: u64,
+    period_count: u16,
+    cancel_fee_cap: u64,
+    cancel_fee_ramp_seconds: u64,
+    require_arbitration_rationale: bool,
+    enable_notifications: bool,
+    seller_threshold: u8,
+    seller_signer_count: u8,
+    seller_signers: [Pubkey; MAX_SELLER_SIGNERS],
+    strict_acceptance: bool,
+    delivery_deadline: u64,
+    late_penalty_bps: u16,
+    disallow_partial: bool,
+    rent_recipient: u8,
+    rent_treasury: Pubkey,
+    releaser: Pubkey,
+    arbitrator_fee: u64,
+    fee_recipient: Pubkey,
+    platform_fee_bps: u16,
+    arbitration_window: u64,
+    mint: Pubkey,
+    mint_decimals: u8,
+    bump: u8,
+}
+
+// Every check CreateEscrowV2 makes before its CPIs, shared with DryRunCreate
+fn validate_create(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<CreateParams, ProgramError> {
+    // Accounts: buyer, seller, arbitrator, escrow, system_program
+    if accounts.len() < 5 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let buyer = &accounts[0];
+    let seller = &accounts[1];
+    let arbitrator = &accounts[2];
+    let escrow = &accounts[3];
+
+    assert_distinct(&[buyer.key(), seller.key(), arbitrator.key(), escrow.key()])?;
+
+    // Curated builds: the seller must be on the allowlist passed after
+    // system_program
+    #[cfg(feature = "seller-allowlist")]
+    {
+        let allowlist = accounts.get(5).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        assert_seller_allowed(program_id, allowlist, seller.key())?;
+    }
+
+    // Parse instruction data: amount(8) + seed(8) + timeout_seconds(8) + terms_hash(32) = 56 bytes
+    if data.len() < 56 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let seed = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let timeout_seconds = u64::from_le_bytes(data[16..24].try_into().unwrap());
+    let mut terms_hash = [0u8; 32];
+    terms_hash.copy_from_slice(&data[24..56]);
+
+    // Optional: appeal_arbitrator(32). Absent or zeroes = rulings are final.
+    let mut appeal_arbitrator = [0u8; 32];
+    if data.len() >= 88 {
+        appeal_arbitrator.copy_from_slice(&data[56..88]);
+    }
+
+    // Optional: min_dispute_delay_seconds(8). Inspection period after delivery
+    // during which neither party may dispute. Absent = 0.
+    let min_dispute_delay = if data.len() >= 96 {
+        u64::from_le_bytes(data[88..96].try_into().unwrap())
+    } else {
+        0
+    };
+
+    // Optional: timeout_mode(1), 0 = refund on timeout or 2 = freeze for the
+    // arbitrator. Absent = refund on timeout.
+    let timeout_mode = if data.len() >= 97 { data[96] } else { TIMEOUT_MODE_REFUND };
+
+    // Optional: require_delivery_proof(1). Non-zero = MarkDelivered must
+    // carry a proof hash. Absent = no requirement.
+    let require_delivery_proof = data.len() >= 98 && data[97] != 0;
+
+    // Optional: price_oracle(32) + price_threshold(8, i64). Enables
+    // ReleaseIfPrice; the threshold is in the oracle's raw price units
+    // (same exponent). Absent or zero oracle = no price condition.
+    let mut price_oracle = [0u8; 32];
+    let mut price_threshold: i64 = 0;
+    if data.len() >= 138 {
+        price_oracle.copy_from_slice(&data[98..130]);
+        price_threshold = i64::from_le_bytes(data[130..138].try_into().unwrap());
+    }
+
+    // Optional: quote_mint(32) + quote_amount(8). The price the deal was
+    // quoted in (e.g. USDC), kept for display and reconciliation only;
+    // settlement always uses the escrowed `amount`.
+    let mut quote_mint = [0u8; 32];
+    let mut quote_amount: u64 = 0;
+    if data.len() >= 178 {
+        quote_mint.copy_from_slice(&data[138..170]);
+        quote_amount = read_u64(data, 170);
+    }
+
+    // Optional: milestone_count(1) + amount(8) per milestone. The milestone
+    // amounts must add up to `amount`. Absent or 0 = a single delivery.
+    let milestone_count = if data.len() >= 179 { data[178] } else { 0 };
+    let mut milestone_amounts = [0u64; MAX_MILESTONES];
+    if milestone_count as usize > MAX_MILESTONES
+        || data.len() < 179 + milestone_count as usize * 8
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let mut milestone_total: u64 = 0;
+    for (i, milestone_amount) in milestone_amounts.iter_mut().take(milestone_count as usize).enumerate() {
+        *milestone_amount = read_u64(data, 179 + i * 8);
+        if *milestone_amount == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        milestone_total = milestone_total
+            .checked_add(*milestone_amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+    }
+
+    // Optional: callback_program(32), right after the milestone amounts. Pact
+    // CPIs into it when the escrow settles. Absent or zeroes = no callback.
+    let callback_offset = 179 + milestone_count as usize * 8;
+    let mut callback_program = [0u8; 32];
+    if data.len() >= callback_offset + 32 {
+        callback_program.copy_from_slice(&data[callback_offset..callback_offset + 32]);
+    }
+
+    // Optional: refund_requires_no_delivery(1), after the callback program.
+    // Non-zero = once the seller has delivered, the buyer can't refund on
+    // timeout and has to dispute instead. Absent = buyer may refund.
+    let refund_requires_no_delivery =
+        data.len() > callback_offset + 32 && data[callback_offset + 32] != 0;
+
+    // Optional: acceptance_window_seconds(8), after refund_requires_no_delivery.
+    // Once this long has passed since delivery without the buyer accepting or
+    // disputing, anyone can AutoRelease to the seller. Absent or 0 = never.
+    let acceptance_offset = callback_offset + 33;
+    let acceptance_window_seconds = if data.len() >= acceptance_offset + 8 {
+        read_u64(data, acceptance_offset)
+    } else {
+        0
+    };
+
+    // Optional: absolute_deadline(8), after the acceptance window. Non-zero =
+    // the escrow times out at this unix time instead of created_at +
+    // timeout_seconds, which must then be 0. Absent or 0 = relative timeout.
+    let deadline_offset = acceptance_offset + 8;
+    let absolute_deadline = if data.len() >= deadline_offset + 8 {
+        read_u64(data, deadline_offset)
+    } else {
+        0
+    };
+
+    // Optional: period_count(2), after the absolute deadline. Non-zero = a
+    // subscription: `amount` is split into equal periods of timeout_seconds,
+    // and RenewEscrow pays one period to the seller as each one ends. Every
+    // period is prepaid at create; nothing is pulled from the buyer later.
+    // Absent or 0 = a one-off deal.
+    let period_offset = deadline_offset + 8;
+    let period_count = if data.len() >= period_offset + 2 {
+        u16::from_le_bytes([data[period_offset], data[period_offset + 1]])
+    } else {
+        0
+    };
+
+    // Optional: cancel_fee_cap(8) + cancel_fee_ramp_seconds(8), after the
+    // period count. If the buyer cancels, the seller keeps a fee that grows
+    // linearly from 0 at created_at to the cap after the ramp (ramp 0 = the
+    // full cap at once). Non-zero cap = the buyer can't refund an Active
+    // escrow for free and has to CancelEscrow. Absent = no fee.
+    let cancel_fee_offset = period_offset + 2;
+    let mut cancel_fee_cap: u64 = 0;
+    let mut cancel_fee_ramp_seconds: u64 = 0;
+    if data.len() >= cancel_fee_offset + 16 {
+        cancel_fee_cap = read_u64(data, cancel_fee_offset);
+        cancel_fee_ramp_seconds = read_u64(data, cancel_fee_offset + 8);
+    }
+
+    // Optional: require_arbitration_rationale(1), after the cancellation fee.
+    // Non-zero = Arbitrate must carry a rationale hash. Absent = optional.
+    let require_arbitration_rationale =
+        data.len() > cancel_fee_offset + 16 && data[cancel_fee_offset + 16] != 0;
+
+    // Optional: enable_notifications(1), after require_arbitration_rationale.
+    // Non-zero = handlers record unseen events per party. Absent = off.
+    let enable_notifications =
+        data.len() > cancel_fee_offset + 17 && data[cancel_fee_offset + 17] != 0;
+
+    // Optional: seller_threshold(1) + seller_signer_count(1) +
+    // seller_signers(32 * count), after enable_notifications. A non-zero
+    // count makes the seller a multisig: seller actions need `threshold` of
+    // these members to sign. Absent or 0 = the seller key signs alone.
+    let multisig_offset = cancel_fee_offset + 18;
+    let mut seller_threshold: u8 = 0;
+    let mut seller_signer_count: u8 = 0;
+    let mut seller_signers = [[0u8; 32]; MAX_SELLER_SIGNERS];
+    if data.len() >= multisig_offset + 2 {
+        seller_threshold = data[multisig_offset];
+        seller_signer_count = data[multisig_offset + 1];
+        let count = seller_signer_count as usize;
+        if count > MAX_SELLER_SIGNERS || data.len() < multisig_offset + 2 + count * 32 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        for (i, signer) in seller_signers.iter_mut().enumerate().take(count) {
+            let offset = multisig_offset + 2 + i * 32;
+            signer.copy_from_slice(&data[offset..offset + 32]);
+        }
+    }
+
+    // Optional: strict_acceptance(1), after the seller signers. Non-zero =
+    // ReleaseV2 is off and funds only reach the seller once the buyer
+    // accepts a delivery. Absent = the buyer may release at any time.
+    let strict_offset = multisig_offset + 2 + seller_signer_count as usize * 32;
+    let strict_acceptance = data.len() > strict_offset && data[strict_offset] != 0;
+
+    // Optional: delivery_deadline(8) + late_penalty_bps(2), after
+    // strict_acceptance. If the seller delivers after the deadline (unix
+    // time), AcceptDelivery pays this share of the amount back to the buyer.
+    // Absent = no SLA penalty.
+    let penalty_offset = strict_offset + 1;
+    let mut delivery_deadline: u64 = 0;
+    let mut late_penalty_bps: u16 = 0;
+    if data.len() >= penalty_offset + 10 {
+        delivery_deadline = read_u64(data, penalty_offset);
+        late_penalty_bps = u16::from_le_bytes([data[penalty_offset + 8], data[penalty_offset + 9]]);
+    }
+
+    // Optional: disallow_partial(1), after the late penalty. Non-zero = the
+    // escrow settles all-or-nothing: PartialAcceptKeepOpen is off and
+    // PartialRelease must take the whole amount. Absent = partial payouts allowed.
+    let policy_offset = penalty_offset + 10;
+    let disallow_partial = data.len() > policy_offset && data[policy_offset] != 0;
+
+    // Optional: rent_recipient(1) [+ treasury(32) if 2], after
+    // disallow_partial. Who CloseEscrow and the *AndClose paths send the
+    // rent to: 0 = buyer, 1 = seller, 2 = the treasury key that follows.
+    // Absent = buyer.
+    let rent_offset = policy_offset + 1;
+    let rent_recipient = if data.len() > rent_offset { data[rent_offset] } else { RENT_TO_BUYER };
+    let mut rent_treasury = [0u8; 32];
+    if rent_recipient == RENT_TO_TREASURY {
+        if data.len() < rent_offset + 33 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        rent_treasury.copy_from_slice(&data[rent_offset + 1..rent_offset + 33]);
+    }
+
+    // Optional: releaser(32), after the rent recipient. A key that may sign
+    // ReleaseV2 in the buyer's place, e.g. a platform finalizing after
+    // off-chain confirmation. Absent or zeroes = the buyer alone.
+    let releaser_offset = rent_offset + if rent_recipient == RENT_TO_TREASURY { 33 } else { 1 };
+    let mut releaser = [0u8; 32];
+    if data.len() >= releaser_offset + 32 {
+        releaser.copy_from_slice(&data[releaser_offset..releaser_offset + 32]);
+    }
+
+    // Optional: arbitrator_fee(8), after the releaser. Lamports Arbitrate pays
+    // the arbitrator out of the escrow before the winner gets the rest.
+    // Absent = no fee.
+    let fee_offset = releaser_offset + 32;
+    let arbitrator_fee = if data.len() >= fee_offset + 8 { read_u64(data, fee_offset) } else { 0 };
+
+    // Optional: fee_recipient(32) + platform_fee_bps(2), after the arbitrator
+    // fee. The share of every release to the seller that goes to the
+    // recipient, e.g. the frontend's protocol fee. Refunds pay no fee.
+    // Absent = no platform fee.
+    let platform_offset = fee_offset + 8;
+    let mut fee_recipient = [0u8; 32];
+    let mut platform_fee_bps: u16 = 0;
+    if data.len() >= platform_offset + 34 {
+        fee_recipient.copy_from_slice(&data[platform_offset..platform_offset + 32]);
+        platform_fee_bps = u16::from_le_bytes([data[platform_offset + 32], data[platform_offset + 33]]);
+    }
+
+    // Optional: arbitration_window(8), after the platform fee. Seconds over
+    // which the arbitrator fee runs down to nothing once a dispute can be
+    // ruled on; see earned_arbitrator_fee. Absent or zero = the full fee on
+    // any ruling.
+    let window_offset = platform_offset + 34;
+    let arbitration_window =
+        if data.len() >= window_offset + 8 { read_u64(data, window_offset) } else { 0 };
+
+    // Validate
+    if !buyer.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // Optional accounts: mint, buyer_token, vault, token_program, after
+    // system_program (and the allowlist). Present = a token escrow: `amount`
+    // is in the mint's base units and moves from buyer_token into the vault,
+    // the escrow PDA's associated token account, which the client creates
+    // beforehand. Absent = native SOL.
+    let token_offset = 5 + cfg!(feature = "seller-allowlist") as usize;
+    let mut mint = [0u8; 32];
+    let mut mint_decimals = NATIVE_DECIMALS;
+    if let Some([mint_account, buyer_token, vault, token_program]) =
+        accounts.get(token_offset..token_offset + 4)
+    {
+        let token_program = token_program.key();
+        if !is_token_program(token_program) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        mint_decimals = read_mint_decimals(mint_account, token_program)?;
+        mint = *mint_account.key();
+        if token_account_balance(buyer_token, token_program, &mint, buyer.key())? < amount {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        if vault.key() != &derive_vault(escrow.key(), token_program, &mint) {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        token_account_balance(vault, token_program, &mint, escrow.key())?;
+
+        // Token payouts exist for ReleaseV2, RefundV2 and final rulings only,
+        // so options whose settlement goes through other paths are refused
+        if appeal_arbitrator != [0u8; 32]
+            || price_oracle != [0u8; 32]
+            || milestone_count > 0
+            || period_count > 0
+            || cancel_fee_cap > 0
+            || strict_acceptance
+            || acceptance_window_seconds > 0
+            || late_penalty_bps > 0
+            || arbitrator_fee > 0
+            || platform_fee_bps > 0
+        {
+            return Err(PactError::TokenEscrowUnsupported.into());
+        }
+    }
+
+    if amount == 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if timeout_mode != TIMEOUT_MODE_REFUND && timeout_mode != TIMEOUT_MODE_FREEZE {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if milestone_count > 0 && milestone_total != amount {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if absolute_deadline != 0
+        && (timeout_seconds != 0 || absolute_deadline <= unix_now(&Clock::get()?))
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    assert_timeout_fits(timeout_seconds)?;
+    // Periods are relative, equal, and the whole amount
+    if period_count > 0
+        && (timeout_seconds == 0
+            || absolute_deadline != 0
+            || milestone_count > 0
+            || amount % period_count as u64 != 0)
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if rent_recipient > RENT_TO_TREASURY
+        || (rent_recipient == RENT_TO_TREASURY
+            && (rent_treasury == [0u8; 32] || &rent_treasury == escrow.key()))
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // Like the arbitrator (see assert_distinct above), the appeal
+    // arbitrator can't be a party to the deal
+    if appeal_arbitrator != [0u8; 32]
+        && (&appeal_arbitrator == buyer.key() || &appeal_arbitrator == seller.key())
+    {
+        return Err(PactError::DuplicateAccount.into());
+    }
+    // The seller can't be its own releaser
+    if releaser != [0u8; 32] && (&releaser == seller.key() || &releaser == escrow.key()) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // Milestones and periods pay out in parts by design
+    if disallow_partial && (milestone_count > 0 || period_count > 0) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if cancel_fee_cap > amount {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // A fee needs an arbitrator to pay, and appealable rulings settle through
+    // ExecuteRuling and ArbitrateFinal, which don't charge one. A window only
+    // scales a fee.
+    if arbitrator_fee > amount
+        || (arbitrator_fee > 0
+            && (arbitrator.key() == &[0u8; 32] || appeal_arbitrator != [0u8; 32]))
+        || (arbitration_window > 0 && arbitrator_fee == 0)
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // A capped fee with somewhere to go. Milestone and period payouts don't
+    // charge it, so they can't be combined with one.
+    if platform_fee_bps > MAX_PLATFORM_FEE_BPS
+        || (platform_fee_bps > 0
+            && (fee_recipient == [0u8; 32]
+                || &fee_recipient == escrow.key()
+                || milestone_count > 0
+                || period_count > 0))
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if acceptance_window_seconds > MAX_ACCEPTANCE_WINDOW_SECONDS {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // A penalty needs a deadline and can't exceed the amount
+    if late_penalty_bps as u64 > BPS_DENOMINATOR || (late_penalty_bps > 0 && delivery_deadline == 0) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    // A usable threshold over distinct, non-zero members
+    if seller_signer_count > 0 || seller_threshold > 0 {
+        let members = &seller_signers[..seller_signer_count as usize];
+        if seller_threshold == 0 || seller_threshold > seller_signer_count {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        for (i, member) in members.iter().enumerate() {
+            if member == &[0u8; 32] || members[i + 1..].contains(member) {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+    }
+    // A frozen escrow can only be unlocked by the arbitrator
+    if timeout_mode == TIMEOUT_MODE_FREEZE && arbitrator.key() == &[0u8; 32] {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // Derive and validate PDA
+    let (expected_pda, bump) = derive_escrow(buyer.key(), seller.key(), seed, program_id);
+    if escrow.key() != &expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    // The create path needs a System-owned, empty account. Lamports sent to
+    // the address ahead of time are fine; CreateEscrowV2 allocates in place.
+    if escrow.owner() != &SYSTEM_PROGRAM_ID {
+        return Err(PactError::EscrowNotSystemOwned.into());
+    }
+    if escrow.data_len() != 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    Ok(CreateParams {
+        amount,
+        seed,
+        timeout_seconds,
+        terms_hash,
+        appeal_arbitrator,
+        min_dispute_delay,
+        timeout_mode,
+        require_delivery_proof,
+        price_oracle,
+        price_threshold,
+        quote_mint,
+        quote_amount,
+        milestone_count,
+        milestone_amounts,
+        callback_program,
+        refund_requires_no_delivery,
+        acceptance_window_seconds,
+        absolute_deadline,
+        period_count,
+        cancel_fee_cap,
+        cancel_fee_ramp_seconds,
+        require_arbitration_rationale,
+        enable_notifications,
+        seller_threshold,
+        seller_signer_count,
+        seller_signers,
+        strict_acceptance,
+        delivery_deadline,
+        late_penalty_bps,
+        disallow_partial,
+        rent_recipient,
+        rent_treasury,
+        releaser,
+        arbitrator_fee,
+        fee_recipient,
+        platform_fee_bps,
+        arbitration_window,
+        mint,
+        mint_decimals,
+        bump,
+    })
+}
+
+pub struct CreateEscrowV2;
+
+impl CreateEscrowV2 {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, seller, arbitrator, escrow, system_program, then
+        // optionally mint, buyer_token, vault, token_program (see validate_create)
+        if accounts.len() < 5 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let arbitrator = &accounts[2];
+        let escrow = &accounts[3];
+        let system_program = &accounts[4];
+
+        let CreateParams {
+            amount,
+            seed,
+            timeout_seconds,
+            terms_hash,
+            appeal_arbitrator,
+            min_dispute_delay,
+            timeout_mode,
+            require_delivery_proof,
+            price_oracle,
+            price_threshold,
+            quote_mint,
+            quote_amount,
+            milestone_count,
+            milestone_amounts,
+            callback_program,
+            refund_requires_no_delivery,
+            acceptance_window_seconds,
+            absolute_deadline,
+            period_count,
+            cancel_fee_cap,
+            cancel_fee_ramp_seconds,
+            require_arbitration_rationale,
+            enable_notifications,
+            seller_threshold,
+            seller_signer_count,
+            seller_signers,
+            strict_acceptance,
+            delivery_deadline,
+            late_penalty_bps,
+            disallow_partial,
+            rent_recipient,
+            rent_treasury,
+            releaser,
+            arbitrator_fee,
+            fee_recipient,
+            platform_fee_bps,
+            arbitration_window,
+            mint,
+            mint_decimals,
+            bump,
+        } = validate_create(program_id, accounts, data)?;
+
+        // Get current timestamp
+        let clock = Clock::get()?;
+        let created_at = unix_now(&clock);
+
+        // Calculate rent
+        let rent = Rent::get()?;
+        let lamports = rent.minimum_balance(ESCROW_SIZE);
+
+        // Create account
+        let bump_bytes = [bump];
+        let seed_bytes = seed.to_le_bytes();
+        let signer_seeds = [
+            Seed::from(b"escrow".as_slice()),
+            Seed::from(buyer.key().as_ref()),
+            Seed::from(seller.key().as_ref()),
+            Seed::from(seed_bytes.as_ref()),
+            Seed::from(bump_bytes.as_ref()),
+        ];
+        let signer = Signer::from(&signer_seeds);
+
+        if escrow.lamports() == 0 {
+            let mut create_data = [0u8; 52];
+            create_data[0..4].copy_from_slice(&0u32.to_le_bytes());
+            create_data[4..12].copy_from_slice(&lamports.to_le_bytes());
+            create_data[12..20].copy_from_slice(&(ESCROW_SIZE as u64).to_le_bytes());
+            create_data[20..52].copy_from_slice(program_id);
+
+            let create_accounts = [
+                AccountMeta::writable_signer(buyer.key()),
+                AccountMeta::writable_signer(escrow.key()),
+            ];
+
+            let create_ix = Instruction {
+                program_id: system_program.key(),
+                accounts: &create_accounts,
+                data: &create_data,
+            };
+
+            invoke_signed(&create_ix, &[buyer, escrow], &[signer])?;
+        } else {
+            // Someone already sent lamports to the PDA, so CreateAccount would
+            // fail: top up to rent, then Allocate and Assign in place
+            let top_up = lamports.saturating_sub(escrow.lamports());
+            if top_up > 0 {
+                system_transfer(buyer, escrow, system_program, top_up)?;
+            }
+
+            let escrow_meta = [AccountMeta::writable_signer(escrow.key())];
+
+            let mut allocate_data = [0u8; 12];
+            allocate_data[0..4].copy_from_slice(&8u32.to_le_bytes());
+            allocate_data[4..12].copy_from_slice(&(ESCROW_SIZE as u64).to_le_bytes());
+            let allocate_ix = Instruction {
+                program_id: system_program.key(),
+                accounts: &escrow_meta,
+                data: &allocate_data,
+            };
+            invoke_signed(&allocate_ix, &[escrow], core::slice::from_ref(&signer))?;
+
+            let mut assign_data = [0u8; 36];
+            assign_data[0..4].copy_from_slice(&1u32.to_le_bytes());
+            assign_data[4..36].copy_from_slice(program_id);
+            let assign_ix = Instruction {
+                program_id: system_program.key(),
+                accounts: &escrow_meta,
+                data: &assign_data,
+            };
+            invoke_signed(&assign_ix, &[escrow], &[signer])?;
+        }
+
+        // Initialize escrow data
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+        
+        write_u64(&mut escrow_data, OFF_DISC, ESCROW_DISC);
+        write_pubkey(&mut escrow_data, OFF_BUYER, buyer.key());
+        write_pubkey(&mut escrow_data, OFF_SELLER, seller.key());
+        write_pubkey(&mut escrow_data, OFF_ARBITRATOR, arbitrator.key());
+        // Zeroes for native SOL
+        write_pubkey(&mut escrow_data, OFF_MINT, &mint);
+        write_u64(&mut escrow_data, OFF_AMOUNT, amount);
+        write_u64(&mut escrow_data, OFF_FUNDED_AMOUNT, amount);
+        write_u64(&mut escrow_data, OFF_CREATED_AT, created_at);
+        escrow_data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&terms_hash);
+        escrow_data[OFF_STATUS] = EscrowStatus::Active.as_u8();
+        let mut flags = Flags::NONE;
+        if absolute_deadline != 0 {
+            flags.insert(Flags::ABSOLUTE_DEADLINE);
+            write_u64(&mut escrow_data, OFF_TIMEOUT, absolute_deadline);
+        } else {
+            write_u64(&mut escrow_data, OFF_TIMEOUT, timeout_seconds);
+        }
+        if require_delivery_proof {
+            flags.insert(Flags::REQUIRE_DELIVERY_PROOF);
+        }
+        if refund_requires_no_delivery {
+            flags.insert(Flags::REFUND_REQUIRES_NO_DELIVERY);
+        }
+        escrow_data[OFF_FLAGS] = flags.as_u8();
+        escrow_data[OFF_BUMP] = bump;
+        write_pubkey(&mut escrow_data, OFF_APPEAL_ARBITRATOR, &appeal_arbitrator);
+        escrow_data[OFF_RULING] = RULING_NONE;
+        write_u64(&mut escrow_data, OFF_RULED_AT, 0);
+        write_u64(&mut escrow_data, OFF_DELIVERED_AT, 0);
+        write_u64(&mut escrow_data, OFF_MIN_DISPUTE_DELAY, min_dispute_delay);
+        escrow_data[OFF_TIMEOUT_MODE] = timeout_mode;
+        escrow_data[OFF_FUNDER_COUNT] = 1;
+        write_pubkey(&mut escrow_data, OFF_FUNDERS, buyer.key());
+        write_u64(&mut escrow_data, OFF_FUNDERS + 32, amount);
+        write_pubkey(&mut escrow_data, OFF_DELIVERY_PROOF, &[0u8; 32]);
+        write_pubkey(&mut escrow_data, OFF_PRICE_ORACLE, &price_oracle);
+        write_u64(&mut escrow_data, OFF_PRICE_THRESHOLD, price_threshold as u64);
+        write_pubkey(&mut escrow_data, OFF_QUOTE_MINT, &quote_mint);
+        write_u64(&mut escrow_data, OFF_QUOTE_AMOUNT, quote_amount);
+        write_u64(&mut escrow_data, OFF_DISPUTED_AT, 0);
+        write_u64(&mut escrow_data, OFF_SEED, seed);
+        escrow_data[OFF_MILESTONE_COUNT] = milestone_count;
+        escrow_data[OFF_MILESTONE_DELIVERED] = 0;
+        escrow_data[OFF_MILESTONE_SETTLED] = 0;
+        for (i, milestone_amount) in milestone_amounts.iter().enumerate() {
+            write_u64(&mut escrow_data, OFF_MILESTONE_AMOUNTS + i * 8, *milestone_amount);
+        }
+        write_pubkey(&mut escrow_data, OFF_CALLBACK_PROGRAM, &callback_program);
+        write_u64(&mut escrow_data, OFF_ACCEPTANCE_WINDOW, acceptance_window_seconds);
+        escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2]
+            .copy_from_slice(&period_count.to_le_bytes());
+        let period_amount = if period_count > 0 { amount / period_count as u64 } else { 0 };
+        write_u64(&mut escrow_data, OFF_PERIOD_AMOUNT, period_amount);
+        write_u64(&mut escrow_data, OFF_CANCEL_FEE_CAP, cancel_fee_cap);
+        write_u64(&mut escrow_data, OFF_CANCEL_FEE_RAMP, cancel_fee_ramp_seconds);
+        escrow_data[OFF_REQUIRE_RATIONALE] = require_arbitration_rationale as u8;
+        escrow_data[OFF_NOTIFY] = if enable_notifications { NOTIFY_ENABLED } else { 0 };
+        escrow_data[OFF_SELLER_THRESHOLD] = seller_threshold;
+        escrow_data[OFF_SELLER_SIGNER_COUNT] = seller_signer_count;
+        for (i, signer) in seller_signers.iter().enumerate() {
+            write_pubkey(&mut escrow_data, OFF_SELLER_SIGNERS + i * 32, signer);
+        }
+        escrow_data[OFF_STRICT_ACCEPTANCE] = strict_acceptance as u8;
+        write_u64(&mut escrow_data, OFF_RENT_PAID, lamports);
+        write_u64(&mut escrow_data, OFF_DELIVERY_DEADLINE, delivery_deadline);
+        escrow_data[OFF_LATE_PENALTY_BPS..OFF_LATE_PENALTY_BPS + 2]
+            .copy_from_slice(&late_penalty_bps.to_le_bytes());
+        escrow_data[OFF_MINT_DECIMALS] = mint_decimals;
+        if disallow_partial {
+            escrow_data[OFF_POLICY] |= POLICY_DISALLOW_PARTIAL;
+        }
+        escrow_data[OFF_RENT_RECIPIENT] = rent_recipient;
+        write_pubkey(&mut escrow_data, OFF_RENT_TREASURY, &rent_treasury);
+        write_pubkey(&mut escrow_data, OFF_RELEASER, &releaser);
+        write_u64(&mut escrow_data, OFF_ARBITRATOR_FEE, arbitrator_fee);
+        write_pubkey(&mut escrow_data, OFF_FEE_RECIPIENT, &fee_recipient);
+        escrow_data[OFF_PLATFORM_FEE_BPS..OFF_PLATFORM_FEE_BPS + 2]
+            .copy_from_slice(&platform_fee_bps.to_le_bytes());
+        write_u64(&mut escrow_data, OFF_ARBITRATION_WINDOW, arbitration_window);
+        
+        drop(escrow_data);
+
+        if mint != [0u8; 32] {
+            // Token escrows fund the vault; validate_create checked the accounts
+            let token_offset = 5 + cfg!(feature = "seller-allowlist") as usize;
+            let [mint_account, buyer_token, vault, token_program] =
+                &accounts[token_offset..token_offset + 4]
+            else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            transfer_checked(
+                [buyer_token, mint_account, vault, buyer],
+                token_program.key(),
+                amount,
+                mint_decimals,
+                &[],
+            )?;
+        } else {
+            // Transfer funds to escrow
+            system_transfer(buyer, escrow, system_program, amount)?;
+        }
+
+        // Fields: amount, seed, created_at
+        log_event("pact:create", escrow.key(), [amount, seed, created_at, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// CreateEscrowBatch
+// ============================================================================
+
+// Most escrows one CreateEscrowBatch creates. Each is a create_account CPI,
+// a transfer and two more accounts, so eight stays well inside the compute
+// and account limits of a transaction.
+const MAX_BATCH_CREATE: usize = 8;
+
+// seller(32) + amount(8) + seed(8) + timeout_seconds(8) + terms_hash(32)
+const BATCH_CREATE_RECORD_LEN: usize = 88;
+
+pub struct CreateEscrowBatch;
+
+impl CreateEscrowBatch {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, system_program, then (seller, escrow) per record,
+        // then the seller allowlist in seller-allowlist builds
+        // The buyer creates and funds up to MAX_BATCH_CREATE escrows at once,
+        // each as CreateEscrowV2 with base terms and no arbitrator. Any
+        // failure fails the instruction, so either every escrow exists
+        // afterwards or none does.
+        let (&count, records) = data.split_first().ok_or(ProgramError::InvalidInstructionData)?;
+        let count = count as usize;
+        if count == 0 || count > MAX_BATCH_CREATE || records.len() != count * BATCH_CREATE_RECORD_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if accounts.len() < 2 + count * 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let system_program = &accounts[1];
+        #[cfg(feature = "seller-allowlist")]
+        let allowlist = accounts.get(2 + count * 2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        let groups = accounts[2..2 + count * 2].chunks_exact(2);
+        for (record, group) in records.chunks_exact(BATCH_CREATE_RECORD_LEN).zip(groups) {
+            let (seller, escrow) = (&group[0], &group[1]);
+            if !pubkey_eq(record, 0, seller.key()) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            // The system program's all-zero key is CreateEscrowV2's "no
+            // arbitrator"
+            #[cfg(not(feature = "seller-allowlist"))]
+            let create_accounts = [*buyer, *seller, *system_program, *escrow, *system_program];
+            #[cfg(feature = "seller-allowlist")]
+            let create_accounts =
+                [*buyer, *seller, *system_program, *escrow, *system_program, *allowlist];
+
+            CreateEscrowV2::process(program_id, &create_accounts, &record[32..])?;
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// MarkDelivered
+// ============================================================================
+
+pub struct MarkDelivered;
+
+impl MarkDelivered {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let seller = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[seller.key(), escrow.key()])?;
+
+        // Optional: delivery_proof_hash(32). Absent or zeroes = no proof.
+        let mut delivery_proof = [0u8; 32];
+        if data.len() >= 32 {
+            delivery_proof.copy_from_slice(&data[0..32]);
+        }
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        // Validate discriminator
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Validate seller
+        assert_seller_authority(seller, &escrow_data, accounts)?;
+
+        // Validate status
+        // Frozen escrows only move through the arbitrator
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        if !can_transition(read_status(&escrow_data)?, EscrowStatus::Delivered) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::REQUIRE_DELIVERY_PROOF)
+            && delivery_proof == [0u8; 32]
+        {
+            return Err(PactError::MissingDeliveryProof.into());
+        }
+
+        let clock = Clock::get()?;
+
+        // Update flags and status
+        write_pubkey(&mut escrow_data, OFF_DELIVERY_PROOF, &delivery_proof);
+        insert_flags(&mut escrow_data, Flags::SELLER_DELIVERED);
+        set_status(&mut escrow_data, EscrowStatus::Delivered)?;
+        write_u64(&mut escrow_data, OFF_DELIVERED_AT, unix_now(&clock));
+        mark_unseen(&mut escrow_data, UNSEEN_BUYER_DELIVERY);
+
+        // Fields: amount, delivered_at
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        log_event("pact:deliver", escrow.key(), [amount, unix_now(&clock), 0, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// AcceptDelivery
+// ============================================================================
+
+pub struct AcceptDelivery;
+
+impl AcceptDelivery {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        // Validate
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Frozen escrows only move through the arbitrator
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        if status != EscrowStatus::Delivered {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Optional: the raw terms, checked against terms_hash
+        assert_terms(&escrow_data, data)?;
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+
+        // SLA penalty: a late delivery pays the buyer back a share of the
+        // amount, rounded down, and the seller takes the rest
+        let deadline = read_u64(&escrow_data, OFF_DELIVERY_DEADLINE);
+        let delivered_at = read_u64(&escrow_data, OFF_DELIVERED_AT);
+        let penalty = if deadline != 0 && delivered_at > deadline {
+            let bps = u16::from_le_bytes([
+                escrow_data[OFF_LATE_PENALTY_BPS],
+                escrow_data[OFF_LATE_PENALTY_BPS + 1],
+            ]);
+            apply_bps(amount, bps)
+        } else {
+            0
+        };
+
+        // Update status. Accepted rather than Released records that the
+        // buyer signed off, as opposed to an automatic or forced payout
+        insert_flags(&mut escrow_data, Flags::BUYER_ACCEPTED);
+        set_status(&mut escrow_data, EscrowStatus::Accepted)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+
+        // Transfer funds to seller, less any late penalty to the buyer and
+        // the platform fee on what the seller is paid
+        transfer_from_escrow(escrow, buyer, penalty)?;
+        let fee = pay_seller(accounts, escrow, seller, amount - penalty)?;
+
+        #[cfg(feature = "seller-reputation")]
+        record_reputation(accounts, escrow, seller.key(), REPUTATION_OFF_COMPLETED)?;
+
+        notify_callback(accounts, escrow)?;
+
+        // Fields: amount paid to the seller, late penalty paid to the buyer,
+        // platform fee
+        log_event("pact:accept", escrow.key(), [amount - penalty - fee, penalty, fee, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ReleaseV2
+// ============================================================================
+
+pub struct ReleaseV2;
+
+impl ReleaseV2 {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: authority (buyer or the escrow's releaser), seller, escrow,
+        // then the fee recipient if the escrow charges a platform fee, or for
+        // a token escrow: mint, vault, seller_token, token_program
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let authority = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[authority.key(), seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let by_releaser = pubkey_eq(&escrow_data, OFF_RELEASER, authority.key())
+            && !pubkey_eq(&escrow_data, OFF_RELEASER, &[0u8; 32]);
+        if by_releaser {
+            assert_signer_at(authority, &escrow_data, OFF_RELEASER)?;
+        } else {
+            assert_signer_at(authority, &escrow_data, OFF_BUYER)?;
+        }
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Frozen escrows only move through the arbitrator
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        // Strict-acceptance escrows keep a delivery record: the seller gets
+        // paid through AcceptDelivery, never a bare release
+        if escrow_data[OFF_STRICT_ACCEPTANCE] != 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let status = read_status(&escrow_data)?;
+        // Can release from Active or Delivered; a dispute settles through the
+        // arbitrator and an accepted escrow is already paid
+        if status != EscrowStatus::Active && status != EscrowStatus::Delivered {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Optional: the raw terms, checked against terms_hash
+        assert_terms(&escrow_data, data)?;
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let token_payout = if is_token_escrow(&escrow_data) {
+            Some(TokenPayout::parse(accounts, 3, escrow, &escrow_data, seller.key(), amount)?)
+        } else {
+            assert_balance_covers(escrow, &escrow_data, amount)?;
+            None
+        };
+        set_status(&mut escrow_data, EscrowStatus::Released)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+        let fee = match token_payout {
+            Some(payout) => {
+                payout.pay(escrow, amount)?;
+                0
+            }
+            None => pay_seller(accounts, escrow, seller, amount)?,
+        };
+
+        #[cfg(feature = "seller-reputation")]
+        record_reputation(accounts, escrow, seller.key(), REPUTATION_OFF_COMPLETED)?;
+
+        notify_callback(accounts, escrow)?;
+
+        // Fields: amount, whether the escrow's releaser signed, platform fee
+        log_event("pact:release", escrow.key(), [amount, by_releaser as u64, fee, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// RefundV2
+// ============================================================================
+
+pub struct RefundV2;
+
+impl RefundV2 {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: authority, buyer, seller, escrow, then the extra funders,
+        // or for a token escrow: mint, vault, buyer_token, token_program
+        if accounts.len() < 4 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let authority = &accounts[0];
+        let buyer = &accounts[1];
+        let seller = &accounts[2];
+        let escrow = &accounts[3];
+
+        assert_distinct(&[authority.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !pubkey_eq(&escrow_data, OFF_BUYER, buyer.key())
+            || !pubkey_eq(&escrow_data, OFF_SELLER, seller.key())
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Frozen escrows only move through the arbitrator
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        if status.is_terminal() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let token_payout = if is_token_escrow(&escrow_data) {
+            Some(TokenPayout::parse(accounts, 4, escrow, &escrow_data, buyer.key(), amount)?)
+        } else {
+            assert_balance_covers(escrow, &escrow_data, amount)?;
+            None
+        };
+
+        // Check who can refund
+        let is_seller = pubkey_eq(&escrow_data, OFF_SELLER, authority.key());
+        let is_buyer = pubkey_eq(&escrow_data, OFF_BUYER, authority.key());
+        let is_arbitrator = pubkey_eq(&escrow_data, OFF_ARBITRATOR, authority.key());
+        if is_seller {
+            assert_seller_authority(authority, &escrow_data, accounts)?;
+        } else if !authority.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        let timeout_reached = timeout_reached(&escrow_data, now);
+        let flags = read_flags(&escrow_data);
+        let refund_on_timeout = escrow_data[OFF_TIMEOUT_MODE] == TIMEOUT_MODE_REFUND
+            && (!flags.intersects(Flags::REFUND_REQUIRES_NO_DELIVERY)
+                || !flags.intersects(Flags::SELLER_DELIVERED));
+
+        // Seller can always refund
+        // Buyer can refund if: timeout reached (refund mode only, and not after
+        // delivery if the escrow was created with refund_requires_no_delivery)
+        // OR status is Active (no delivery yet) and there's no cancellation fee
+        // Arbitrator can refund if disputed, unless rulings are appealable
+        // (then the refund must go through Arbitrate so it can be appealed)
+        let can_refund = is_seller 
+            || (is_buyer
+                && ((timeout_reached && refund_on_timeout)
+                    || (status == EscrowStatus::Active
+                        && read_u64(&escrow_data, OFF_CANCEL_FEE_CAP) == 0)))
+            || (is_arbitrator
+                && status == EscrowStatus::Disputed
+                && pubkey_eq(&escrow_data, OFF_APPEAL_ARBITRATOR, &[0u8; 32]));
+
+        if !can_refund {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // A buyer revoking a subscription gets back only the periods that
+        // haven't started: the one under way goes to the seller, and one that
+        // has already ended has to be paid out by RenewEscrow first
+        let periods_remaining = u16::from_le_bytes([
+            escrow_data[OFF_PERIODS_REMAINING],
+            escrow_data[OFF_PERIODS_REMAINING + 1],
+        ]);
+        let seller_share = if is_buyer && !is_seller && periods_remaining != 0 {
+            if timeout_reached {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            read_u64(&escrow_data, OFF_PERIOD_AMOUNT).min(amount)
+        } else {
+            0
+        };
+        let refund = amount - seller_share;
+
+        let funders = read_funders(&escrow_data);
+        set_status(&mut escrow_data, EscrowStatus::Refunded)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+        // Token escrows have a single funder: AddFunds only takes lamports.
+        // Subscriptions are lamport-only, so seller_share is 0 for tokens.
+        match token_payout {
+            Some(payout) => payout.pay(escrow, amount)?,
+            None => {
+                if seller_share > 0 {
+                    transfer_from_escrow(escrow, seller, seller_share)?;
+                }
+                refund_funders(escrow, buyer, &accounts[4..], &funders, amount, refund)?
+            }
+        }
+
+        notify_callback(accounts, escrow)?;
+
+        // Fields: amount, who refunded (0 = buyer, 1 = seller, 2 = arbitrator),
+        // the subscription period paid to the seller (out of amount)
+        let by = if is_buyer { 0 } else if is_seller { 1 } else { 2 };
+        log_event("pact:refund", escrow.key(), [amount, by, seller_share, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Dispute
+// ============================================================================
+
+pub struct Dispute;
+
+impl Dispute {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let authority = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[authority.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let is_buyer = pubkey_eq(&escrow_data, OFF_BUYER, authority.key());
+        let is_seller = pubkey_eq(&escrow_data, OFF_SELLER, authority.key());
+
+        if !is_buyer && !is_seller {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if is_seller {
+            assert_seller_authority(authority, &escrow_data, accounts)?;
+        } else if !authority.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Frozen escrows only move through the arbitrator
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        let responding = status == EscrowStatus::Disputed;
+        if !responding && !can_transition(status, EscrowStatus::Disputed) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Nothing left to fight over once the escrow has been drained
+        if read_u64(&escrow_data, OFF_AMOUNT) == 0 {
+            return Err(PactError::NothingToDispute.into());
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+
+        // The other party's response to an open dispute: their side is
+        // recorded next to it until the arbitrator rules. The dispute keeps
+        // its original disputed_at, so the response window doesn't restart.
+        if responding {
+            let own = if is_buyer { Flags::BUYER_DISPUTED } else { Flags::SELLER_DISPUTED };
+            if read_flags(&escrow_data).intersects(own) || escrow_data[OFF_RULING] != RULING_NONE {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            insert_flags(&mut escrow_data, own);
+            mark_unseen(
+                &mut escrow_data,
+                if is_buyer { UNSEEN_SELLER_DISPUTE } else { UNSEEN_BUYER_DISPUTE },
+            );
+
+            // Fields: party, responded_at, amount, 1 = response
+            let amount = read_u64(&escrow_data, OFF_AMOUNT);
+            log_event("pact:dispute", escrow.key(), [!is_buyer as u64, now, amount, 1, 0]);
+            return Ok(());
+        }
+
+        // After delivery, enforce the minimum inspection period
+        if status == EscrowStatus::Delivered {
+            let delivered_at = read_u64(&escrow_data, OFF_DELIVERED_AT);
+            let min_dispute_delay = read_u64(&escrow_data, OFF_MIN_DISPUTE_DELAY);
+            if now < delivered_at.saturating_add(min_dispute_delay) {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        // Set dispute flag
+        if is_buyer {
+            insert_flags(&mut escrow_data, Flags::BUYER_DISPUTED);
+            mark_unseen(&mut escrow_data, UNSEEN_SELLER_DISPUTE);
+        } else {
+            insert_flags(&mut escrow_data, Flags::SELLER_DISPUTED);
+            mark_unseen(&mut escrow_data, UNSEEN_BUYER_DISPUTE);
+        }
+        set_status(&mut escrow_data, EscrowStatus::Disputed)?;
+        write_u64(&mut escrow_data, OFF_DISPUTED_AT, now);
+
+        // SLA log. Fields: party (0 = buyer, 1 = seller), disputed_at, amount
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        log_event("pact:dispute", escrow.key(), [!is_buyer as u64, now, amount, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Arbitrate
+// ============================================================================
+
+pub struct Arbitrate;
+
+impl Arbitrate {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: arbitrator, buyer, seller, escrow, then the extra funders,
+        // or for a token escrow: mint, vault, winner_token, token_program
+        if accounts.len() < 4 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let arbitrator = &accounts[0];
+        let buyer = &accounts[1];
+        let seller = &accounts[2];
+        let escrow = &accounts[3];
+
+        assert_distinct(&[arbitrator.key(), buyer.key(), seller.key(), escrow.key()])?;
+
+        // The ruling: all to the buyer, all to the seller, or a split. The
+        // rationale_hash links it to an off-chain explanation.
+        let (seller_bps, rationale_hash) = parse_ruling(data)?;
+        let decision = ruling_decision(seller_bps);
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
+        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
+        let stored_arbitrator = read_pubkey(&escrow_data, OFF_ARBITRATOR);
+
+        assert_signer_role(arbitrator, &stored_arbitrator)?;
+        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Check arbitrator is not zero (no arbitrator set)
+        if stored_arbitrator == [0u8; 32] {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Resolves disputes, and frozen escrows awaiting review
+        assert_not_settled(&escrow_data)?;
+        let status = read_status(&escrow_data)?;
+        let frozen = read_flags(&escrow_data).intersects(Flags::FROZEN);
+        if status != EscrowStatus::Disputed && !frozen {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // A Disputed escrow must say who disputed it; don't move funds on an
+        // account whose status and flags disagree
+        if status == EscrowStatus::Disputed
+            && !read_flags(&escrow_data).intersects(Flags::DISPUTED)
+        {
+            return Err(PactError::DisputeFlagsMissing.into());
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+
+        // Escrows created with require_arbitration_rationale reject bare rulings
+        if escrow_data[OFF_REQUIRE_RATIONALE] != 0 && rationale_hash == [0u8; 32] {
+            return Err(PactError::MissingRationale.into());
+        }
+        write_pubkey(&mut escrow_data, OFF_RATIONALE_HASH, &rationale_hash);
+        mark_unseen(&mut escrow_data, UNSEEN_BUYER_RULING | UNSEEN_SELLER_RULING);
+
+        // A settlement proposal blocks a unilateral ruling until its ratify
+        // window runs out
+        let proposed_at = read_u64(&escrow_data, OFF_PROPOSED_AT);
+        if proposed_at != 0 && now < proposed_at.saturating_add(SETTLEMENT_RATIFY_WINDOW_SECONDS) {
+            return Err(PactError::SettlementPending.into());
+        }
+
+        // The other party gets the response window to file their side first.
+        // Frozen escrows nobody disputed have no one to hear from.
+        if status == EscrowStatus::Disputed {
+            let disputed_at = read_u64(&escrow_data, OFF_DISPUTED_AT);
+            if now < disputed_at.saturating_add(DISPUTE_RESPONSE_WINDOW_SECONDS) {
+                return Err(PactError::DisputeResponsePending.into());
+            }
+        }
+
+        // Surface who raised the dispute alongside the decision so audit
+        // tooling can compare rulings against the complaining party. A split
+        // sides with neither.
+        // Return data: decision(1) + dispute_flags(1) + sided_with_disputer(1) +
+        // rationale_hash(32) + seller_bps(2)
+        let dispute_flags = read_flags(&escrow_data).intersection(Flags::DISPUTED);
+        let sided_with_disputer = (decision == 0 && dispute_flags.intersects(Flags::BUYER_DISPUTED))
+            || (decision == 1 && dispute_flags.intersects(Flags::SELLER_DISPUTED));
+        if dispute_flags != Flags::NONE {
+            if decision == DECISION_SPLIT {
+                msg!("Arbitrate: split between the parties");
+            } else if sided_with_disputer {
+                msg!("Arbitrate: ruled for the disputing party");
+            } else {
+                msg!("Arbitrate: ruled against the disputing party");
+            }
+        }
+        let mut ret = [0u8; 37];
+        ret[0] = decision;
+        ret[1] = dispute_flags.as_u8();
+        ret[2] = sided_with_disputer as u8;
+        ret[3..35].copy_from_slice(&rationale_hash);
+        ret[35..37].copy_from_slice(&seller_bps.to_le_bytes());
+        set_return_data(&ret);
+
+        // A split pays both parties in lamports, and a pending ruling only
+        // has room for refund or release
+        let deferred = !pubkey_eq(&escrow_data, OFF_APPEAL_ARBITRATOR, &[0u8; 32]) && !frozen;
+        if decision == DECISION_SPLIT {
+            if is_token_escrow(&escrow_data) {
+                return Err(PactError::TokenEscrowUnsupported.into());
+            }
+            if deferred {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let (buyer_share, seller_share, fee) =
+            ruling_payout(amount, seller_bps, earned_arbitrator_fee(&escrow_data, now));
+
+        // SLA log. Fields: decision, resolved_at, disputed_at, amount, arbitrator_fee
+        let disputed_at = read_u64(&escrow_data, OFF_DISPUTED_AT);
+        log_event("pact:arbitrate", escrow.key(), [decision as u64, now, disputed_at, amount, fee]);
+        if decision == DECISION_SPLIT {
+            // Fields: seller_bps, refunded to the buyer side, paid to the seller
+            log_event(
+                "pact:arbitrate_split",
+                escrow.key(),
+                [seller_bps as u64, buyer_share, seller_share, 0, 0],
+            );
+        }
+
+        let token_payout = if is_token_escrow(&escrow_data) {
+            let winner = if decision == 0 { &stored_buyer } else { &stored_seller };
+            Some(TokenPayout::parse(accounts, 4, escrow, &escrow_data, winner, amount)?)
+        } else {
+            assert_balance_covers(escrow, &escrow_data, amount)?;
+            None
+        };
+        let funders = read_funders(&escrow_data);
+
+        // With an appeal arbitrator configured, a dispute ruling is only recorded
+        // here. It pays out via ExecuteRuling once the appeal window has passed.
+        // Rulings on frozen escrows are final.
+        if deferred {
+            if escrow_data[OFF_RULING] != RULING_NONE {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            escrow_data[OFF_RULING] = if decision == 0 { RULING_REFUND } else { RULING_RELEASE };
+            write_u64(&mut escrow_data, OFF_RULED_AT, now);
+            return Ok(());
+        }
+
+        // The fee is paid to the arbitrator's own account
+        if fee > 0 && !arbitrator.is_writable() {
+            return Err(ProgramError::Immutable);
+        }
+
+        if decision == 0 {
+            // Refund to buyer
+            set_status(&mut escrow_data, EscrowStatus::Refunded)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destination right at the transfer
+            if buyer.key() != &stored_buyer {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            // Never pay the arbitrator who made the ruling
+            if buyer.key() == &stored_arbitrator {
+                return Err(PactError::ArbitratorSelfPayout.into());
+            }
+            assert_settled(escrow)?;
+            match &token_payout {
+                Some(payout) => payout.pay(escrow, buyer_share)?,
+                None => refund_funders(escrow, buyer, &accounts[4..], &funders, amount, buyer_share)?,
+            }
+
+            #[cfg(feature = "seller-reputation")]
+            record_reputation(accounts, escrow, &stored_seller, REPUTATION_OFF_DISPUTED_AGAINST)?;
+        } else if decision == DECISION_SPLIT {
+            // Both parties are paid: the buyer side pro rata across funders,
+            // as in RatifySettlement, and the seller the rest
+            set_status(&mut escrow_data, EscrowStatus::Released)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destinations right at the transfer
+            if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            // Never pay the arbitrator who made the ruling
+            if buyer.key() == &stored_arbitrator || seller.key() == &stored_arbitrator {
+                return Err(PactError::ArbitratorSelfPayout.into());
+            }
+            assert_settled(escrow)?;
+            refund_funders(escrow, buyer, &accounts[4..], &funders, amount, buyer_share)?;
+            transfer_from_escrow(escrow, seller, seller_share)?;
+        } else {
+            // Release to seller
+            set_status(&mut escrow_data, EscrowStatus::Released)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destination right at the transfer
+            if seller.key() != &stored_seller {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            // Never pay the arbitrator who made the ruling
+            if seller.key() == &stored_arbitrator {
+                return Err(PactError::ArbitratorSelfPayout.into());
+            }
+            assert_settled(escrow)?;
+            match &token_payout {
+                Some(payout) => payout.pay(escrow, seller_share)?,
+                None => transfer_from_escrow(escrow, seller, seller_share)?,
+            }
+        }
+        if fee > 0 {
+            transfer_from_escrow(escrow, arbitrator, fee)?;
+        }
+
+        notify_callback(accounts, escrow)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// SimulateArbitrate
+// ============================================================================
+
+pub struct SimulateArbitrate;
+
+impl SimulateArbitrate {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: arbitrator, escrow
+        // Previews what Arbitrate would pay for a ruling, in the same data
+        // format, without changing anything. Needs no signature; the escrow
+        // isn't written.
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let arbitrator = &accounts[0];
+        let escrow = &accounts[1];
+
+        let (seller_bps, _) = parse_ruling(data)?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let escrow_data = escrow.try_borrow_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if pubkey_eq(&escrow_data, OFF_ARBITRATOR, &[0u8; 32])
+            || !pubkey_eq(&escrow_data, OFF_ARBITRATOR, arbitrator.key())
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Same gate as Arbitrate, less its waiting periods: a ruling can be
+        // previewed while a settlement or a dispute response is pending
+        assert_not_settled(&escrow_data)?;
+        let status = read_status(&escrow_data)?;
+        let frozen = read_flags(&escrow_data).intersects(Flags::FROZEN);
+        if status != EscrowStatus::Disputed && !frozen {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if status == EscrowStatus::Disputed
+            && !read_flags(&escrow_data).intersects(Flags::DISPUTED)
+        {
+            return Err(PactError::DisputeFlagsMissing.into());
+        }
+
+        // Appealable rulings are only recorded; ExecuteRuling pays them later
+        let deferred = !pubkey_eq(&escrow_data, OFF_APPEAL_ARBITRATOR, &[0u8; 32]) && !frozen;
+        // Arbitrate refuses the same splits
+        if ruling_decision(seller_bps) == DECISION_SPLIT {
+            if is_token_escrow(&escrow_data) {
+                return Err(PactError::TokenEscrowUnsupported.into());
+            }
+            if deferred {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let now = unix_now(&Clock::get()?);
+        let (buyer_share, seller_share, fee) =
+            ruling_payout(amount, seller_bps, earned_arbitrator_fee(&escrow_data, now));
+
+        // Return data: buyer_amount(8) + seller_amount(8) + arbitrator_fee(8) +
+        // bond_disposition(1) + deferred(1). The buyer amount is split pro rata
+        // across pooled funders. Pact holds no bond, so that is always 0.
+        let mut ret = [0u8; 26];
+        ret[0..8].copy_from_slice(&buyer_share.to_le_bytes());
+        ret[8..16].copy_from_slice(&seller_share.to_le_bytes());
+        ret[16..24].copy_from_slice(&fee.to_le_bytes());
+        ret[25] = deferred as u8;
+        set_return_data(&ret);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Appeal
+// ============================================================================
+//
+// Timing, when an escrow has an appeal arbitrator:
+//   1. Arbitrate records the ruling and `ruled_at`; no funds move.
+//   2. Until `ruled_at + APPEAL_WINDOW_SECONDS`, the losing party may Appeal.
+//      The escrow becomes EscrowStatus::Appealed and only ArbitrateFinal can settle it.
+//   3. From `ruled_at + APPEAL_WINDOW_SECONDS` on, Appeal is rejected and
+//      anyone may call ExecuteRuling to pay out the original ruling.
+
+pub struct Appeal;
+
+impl Appeal {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let authority = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[authority.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Frozen escrows only move through the arbitrator
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        let ruling = escrow_data[OFF_RULING];
+        if !can_transition(status, EscrowStatus::Appealed) || ruling == RULING_NONE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Only the losing party can appeal
+        if ruling == RULING_REFUND {
+            assert_seller_authority(authority, &escrow_data, accounts)?;
+        } else {
+            assert_signer_at(authority, &escrow_data, OFF_BUYER)?;
+        }
+
+        let ruled_at = read_u64(&escrow_data, OFF_RULED_AT);
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        if now >= ruled_at.saturating_add(APPEAL_WINDOW_SECONDS) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        set_status(&mut escrow_data, EscrowStatus::Appealed)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ArbitrateFinal
+// ============================================================================
+
+pub struct ArbitrateFinal;
+
+impl ArbitrateFinal {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        if accounts.len() < 4 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let appeal_arbitrator = &accounts[0];
+        let buyer = &accounts[1];
+        let seller = &accounts[2];
+        let escrow = &accounts[3];
+
+        assert_distinct(&[appeal_arbitrator.key(), buyer.key(), seller.key(), escrow.key()])?;
+
+        // Decision: 0 = refund, 1 = release
+        if data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let decision = data[0];
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
+        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
+        let stored_appeal_arbitrator = read_pubkey(&escrow_data, OFF_APPEAL_ARBITRATOR);
+
+        assert_signer_role(appeal_arbitrator, &stored_appeal_arbitrator)?;
+        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_not_settled(&escrow_data)?;
+        let status = read_status(&escrow_data)?;
+        if status != EscrowStatus::Appealed {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        mark_unseen(&mut escrow_data, UNSEEN_BUYER_RULING | UNSEEN_SELLER_RULING);
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        let funders = read_funders(&escrow_data);
+
+        if decision == 0 {
+            escrow_data[OFF_RULING] = RULING_REFUND;
+            set_status(&mut escrow_data, EscrowStatus::Refunded)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destination right at the transfer
+            if buyer.key() != &stored_buyer {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            // Never pay the arbitrator who made the ruling
+            if buyer.key() == &stored_appeal_arbitrator {
+                return Err(PactError::ArbitratorSelfPayout.into());
+            }
+            assert_settled(escrow)?;
+            refund_funders(escrow, buyer, &accounts[4..], &funders, amount, amount)?;
+
+            #[cfg(feature = "seller-reputation")]
+            record_reputation(accounts, escrow, &stored_seller, REPUTATION_OFF_DISPUTED_AGAINST)?;
+        } else {
+            escrow_data[OFF_RULING] = RULING_RELEASE;
+            set_status(&mut escrow_data, EscrowStatus::Released)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destination right at the transfer
+            if seller.key() != &stored_seller {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            // Never pay the arbitrator who made the ruling
+            if seller.key() == &stored_appeal_arbitrator {
+                return Err(PactError::ArbitratorSelfPayout.into());
+            }
+            assert_settled(escrow)?;
+            transfer_from_escrow(escrow, seller, amount)?;
+        }
+
+        notify_callback(accounts, escrow)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ExecuteRuling
+// ============================================================================
+
+pub struct ExecuteRuling;
+
+impl ExecuteRuling {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Permissionless: anyone can crank an unappealed ruling once the window closes
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
+        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
+        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_not_settled(&escrow_data)?;
+        let status = read_status(&escrow_data)?;
+        let ruling = escrow_data[OFF_RULING];
+        if status != EscrowStatus::Disputed || ruling == RULING_NONE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let ruled_at = read_u64(&escrow_data, OFF_RULED_AT);
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        if now < ruled_at.saturating_add(APPEAL_WINDOW_SECONDS) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        let funders = read_funders(&escrow_data);
+
+        if ruling == RULING_REFUND {
+            set_status(&mut escrow_data, EscrowStatus::Refunded)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destination right at the transfer
+            if buyer.key() != &stored_buyer {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            assert_settled(escrow)?;
+            refund_funders(escrow, buyer, &accounts[3..], &funders, amount, amount)?;
+
+            #[cfg(feature = "seller-reputation")]
+            record_reputation(accounts, escrow, &stored_seller, REPUTATION_OFF_DISPUTED_AGAINST)?;
+        } else {
+            set_status(&mut escrow_data, EscrowStatus::Released)?;
+            write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            drop(escrow_data);
+
+            // Re-check the payout destination right at the transfer
+            if seller.key() != &stored_seller {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            assert_settled(escrow)?;
+            transfer_from_escrow(escrow, seller, amount)?;
+        }
+
+        notify_callback(accounts, escrow)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// LookupEscrow
+// ============================================================================
+
+pub struct LookupEscrow;
+
+impl LookupEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: escrow
+        if accounts.is_empty() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let escrow = &accounts[0];
+
+        // Parse instruction data: buyer(32) + seller(32) + seed(8) = 72 bytes
+        if data.len() < 72 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let buyer = read_pubkey(data, 0);
+        let seller = read_pubkey(data, 32);
+        let seed = read_u64(data, 64);
+
+        let (expected_pda, _) = derive_escrow(&buyer, &seller, seed, program_id);
+        if escrow.key() != &expected_pda || escrow.data_len() != ESCROW_SIZE {
+            set_return_data(&[LOOKUP_NOT_FOUND]);
+            return Ok(());
+        }
+
+        let escrow_data = escrow.try_borrow_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            set_return_data(&[LOOKUP_NOT_FOUND]);
+            return Ok(());
+        }
+
+        // Return data: status(1) + flags(1) + amount(8) + quote_mint(32) +
+        // quote_amount(8) + seed(8) + acceptance_window_seconds(8) +
+        // auto_release_at(8), 0 until delivered or when there's no window +
+        // mint_decimals(1)
+        let acceptance_window = read_u64(&escrow_data, OFF_ACCEPTANCE_WINDOW);
+        let delivered_at = read_u64(&escrow_data, OFF_DELIVERED_AT);
+        let auto_release_at = if acceptance_window != 0 && delivered_at != 0 {
+            delivered_at.saturating_add(acceptance_window)
+        } else {
+            0
+        };
+        let mut ret = [0u8; 75];
+        ret[0] = escrow_data[OFF_STATUS];
+        ret[1] = escrow_data[OFF_FLAGS];
+        ret[2..10].copy_from_slice(&escrow_data[OFF_AMOUNT..OFF_AMOUNT + 8]);
+        ret[10..50].copy_from_slice(&escrow_data[OFF_QUOTE_MINT..OFF_QUOTE_AMOUNT + 8]);
+        ret[50..58].copy_from_slice(&escrow_data[OFF_SEED..OFF_SEED + 8]);
+        ret[58..66].copy_from_slice(&acceptance_window.to_le_bytes());
+        ret[66..74].copy_from_slice(&auto_release_at.to_le_bytes());
+        ret[74] = escrow_data[OFF_MINT_DECIMALS];
+        set_return_data(&ret);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// FreezeOnTimeout
+// ============================================================================
+
+pub struct FreezeOnTimeout;
+
+impl FreezeOnTimeout {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Permissionless: anyone can freeze an expired escrow in freeze mode.
+        // Until then the parties may still settle normally, except that the
+        // buyer's timeout refund is disabled in this mode.
+        if accounts.is_empty() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let escrow = &accounts[0];
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if escrow_data[OFF_TIMEOUT_MODE] != TIMEOUT_MODE_FREEZE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        if status.is_terminal() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        if !timeout_reached(&escrow_data, now) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        insert_flags(&mut escrow_data, Flags::FROZEN);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// ResizeEscrow
+// ============================================================================
+
+pub struct ResizeEscrow;
+
+impl ResizeEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Brings an escrow created under an older layout to ESCROW_SIZE. The
+        // buyer paid the original rent, so the buyer covers any increase and
+        // receives any rent freed by a smaller layout.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let escrow = &accounts[1];
+        let system_program = &accounts[2];
+
+        assert_distinct(&[buyer.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let escrow_data = escrow.try_borrow_data()?;
+
+        // A v1 escrow shares the discriminator but none of the fields past
+        // the seller; MigrateV1ToV2 converts it instead
+        if escrow_data.len() == V1_ESCROW_SIZE {
+            return Err(PactError::InvalidEscrowSize.into());
+        }
+
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let status = read_status(&escrow_data)?;
+        drop(escrow_data);
+
+        if escrow.data_len() == ESCROW_SIZE {
+            return Ok(());
+        }
+
+        escrow.resize(ESCROW_SIZE)?;
+
+        let settled = status.is_terminal();
+
+        // Older layouts have no funded_amount: backfill it from amount, and
+        // settled escrows adopt the zero-remaining meaning of amount
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+        if read_u64(&escrow_data, OFF_FUNDED_AMOUNT) == 0 {
+            write_u64(&mut escrow_data, OFF_FUNDED_AMOUNT, amount);
+            if settled {
+                write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+            }
+        }
+        // Every escrow before mint_decimals was native SOL
+        if escrow_data[OFF_MINT_DECIMALS] == 0 {
+            escrow_data[OFF_MINT_DECIMALS] = NATIVE_DECIMALS;
+        }
+        drop(escrow_data);
+
+        let rent = Rent::get()?;
+        let required = rent.minimum_balance(ESCROW_SIZE);
+
+        // Settled escrows no longer hold the escrowed amount
+        let held = if settled { 0 } else { amount };
+        let rent_balance = escrow
+            .lamports()
+            .checked_sub(held)
+            .ok_or(ProgramError::InsufficientFunds)?;
+
+        if rent_balance < required {
+            // Grew: buyer tops up rent for the new size
+            system_transfer(buyer, escrow, system_program, required - rent_balance)?;
+        } else if rent_balance > required {
+            // Shrank: sweep rent above the new minimum back to the buyer,
+            // never touching the escrowed funds or the rent the new size needs
+            transfer_from_escrow(escrow, buyer, rent_balance - required)?;
+        }
+
+        // The reserve is now exactly the new size's rent
+        write_u64(&mut escrow.try_borrow_mut_data()?, OFF_RENT_PAID, required);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// MigrateV1ToV2
+// ============================================================================
+
+pub struct MigrateV1ToV2;
+
+impl MigrateV1ToV2 {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, escrow, system_program
+        // Rewrites a v1 escrow in place as a v2 escrow with the same parties,
+        // amount and outcome, so it can use v2 handlers without closing and
+        // recreating it. Only possible where the escrow is owned by the program
+        // running this, i.e. one deployment serving both layouts. The buyer
+        // pays the rent for the larger account.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let escrow = &accounts[1];
+        let system_program = &accounts[2];
+
+        assert_distinct(&[buyer.key(), escrow.key()])?;
+
+        // Parse instruction data: seed(8). v1 never stored it, and the PDA
+        // check below recovers the bump from it.
+        if data.len() < 8 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let seed = read_u64(data, 0);
+
+        assert_escrow_owner(escrow, program_id)?;
+        let escrow_data = escrow.try_borrow_data()?;
+
+        if escrow_data.len() != V1_ESCROW_SIZE {
+            return Err(PactError::InvalidEscrowSize.into());
+        }
+        if read_u64(&escrow_data, OFF_DISC) != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, V1_OFF_BUYER)?;
+        let seller = read_pubkey(&escrow_data, V1_OFF_SELLER);
+
+        let v1_amount = read_u64(&escrow_data, V1_OFF_AMOUNT);
+        let status = match escrow_data[V1_OFF_STATUS] {
+            V1_STATUS_ACTIVE => EscrowStatus::Active,
+            V1_STATUS_RELEASED => EscrowStatus::Released,
+            V1_STATUS_REFUNDED => EscrowStatus::Refunded,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        drop(escrow_data);
+
+        let (expected_pda, bump) = derive_escrow(buyer.key(), &seller, seed, program_id);
+        if escrow.key() != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        escrow.resize(ESCROW_SIZE)?;
+
+        // Settled v1 escrows still record what they paid out; in v2 that's
+        // funded_amount, and amount is what's left
+        let settled = status.is_terminal();
+        let amount = if settled { 0 } else { v1_amount };
+        let now = unix_now(&Clock::get()?);
+
+        // Discriminator, buyer and seller stay where they are. Everything not
+        // set here reads as its default: no arbitrator, native SOL, no
+        // timeout (v1 had none, and a default would hand the buyer a refund
+        // right they never had), rent back to the buyer
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+        escrow_data[V1_OFF_AMOUNT..].fill(0);
+        write_u64(&mut escrow_data, OFF_AMOUNT, amount);
+        write_u64(&mut escrow_data, OFF_CREATED_AT, now);
+        escrow_data[OFF_STATUS] = status.as_u8();
+        escrow_data[OFF_BUMP] = bump;
+        escrow_data[OFF_TIMEOUT_MODE] = TIMEOUT_MODE_REFUND;
+        escrow_data[OFF_FUNDER_COUNT] = 1;
+        write_pubkey(&mut escrow_data, OFF_FUNDERS, buyer.key());
+        write_u64(&mut escrow_data, OFF_FUNDERS + 32, amount);
+        write_u64(&mut escrow_data, OFF_FUNDED_AMOUNT, v1_amount);
+        write_u64(&mut escrow_data, OFF_SEED, seed);
+        write_u64(&mut escrow_data, OFF_UPDATED_AT, now);
+        escrow_data[OFF_MINT_DECIMALS] = NATIVE_DECIMALS;
+        escrow_data[OFF_RENT_RECIPIENT] = RENT_TO_BUYER;
+        drop(escrow_data);
+
+        // Same rent settlement as ResizeEscrow
+        let required = Rent::get()?.minimum_balance(ESCROW_SIZE);
+        let rent_balance = escrow
+            .lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        if rent_balance < required {
+            system_transfer(buyer, escrow, system_program, required - rent_balance)?;
+        } else if rent_balance > required {
+            transfer_from_escrow(escrow, buyer, rent_balance - required)?;
+        }
+        write_u64(&mut escrow.try_borrow_mut_data()?, OFF_RENT_PAID, required);
+
+        // Fields: status, amount, funded amount, seed
+        log_event(
+            "pact:migrate_v1",
+            escrow.key(),
+            [status.as_u8() as u64, amount, v1_amount, seed, 0],
+        );
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// AddFunds
+// ============================================================================
+
+pub struct AddFunds;
+
+impl AddFunds {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: funder, escrow, system_program
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let funder = &accounts[0];
+        let escrow = &accounts[1];
+        let system_program = &accounts[2];
+
+        assert_distinct(&[funder.key(), escrow.key()])?;
+
+        if !funder.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Parse instruction data: amount(8)
+        if data.len() < 8 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let contribution = read_u64(data, 0);
+        if contribution == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        if status != EscrowStatus::Active && status != EscrowStatus::Delivered {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Milestone and period amounts are fixed at creation and must keep
+        // summing to amount
+        if escrow_data[OFF_MILESTONE_COUNT] != 0
+            || escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2] != [0, 0]
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // Contributions are lamports
+        if is_token_escrow(&escrow_data) {
+            return Err(PactError::TokenEscrowUnsupported.into());
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let new_amount = amount.checked_add(contribution).ok_or(ProgramError::ArithmeticOverflow)?;
+
+        let mut funders = read_funders(&escrow_data);
+
+        // Escrows resized from before pooled funding have no entries yet
+        if funders.count == 0 {
+            funders.keys[0] = read_pubkey(&escrow_data, OFF_BUYER);
+            funders.amounts[0] = amount;
+            funders.count = 1;
+        }
+
+        let index = match funders.keys[..funders.count].iter().position(|k| k == funder.key()) {
+            Some(i) => i,
+            None if funders.count < MAX_FUNDERS => {
+                funders.keys[funders.count] = *funder.key();
+                funders.count += 1;
+                funders.count - 1
+            }
+            None => return Err(PactError::TooManyFunders.into()),
+        };
+        funders.amounts[index] = funders.amounts[index]
+            .checked_add(contribution)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        escrow_data[OFF_FUNDER_COUNT] = funders.count as u8;
+        for i in 0..funders.count {
+            let offset = OFF_FUNDERS + i * FUNDER_ENTRY_SIZE;
+            write_pubkey(&mut escrow_data, offset, &funders.keys[i]);
+            write_u64(&mut escrow_data, offset + 32, funders.amounts[i]);
+        }
+        write_u64(&mut escrow_data, OFF_AMOUNT, new_amount);
+        let funded = read_u64(&escrow_data, OFF_FUNDED_AMOUNT)
+            .checked_add(contribution)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        write_u64(&mut escrow_data, OFF_FUNDED_AMOUNT, funded);
+        drop(escrow_data);
+
+        system_transfer(funder, escrow, system_program, contribution)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// TopUp
+// ============================================================================
+
+pub struct TopUp;
+
+impl TopUp {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, escrow, system_program
+        // AddFunds for the escrow's own buyer only, when an order grows after
+        // creation. Same data, checks and bookkeeping: amount and
+        // funded_amount go up, and so does the buyer's funder entry.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_escrow_owner(escrow, program_id)?;
+        {
+            let escrow_data = escrow.try_borrow_data()?;
+            assert_escrow_size(&escrow_data)?;
+            assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        }
+
+        AddFunds::process(program_id, accounts, data)
+    }
+}
+
+// ============================================================================
+// ReleaseIfPrice
+// ============================================================================
+
+pub struct ReleaseIfPrice;
+
+impl ReleaseIfPrice {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: seller, escrow, price_oracle, then the fee recipient if
+        // the escrow charges a platform fee
+        // Permissionless: the stored oracle condition decides, not the caller.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let seller = &accounts[0];
+        let escrow = &accounts[1];
+        let price_oracle = &accounts[2];
+
+        assert_distinct(&[seller.key(), escrow.key(), price_oracle.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if pubkey_eq(&escrow_data, OFF_PRICE_ORACLE, &[0u8; 32])
+            || !pubkey_eq(&escrow_data, OFF_PRICE_ORACLE, price_oracle.key())
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        let status = read_status(&escrow_data)?;
+        if status != EscrowStatus::Active && status != EscrowStatus::Delivered {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let price = read_oracle_price(price_oracle)?;
+        let threshold = read_u64(&escrow_data, OFF_PRICE_THRESHOLD) as i64;
+        if price < threshold {
+            return Err(PactError::PriceBelowThreshold.into());
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+
+        set_status(&mut escrow_data, EscrowStatus::Released)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+        pay_seller(accounts, escrow, seller, amount)?;
+
+        notify_callback(accounts, escrow)?;
+
+        Ok(())
+    }
+}
+
+// Reads the aggregate price from a Pyth v2 price account, rejecting anything
+// that isn't a price account, isn't currently trading, or was last published
+// more than MAX_ORACLE_AGE_SLOTS ago. The price is the raw mantissa: callers
+// compare it against a threshold in the feed's own exponent units, so a
+// threshold of 2_500_000 on an expo -5 feed means 25.0.
+fn read_oracle_price(oracle: &AccountInfo) -> Result<i64, ProgramError> {
+    let data = oracle.try_borrow_data()?;
+    if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let read_u32 = |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    if read_u32(PYTH_OFF_MAGIC) != PYTH_MAGIC || read_u32(PYTH_OFF_ATYPE) != PYTH_ATYPE_PRICE {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if read_u32(PYTH_OFF_AGG_STATUS) != PYTH_STATUS_TRADING {
+        return Err(PactError::OraclePriceUnavailable.into());
+    }
+    let published_slot = read_u64(&data, PYTH_OFF_AGG_PUB_SLOT);
+    if Clock::get()?.slot.saturating_sub(published_slot) > MAX_ORACLE_AGE_SLOTS {
+        return Err(PactError::StaleOraclePrice.into());
+    }
+
+    Ok(read_u64(&data, PYTH_OFF_AGG_PRICE) as i64)
+}
+
+// ============================================================================
+// ProposeSettlement
+// ============================================================================
+
+pub struct ProposeSettlement;
+
+impl ProposeSettlement {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: arbitrator, escrow
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let arbitrator = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[arbitrator.key(), escrow.key()])?;
+
+        // Parse instruction data: buyer_bps(2)
+        if data.len() < 2 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let buyer_bps = u16::from_le_bytes([data[0], data[1]]);
+        if buyer_bps as u64 > BPS_DENOMINATOR {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if pubkey_eq(&escrow_data, OFF_ARBITRATOR, &[0u8; 32]) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        assert_signer_at(arbitrator, &escrow_data, OFF_ARBITRATOR)?;
+
+        assert_not_settled(&escrow_data)?;
+        if read_status(&escrow_data)? != EscrowStatus::Disputed
+            || escrow_data[OFF_RULING] != RULING_NONE
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // A new proposal replaces any earlier one and restarts the window
+        let clock = Clock::get()?;
+        escrow_data[OFF_PROPOSED_BUYER_BPS..OFF_PROPOSED_BUYER_BPS + 2]
+            .copy_from_slice(&buyer_bps.to_le_bytes());
+        write_u64(&mut escrow_data, OFF_PROPOSED_AT, unix_now(&clock));
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// RatifySettlement
+// ============================================================================
+
+pub struct RatifySettlement;
+
+impl RatifySettlement {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow, then any pooled funders after the buyer
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        assert_seller_authority(seller, &escrow_data, accounts)?;
+
+        assert_not_settled(&escrow_data)?;
+        if read_status(&escrow_data)? != EscrowStatus::Disputed {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let proposed_at = read_u64(&escrow_data, OFF_PROPOSED_AT);
+        if proposed_at == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        if now >= proposed_at.saturating_add(SETTLEMENT_RATIFY_WINDOW_SECONDS) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let buyer_bps = u16::from_le_bytes([
+            escrow_data[OFF_PROPOSED_BUYER_BPS],
+            escrow_data[OFF_PROPOSED_BUYER_BPS + 1],
+        ]);
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        let funders = read_funders(&escrow_data);
+
+        let (buyer_share, seller_share) = split_amount(amount, buyer_bps);
+
+        // A split that returns everything counts as a refund, anything else
+        // as a release
+        let settled = if buyer_share == amount {
+            EscrowStatus::Refunded
+        } else {
+            EscrowStatus::Released
+        };
+        set_status(&mut escrow_data, settled)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        write_u64(&mut escrow_data, OFF_PROPOSED_AT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+        refund_funders(escrow, buyer, &accounts[3..], &funders, amount, buyer_share)?;
+        transfer_from_escrow(escrow, seller, seller_share)?;
+
+        notify_callback(accounts, escrow)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// DryRunCreate
+// ============================================================================
+
+pub struct DryRunCreate;
+
+impl DryRunCreate {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Same accounts and data as CreateEscrowV2. Runs its validation without
+        // creating or funding anything, meant to be simulated before the real
+        // create. Return data: error code as u64 LE, 0 = the create would pass
+        // validation.
+        let code = match validate_create(program_id, accounts, data) {
+            Ok(_) => 0,
+            Err(e) => u64::from(e),
+        };
+        set_return_data(&code.to_le_bytes());
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// CloseEscrow
+// ============================================================================
+
+pub struct CloseEscrow;
+
+impl CloseEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: buyer, escrow, [keeper], [rent recipient], [vault,
+        // token_program]
+        // Returns a settled escrow's rent to its rent recipient and leaves the
+        // PDA empty and System-owned, so CreateEscrowV2 can reuse the same
+        // seed. The recipient account is only needed when it isn't the buyer;
+        // the vault accounts only for a token escrow, whose vault is closed
+        // with it.
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[buyer.key(), escrow.key()])?;
+
+        // With a closer reward, anyone may close in the buyer's place and keeps
+        // the reward; a buyer closing their own escrow gets all the rent back
+        let keeper = if buyer.is_signer() || CLOSER_REWARD_LAMPORTS == 0 {
+            None
+        } else {
+            let keeper = accounts.get(2).ok_or(ProgramError::MissingRequiredSignature)?;
+            assert_distinct(&[buyer.key(), escrow.key(), keeper.key()])?;
+            Some(keeper)
+        };
+
+        if !buyer.is_signer() && !keeper.is_some_and(|k| k.is_signer()) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        assert_escrow_owner(escrow, program_id)?;
+
+        let escrow_data = escrow.try_borrow_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !pubkey_eq(&escrow_data, OFF_BUYER, buyer.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let status = read_status(&escrow_data)?;
+        if !status.is_terminal() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        drop(escrow_data);
+
+        if let Some(keeper) = keeper {
+            sweep_from_escrow(escrow, keeper, CLOSER_REWARD_LAMPORTS)?;
+        }
+        close_to_rent_recipient(accounts, escrow)
+    }
+}
+
+// ============================================================================
+// RefundAndClose / ReleaseAndClose
+// ============================================================================
+
+pub struct RefundAndClose;
+
+impl RefundAndClose {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: as RefundV2 (authority, buyer, seller, escrow, [funders...]),
+        // plus the treasury if the rent goes there
+        // RefundV2, then CloseEscrow in the same instruction: the result is
+        // terminal, so the rent goes straight to its recipient.
+        if accounts.len() < 4 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        RefundV2::process(program_id, accounts)?;
+        close_to_rent_recipient(accounts, &accounts[3])
+    }
+}
+
+pub struct ReleaseAndClose;
+
+impl ReleaseAndClose {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: as ReleaseV2 (authority, seller, escrow), plus the rent
+        // recipient if it isn't among them
+        // Pays the seller, then sends the escrow's rent to its recipient.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        ReleaseV2::process(program_id, accounts, &[])?;
+        close_to_rent_recipient(accounts, &accounts[2])
+    }
+}
+
+// ============================================================================
+// SplitEscrow
+// ============================================================================
+
+pub struct SplitEscrow;
+
+impl SplitEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, parent escrow, child seller, child arbitrator,
+        // child escrow, system_program
+        if accounts.len() < 6 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let parent = &accounts[1];
+        let seller = &accounts[2];
+        let arbitrator = &accounts[3];
+        let child = &accounts[4];
+        let system_program = &accounts[5];
+
+        assert_distinct(&[buyer.key(), parent.key(), seller.key(), arbitrator.key(), child.key()])?;
+
+        // Curated builds: the child's seller must be allowlisted too
+        #[cfg(feature = "seller-allowlist")]
+        {
+            let allowlist = accounts.get(6).ok_or(ProgramError::NotEnoughAccountKeys)?;
+            assert_seller_allowed(program_id, allowlist, seller.key())?;
+        }
+
+        // Parse instruction data: carve_amount(8) + seed(8) + timeout_seconds(8) +
+        // terms_hash(32) = 56 bytes, the child's terms
+        if data.len() < 56 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let carve_amount = read_u64(data, 0);
+        let seed = read_u64(data, 8);
+        let timeout_seconds = read_u64(data, 16);
+        let mut terms_hash = [0u8; 32];
+        terms_hash.copy_from_slice(&data[24..56]);
+
+        assert_escrow_owner(parent, program_id)?;
+
+        let mut parent_data = parent.try_borrow_mut_data()?;
+
+        assert_escrow_size(&parent_data)?;
+        assert_not_processing(&parent_data)?;
+        let disc = read_u64(&parent_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &parent_data, OFF_BUYER)?;
+
+        if read_flags(&parent_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        if read_status(&parent_data)? != EscrowStatus::Active {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // The carve-out moves lamports from the parent PDA into the child
+        if is_token_escrow(&parent_data) {
+            return Err(PactError::TokenEscrowUnsupported.into());
+        }
+
+        // Pooled contributions, milestone schedules and subscriptions can't be
+        // attributed to one side of a split
+        if parent_data[OFF_FUNDER_COUNT] > 1
+            || parent_data[OFF_MILESTONE_COUNT] != 0
+            || parent_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2] != [0, 0]
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Nor can protections the parties agreed for this escrow: a child
+        // without them would let the buyer carve the funds into a fee-free,
+        // refund-on-timeout escrow and cancel it straight away
+        if read_u64(&parent_data, OFF_CANCEL_FEE_CAP) != 0
+            || parent_data[OFF_TIMEOUT_MODE] != TIMEOUT_MODE_REFUND
+            || read_u64(&parent_data, OFF_MIN_DISPUTE_DELAY) != 0
+            || !pubkey_eq(&parent_data, OFF_CALLBACK_PROGRAM, &[0u8; 32])
+            || parent_data[OFF_STRICT_ACCEPTANCE] != 0
+            || read_flags(&parent_data).intersects(Flags::REFUND_REQUIRES_NO_DELIVERY)
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Both escrows must keep a nonzero amount
+        let amount = read_u64(&parent_data, OFF_AMOUNT);
+        if carve_amount == 0 || carve_amount >= amount {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let remaining = amount - carve_amount;
+
+        // The parent keeps its rent reserve on top of what it still holds
+        let parent_after = parent
+            .lamports()
+            .checked_sub(carve_amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        if parent_after < rent_reserve(parent, &parent_data)?.saturating_add(remaining) {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        assert_timeout_fits(timeout_seconds)?;
+
+        let (expected_pda, bump) = derive_escrow(buyer.key(), seller.key(), seed, program_id);
+        if child.key() != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        write_u64(&mut parent_data, OFF_AMOUNT, remaining);
+        if parent_data[OFF_FUNDER_COUNT] == 1 {
+            write_u64(&mut parent_data, OFF_FUNDERS + 32, remaining);
+        }
+        // The arbitrator fee follows the funds, rounding in the parent's favour
+        let parent_fee = read_u64(&parent_data, OFF_ARBITRATOR_FEE);
+        let child_fee = (parent_fee as u128 * carve_amount as u128 / amount as u128) as u64;
+        write_u64(&mut parent_data, OFF_ARBITRATOR_FEE, parent_fee - child_fee);
+        let mint_decimals = parent_data[OFF_MINT_DECIMALS];
+        let policy = parent_data[OFF_POLICY];
+        let rent_recipient = parent_data[OFF_RENT_RECIPIENT];
+        let rent_treasury = read_pubkey(&parent_data, OFF_RENT_TREASURY);
+        let releaser = read_pubkey(&parent_data, OFF_RELEASER);
+        let fee_recipient = read_pubkey(&parent_data, OFF_FEE_RECIPIENT);
+        let platform_fee_bps = [parent_data[OFF_PLATFORM_FEE_BPS], parent_data[OFF_PLATFORM_FEE_BPS + 1]];
+        let arbitration_window = read_u64(&parent_data, OFF_ARBITRATION_WINDOW);
+        drop(parent_data);
+
+        // Create the child; the buyer pays its rent
+        let lamports = Rent::get()?.minimum_balance(ESCROW_SIZE);
+
+        let bump_bytes = [bump];
+        let seed_bytes = seed.to_le_bytes();
+        let signer_seeds = [
+            Seed::from(b"escrow".as_slice()),
+            Seed::from(buyer.key().as_ref()),
+            Seed::from(seller.key().as_ref()),
+            Seed::from(seed_bytes.as_ref()),
+            Seed::from(bump_bytes.as_ref()),
+        ];
+        let signer = Signer::from(&signer_seeds);
+
+        let mut create_data = [0u8; 52];
+        create_data[0..4].copy_from_slice(&0u32.to_le_bytes());
+        create_data[4..12].copy_from_slice(&lamports.to_le_bytes());
+        create_data[12..20].copy_from_slice(&(ESCROW_SIZE as u64).to_le_bytes());
+        create_data[20..52].copy_from_slice(program_id);
+
+        let create_accounts = [
+            AccountMeta::writable_signer(buyer.key()),
+            AccountMeta::writable_signer(child.key()),
+        ];
+
+        let create_ix = Instruction {
+            program_id: system_program.key(),
+            accounts: &create_accounts,
+            data: &create_data,
+        };
+
+        invoke_signed(&create_ix, &[buyer, child], &[signer])?;
+
+        // Initialize the child. Fields not written here start zeroed, which
+        // means no appeal arbitrator, no dispute delay and refund on timeout.
+        let clock = Clock::get()?;
+        let mut child_data = child.try_borrow_mut_data()?;
+        write_u64(&mut child_data, OFF_DISC, ESCROW_DISC);
+        write_pubkey(&mut child_data, OFF_BUYER, buyer.key());
+        write_pubkey(&mut child_data, OFF_SELLER, seller.key());
+        write_pubkey(&mut child_data, OFF_ARBITRATOR, arbitrator.key());
+        write_u64(&mut child_data, OFF_AMOUNT, carve_amount);
+        write_u64(&mut child_data, OFF_CREATED_AT, unix_now(&clock));
+        write_u64(&mut child_data, OFF_TIMEOUT, timeout_seconds);
+        child_data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&terms_hash);
+        child_data[OFF_STATUS] = EscrowStatus::Active.as_u8();
+        child_data[OFF_BUMP] = bump;
+        child_data[OFF_FUNDER_COUNT] = 1;
+        write_pubkey(&mut child_data, OFF_FUNDERS, buyer.key());
+        write_u64(&mut child_data, OFF_FUNDERS + 32, carve_amount);
+        write_u64(&mut child_data, OFF_FUNDED_AMOUNT, carve_amount);
+        write_u64(&mut child_data, OFF_SEED, seed);
+        write_u64(&mut child_data, OFF_RENT_PAID, lamports);
+        child_data[OFF_MINT_DECIMALS] = mint_decimals;
+        child_data[OFF_POLICY] = policy;
+        child_data[OFF_RENT_RECIPIENT] = rent_recipient;
+        write_pubkey(&mut child_data, OFF_RENT_TREASURY, &rent_treasury);
+        write_pubkey(&mut child_data, OFF_RELEASER, &releaser);
+        write_u64(&mut child_data, OFF_ARBITRATOR_FEE, child_fee);
+        write_pubkey(&mut child_data, OFF_FEE_RECIPIENT, &fee_recipient);
+        child_data[OFF_PLATFORM_FEE_BPS..OFF_PLATFORM_FEE_BPS + 2].copy_from_slice(&platform_fee_bps);
+        write_u64(&mut child_data, OFF_ARBITRATION_WINDOW, arbitration_window);
+        drop(child_data);
+
+        // Fund the child from the parent
+        transfer_from_escrow(parent, child, carve_amount)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// MarkMilestoneDelivered
+// ============================================================================
+
+pub struct MarkMilestoneDelivered;
+
+impl MarkMilestoneDelivered {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: seller, escrow
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let seller = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[seller.key(), escrow.key()])?;
+
+        // Parse instruction data: index(1)
+        let index = *data.first().ok_or(ProgramError::InvalidInstructionData)? as usize;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_seller_authority(seller, &escrow_data, accounts)?;
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        if read_status(&escrow_data)? != EscrowStatus::Active {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if index >= escrow_data[OFF_MILESTONE_COUNT] as usize {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let bit = 1u8 << index;
+        if (escrow_data[OFF_MILESTONE_DELIVERED] | escrow_data[OFF_MILESTONE_SETTLED]) & bit != 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        escrow_data[OFF_MILESTONE_DELIVERED] |= bit;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// AcceptMilestone / ReleaseMilestone
+// ============================================================================
+
+// Pays milestone `index` (data: index(1)) to the seller and marks it settled;
+// the last one settles the escrow. Accounts: buyer, seller, escrow. With
+// `accept`, the milestone must have been marked delivered and the buyer is
+// accepting it; otherwise the buyer releases it outright, like ReleaseV2.
+fn settle_milestone(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+    accept: bool,
+) -> ProgramResult {
+    if accounts.len() < 3 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let buyer = &accounts[0];
+    let seller = &accounts[1];
+    let escrow = &accounts[2];
+
+    assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+    // Parse instruction data: index(1)
+    let index = *data.first().ok_or(ProgramError::InvalidInstructionData)? as usize;
+
+    assert_escrow_owner(escrow, program_id)?;
+    let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+    assert_escrow_size(&escrow_data)?;
+    assert_not_processing(&escrow_data)?;
+    let disc = read_u64(&escrow_data, OFF_DISC);
+    if disc != ESCROW_DISC {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+    if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+        return Err(PactError::EscrowFrozen.into());
+    }
+    // Strict-acceptance escrows only pay for delivered work
+    if !accept && escrow_data[OFF_STRICT_ACCEPTANCE] != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if read_status(&escrow_data)? != EscrowStatus::Active {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let milestone_count = escrow_data[OFF_MILESTONE_COUNT] as usize;
+    if index >= milestone_count {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let bit = 1u8 << index;
+    if escrow_data[OFF_MILESTONE_SETTLED] & bit != 0
+        || (accept && escrow_data[OFF_MILESTONE_DELIVERED] & bit == 0)
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let milestone_amount = read_u64(&escrow_data, OFF_MILESTONE_AMOUNTS + index * 8);
+    let amount = read_u64(&escrow_data, OFF_AMOUNT);
+    assert_balance_covers(escrow, &escrow_data, amount)?;
+    let remaining = amount
+        .checked_sub(milestone_amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+
+    escrow_data[OFF_MILESTONE_SETTLED] |= bit;
+    write_u64(&mut escrow_data, OFF_AMOUNT, remaining);
+
+    // The last milestone settles the whole escrow
+    let all_settled = (1u16 << milestone_count) - 1;
+    if escrow_data[OFF_MILESTONE_SETTLED] as u16 == all_settled {
+        if accept {
+            insert_flags(&mut escrow_data, Flags::BUYER_ACCEPTED);
+        }
+        set_status(&mut escrow_data, EscrowStatus::Released)?;
+    }
+    drop(escrow_data);
+
+    let lamports_before = escrow.lamports();
+    transfer_from_escrow(escrow, seller, milestone_amount)?;
+    assert_partial_payout(escrow, lamports_before, milestone_amount, remaining)?;
+
+    notify_callback(accounts, escrow)?;
+
+    Ok(())
+}
+
+pub struct AcceptMilestone;
+
+impl AcceptMilestone {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow
+        // Accepting a delivered milestone releases that milestone's funds.
+        settle_milestone(program_id, accounts, data, true)
+    }
+}
+
+pub struct ReleaseMilestone;
+
+impl ReleaseMilestone {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow
+        // The buyer releases a milestone's funds whether or not the seller
+        // marked it delivered, e.g. for work confirmed off-chain.
+        settle_milestone(program_id, accounts, data, false)
+    }
+}
+
+// ============================================================================
+// InspectEscrow
+// ============================================================================
+
+pub struct InspectEscrow;
+
+impl InspectEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Read-only health probe for monitoring. Returns an INVARIANT_* bitmask
+        // of the invariants that hold; INVARIANTS_ALL means a healthy escrow.
+        // The layout has no version field: the discriminator plus the current
+        // size stand in for it. Escrows created before the seed was stored
+        // report a non-canonical bump.
+        if accounts.is_empty() {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let escrow = &accounts[0];
+        let escrow_data = escrow.try_borrow_data()?;
+
+        let mut invariants = 0;
+        if escrow.owner() == program_id
+            && escrow_data.len() >= 8
+            && read_u64(&escrow_data, OFF_DISC) == ESCROW_DISC
+        {
+            invariants |= INVARIANT_DISCRIMINATOR;
+        }
+        if escrow_data.len() == ESCROW_SIZE {
+            invariants |= INVARIANT_SIZE;
+        }
+        // The remaining checks read fields of the current layout
+        if invariants != INVARIANT_DISCRIMINATOR | INVARIANT_SIZE {
+            set_return_data(&[invariants]);
+            return Ok(());
+        }
+
+        // An unknown status byte is reported, not rejected
+        let status = EscrowStatus::from_u8(escrow_data[OFF_STATUS]).ok();
+        if status.is_some() {
+            invariants |= INVARIANT_STATUS;
+        }
+        if status.is_none_or(|status| flags_consistent(status, read_flags(&escrow_data))) {
+            invariants |= INVARIANT_FLAGS;
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        let settled = status.is_some_and(EscrowStatus::is_terminal);
+        // A token escrow's balance is in its vault, which isn't passed here
+        let covered = is_token_escrow(&escrow_data)
+            || assert_balance_covers(escrow, &escrow_data, amount).is_ok();
+        if covered && (!settled || amount == 0) {
+            invariants |= INVARIANT_AMOUNT;
+        }
+
+        // A reassigned escrow keeps the address derived from its first seller
+        let buyer = read_pubkey(&escrow_data, OFF_BUYER);
+        let original_seller = read_pubkey(&escrow_data, OFF_ORIGINAL_SELLER);
+        let seller = if original_seller != [0u8; 32] {
+            original_seller
+        } else {
+            read_pubkey(&escrow_data, OFF_SELLER)
+        };
+        let seed = read_u64(&escrow_data, OFF_SEED);
+        let (expected_pda, bump) = derive_escrow(&buyer, &seller, seed, program_id);
+        if escrow.key() == &expected_pda && escrow_data[OFF_BUMP] == bump {
+            invariants |= INVARIANT_BUMP;
+        }
+
+        set_return_data(&[invariants]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// AutoRelease
+// ============================================================================
+
+pub struct AutoRelease;
+
+impl AutoRelease {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: seller, escrow, then the fee recipient if the escrow
+        // charges a platform fee
+        // Permissionless: releases a delivered escrow the buyer has neither
+        // accepted nor disputed within the acceptance window.
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let seller = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+
+        if read_status(&escrow_data)? != EscrowStatus::Delivered
+            || read_flags(&escrow_data).intersects(Flags::DISPUTED)
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let acceptance_window = read_u64(&escrow_data, OFF_ACCEPTANCE_WINDOW);
+        let delivered_at = read_u64(&escrow_data, OFF_DELIVERED_AT);
+        if acceptance_window == 0 || delivered_at == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        if now < delivered_at.saturating_add(acceptance_window) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+
+        set_status(&mut escrow_data, EscrowStatus::Released)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+        let fee = pay_seller(accounts, escrow, seller, amount)?;
+
+        notify_callback(accounts, escrow)?;
+
+        // Fields: amount, delivered_at, platform fee (out of amount)
+        log_event("pact:auto_release", escrow.key(), [amount, delivered_at, fee, 0, 0]);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// RenewEscrow
+// ============================================================================
+
+pub struct RenewEscrow;
+
+impl RenewEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: seller, escrow
+        // Permissionless crank for subscriptions: once the current period has
+        // ended, pays it to the seller and starts the next one. The buyer
+        // stops renewal through RefundV2, which returns only the periods that
+        // haven't started.
+        if accounts.len() < 2 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let seller = &accounts[0];
+        let escrow = &accounts[1];
+
+        assert_distinct(&[seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        if read_status(&escrow_data)? != EscrowStatus::Active {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let periods_remaining = u16::from_le_bytes([
+            escrow_data[OFF_PERIODS_REMAINING],
+            escrow_data[OFF_PERIODS_REMAINING + 1],
+        ]);
+        if periods_remaining == 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        if !timeout_reached(&escrow_data, now) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        let period_amount = read_u64(&escrow_data, OFF_PERIOD_AMOUNT).min(amount);
+        let periods_remaining = periods_remaining - 1;
+
+        // Roll forward from the end of the period just paid, not from now, so
+        // a late crank doesn't shift the schedule
+        let created_at = read_u64(&escrow_data, OFF_CREATED_AT);
+        let timeout_seconds = read_u64(&escrow_data, OFF_TIMEOUT);
+        write_u64(&mut escrow_data, OFF_CREATED_AT, created_at.saturating_add(timeout_seconds));
+        escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2]
+            .copy_from_slice(&periods_remaining.to_le_bytes());
+        write_u64(&mut escrow_data, OFF_AMOUNT, amount - period_amount);
+        let finished = periods_remaining == 0;
+        if finished {
+            set_status(&mut escrow_data, EscrowStatus::Released)?;
+        }
+        drop(escrow_data);
+
+        let lamports_before = escrow.lamports();
+        transfer_from_escrow(escrow, seller, period_amount)?;
+        assert_partial_payout(escrow, lamports_before, period_amount, amount - period_amount)?;
+
+        if finished {
+            notify_callback(accounts, escrow)?;
+        }
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// PartialAcceptKeepOpen
+// ============================================================================
+
+pub struct PartialAcceptKeepOpen;
+
+impl PartialAcceptKeepOpen {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow, then the fee recipient if the
+        // escrow charges a platform fee
+        // The buyer accepts part of a delivery: that part goes to the seller
+        // and the rest reopens as Active with a fresh timeout, so the seller
+        // can deliver the missing part instead of going to arbitration.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        // Parse instruction data: accepted_amount(8)
+        if data.len() < 8 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let accepted_amount = read_u64(data, 0);
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        if read_status(&escrow_data)? != EscrowStatus::Delivered {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if escrow_data[OFF_POLICY] & POLICY_DISALLOW_PARTIAL != 0 {
+            return Err(PactError::PartialPayoutDisabled.into());
+        }
+
+        // Pooled contributions and subscription periods must keep adding up
+        // to amount, so they can't shrink by an arbitrary part
+        if escrow_data[OFF_FUNDER_COUNT] > 1
+            || escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2] != [0, 0]
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        if accepted_amount == 0 || accepted_amount >= amount {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // The remainder starts a new delivery cycle
+        let clock = Clock::get()?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, amount - accepted_amount);
+        write_u64(&mut escrow_data, OFF_CREATED_AT, unix_now(&clock));
+        write_u64(&mut escrow_data, OFF_DELIVERED_AT, 0);
+        remove_flags(&mut escrow_data, Flags::SELLER_DELIVERED);
+        set_status(&mut escrow_data, EscrowStatus::Active)?;
+        if escrow_data[OFF_FUNDER_COUNT] == 1 {
+            write_u64(&mut escrow_data, OFF_FUNDERS + 32, amount - accepted_amount);
+        }
+        drop(escrow_data);
+
+        let lamports_before = escrow.lamports();
+        pay_seller(accounts, escrow, seller, accepted_amount)?;
+        assert_partial_payout(escrow, lamports_before, accepted_amount, amount - accepted_amount)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// PartialRelease
+// ============================================================================
+
+pub struct PartialRelease;
+
+impl PartialRelease {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow, then the fee recipient if the
+        // escrow charges a platform fee
+        // The buyer pays part of the escrowed amount to the seller and the rest
+        // stays escrowed in the same state, e.g. for staged service delivery.
+        // Releasing the whole remainder settles the escrow like ReleaseV2.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        // Parse instruction data: release_amount(8)
+        if data.len() < 8 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let release_amount = read_u64(data, 0);
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        // Same gate as ReleaseV2: strict-acceptance escrows pay through
+        // AcceptDelivery only
+        if escrow_data[OFF_STRICT_ACCEPTANCE] != 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        assert_not_settled(&escrow_data)?;
+        let status = read_status(&escrow_data)?;
+        if status != EscrowStatus::Active && status != EscrowStatus::Delivered {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Pooled contributions, milestone amounts and subscription periods
+        // must keep adding up to amount, so they can't shrink by an arbitrary part
+        if escrow_data[OFF_FUNDER_COUNT] > 1
+            || escrow_data[OFF_MILESTONE_COUNT] != 0
+            || escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2] != [0, 0]
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // The balance must cover the whole amount on top of the rent reserve,
+        // so no release can take the escrow below rent exemption
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        if release_amount == 0 || release_amount > amount {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let remaining = amount - release_amount;
+        if remaining > 0 && escrow_data[OFF_POLICY] & POLICY_DISALLOW_PARTIAL != 0 {
+            return Err(PactError::PartialPayoutDisabled.into());
+        }
+
+        write_u64(&mut escrow_data, OFF_AMOUNT, remaining);
+        if escrow_data[OFF_FUNDER_COUNT] == 1 {
+            write_u64(&mut escrow_data, OFF_FUNDERS + 32, remaining);
+        }
+        if remaining == 0 {
+            set_status(&mut escrow_data, EscrowStatus::Released)?;
+        }
+        drop(escrow_data);
+
+        if remaining == 0 {
+            assert_settled(escrow)?;
+            pay_seller(accounts, escrow, seller, release_amount)?;
+
+            #[cfg(feature = "seller-reputation")]
+            record_reputation(accounts, escrow, seller.key(), REPUTATION_OFF_COMPLETED)?;
+
+            return notify_callback(accounts, escrow);
+        }
+
+        let lamports_before = escrow.lamports();
+        pay_seller(accounts, escrow, seller, release_amount)?;
+        assert_partial_payout(escrow, lamports_before, release_amount, remaining)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// CancelEscrow
+// ============================================================================
+
+pub struct CancelEscrow;
+
+impl CancelEscrow {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow, [funders 1..n]
+        // The buyer walks away before the seller has delivered anything,
+        // without waiting for the timeout: the seller keeps the vested
+        // cancellation fee, if the escrow has one, and the rest is refunded.
+        // Once a delivery is on record the buyer has to go through RefundV2
+        // or a dispute.
+        if accounts.len() < 3 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let seller = &accounts[1];
+        let escrow = &accounts[2];
+
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        if !pubkey_eq(&escrow_data, OFF_SELLER, seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_not_settled(&escrow_data)?;
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        if read_status(&escrow_data)? == EscrowStatus::Delivered
+            || read_flags(&escrow_data).intersects(Flags::SELLER_DELIVERED)
+            || escrow_data[OFF_MILESTONE_DELIVERED] != 0
+        {
+            return Err(PactError::AlreadyDelivered.into());
+        }
+        if read_status(&escrow_data)? != EscrowStatus::Active {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // Subscriptions are revoked through RefundV2, which keeps the period
+        // under way for the seller
+        if escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2] != [0, 0] {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        assert_balance_covers(escrow, &escrow_data, amount)?;
+        let funders = read_funders(&escrow_data);
+
+        let clock = Clock::get()?;
+        let now = unix_now(&clock);
+        let elapsed = elapsed_since(now, read_u64(&escrow_data, OFF_CREATED_AT));
+        let fee = vested_cancel_fee(
+            read_u64(&escrow_data, OFF_CANCEL_FEE_CAP),
+            read_u64(&escrow_data, OFF_CANCEL_FEE_RAMP),
+            elapsed,
+            amount,
+        );
+
+        set_status(&mut escrow_data, EscrowStatus::Refunded)?;
+        write_u64(&mut escrow_data, OFF_AMOUNT, 0);
+        drop(escrow_data);
+
+        assert_settled(escrow)?;
+        refund_funders(escrow, buyer, &accounts[3..], &funders, amount, amount - fee)?;
+        transfer_from_escrow(escrow, seller, fee)?;
+
+        notify_callback(accounts, escrow)?;
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Reassign
+// ============================================================================
+
+pub struct Reassign;
+
+impl Reassign {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, old_seller, new_seller, escrow, [allowlist]
+        // Hands an undelivered deal to a new seller under new terms in one
+        // step. The buyer, the old seller and the new seller all sign.
+        // The escrow doesn't move: its address stays derived from the first
+        // seller, and only the stored seller changes.
+        if accounts.len() < 4 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        let old_seller = &accounts[1];
+        let new_seller = &accounts[2];
+        let escrow = &accounts[3];
+
+        assert_distinct(&[buyer.key(), old_seller.key(), new_seller.key(), escrow.key()])?;
+
+        // Curated builds: the new seller must be allowlisted
+        #[cfg(feature = "seller-allowlist")]
+        {
+            let allowlist = accounts.get(4).ok_or(ProgramError::NotEnoughAccountKeys)?;
+            assert_seller_allowed(program_id, allowlist, new_seller.key())?;
+        }
+
+        // Parse instruction data: terms_hash(32)
+        if data.len() < 32 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let terms_hash = read_pubkey(data, 0);
+
+        if !new_seller.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        assert_escrow_owner(escrow, program_id)?;
+        let mut escrow_data = escrow.try_borrow_mut_data()?;
+
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
+        let disc = read_u64(&escrow_data, OFF_DISC);
+        if disc != ESCROW_DISC {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        if !pubkey_eq(&escrow_data, OFF_SELLER, old_seller.key()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // The arbitrators stay neutral: neither can become the seller
+        if pubkey_eq(&escrow_data, OFF_ARBITRATOR, new_seller.key())
+            || pubkey_eq(&escrow_data, OFF_APPEAL_ARBITRATOR, new_seller.key())
+        {
+            return Err(PactError::DuplicateAccount.into());
+        }
+        // Nor can the seller become its own releaser
+        if pubkey_eq(&escrow_data, OFF_RELEASER, new_seller.key()) {
+            return Err(PactError::DuplicateAccount.into());
+        }
+
+        // Handing the job over is the old seller's call too
+        assert_seller_authority(old_seller, &escrow_data, accounts)?;
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        // Delivered work stays with the seller who did it. Paid milestones
+        // are settled and don't count
+        let status = read_status(&escrow_data)?;
+        if status == EscrowStatus::Delivered
+            || read_flags(&escrow_data).intersects(Flags::SELLER_DELIVERED)
+            || escrow_data[OFF_MILESTONE_DELIVERED] & !escrow_data[OFF_MILESTONE_SETTLED] != 0
+        {
+            return Err(PactError::AlreadyDelivered.into());
+        }
+        if status != EscrowStatus::Active {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // The address stays derived from the first seller; keep it findable
+        if pubkey_eq(&escrow_data, OFF_ORIGINAL_SELLER, &[0u8; 32]) {
+            write_pubkey(&mut escrow_data, OFF_ORIGINAL_SELLER, old_seller.key());
+        }
+
+        // The new seller signs alone
+        let clock = Clock::get()?;
+        write_pubkey(&mut escrow_data, OFF_SELLER, new_seller.key());
+        write_pubkey(&mut escrow_data, OFF_TERMS_HASH, &terms_hash);
+        write_u64(&mut escrow_data, OFF_UPDATED_AT, unix_now(&clock));
+        escrow_data[OFF_SELLER_THRESHOLD] = 0;
+        escrow_data[OFF_SELLER_SIGNER_COUNT] = 0;
+        escrow_data[OFF_SELLER_SIGNERS..OFF_SELLER_SIGNERS + MAX_SELLER_SIGNERS * 32].fill(0);
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// MarkSeen
 // ============================================================================
 
-pub struct MarkDelivered;
+pub struct MarkSeen;
 
-impl MarkDelivered {
-    pub fn process(accounts: &[AccountInfo]) -> ProgramResult {
+impl MarkSeen {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: party (buyer or seller), escrow
+        // Data: optional bits(1) to clear; absent = all of the party's bits
         if accounts.len() < 2 {
             return Err(ProgramError::NotEnoughAccountKeys);
         }
 
-        let seller = &accounts[0];
+        let party = &accounts[0];
         let escrow = &accounts[1];
 
-        if !seller.is_signer() {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        assert_distinct(&[party.key(), escrow.key()])?;
 
+        assert_escrow_owner(escrow, program_id)?;
         let mut escrow_data = escrow.try_borrow_mut_data()?;
 
-        // Validate discriminator
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
         let disc = read_u64(&escrow_data, OFF_DISC);
         if disc != ESCROW_DISC {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // Validate seller
-        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
-        if seller.key() != &stored_seller {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        // Validate status
-        let status = escrow_data[OFF_STATUS];
-        if status != STATUS_ACTIVE {
+        // A party can only clear its own badge
+        let mask = if pubkey_eq(&escrow_data, OFF_BUYER, party.key()) {
+            UNSEEN_BUYER_MASK
+        } else if pubkey_eq(&escrow_data, OFF_SELLER, party.key()) {
+            UNSEEN_SELLER_MASK
+        } else {
             return Err(ProgramError::InvalidAccountData);
+        };
+        if !party.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // Update flags and status
-        escrow_data[OFF_FLAGS] |= FLAG_SELLER_DELIVERED;
-        escrow_data[OFF_STATUS] = STATUS_DELIVERED;
+        let clear = data.first().copied().unwrap_or(mask) & mask;
+        escrow_data[OFF_NOTIFY] &= !clear;
 
         Ok(())
     }
 }
 
 // ============================================================================
-// AcceptDelivery
+// ExportState
 // ============================================================================
 
-pub struct AcceptDelivery;
+pub struct ExportState;
 
-impl AcceptDelivery {
-    pub fn process(accounts: &[AccountInfo]) -> ProgramResult {
-        if accounts.len() < 3 {
+impl ExportState {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: escrow
+        // Data: optional offset(2); absent = 0
+        // Read-only, version-stamped snapshot of the raw escrow for backups.
+        // The account is larger than return data allows, so it comes in
+        // chunks: read from offset 0 and repeat from offset + chunk length
+        // until offset + chunk length reaches total_len.
+        if accounts.is_empty() {
             return Err(ProgramError::NotEnoughAccountKeys);
         }
 
-        let buyer = &accounts[0];
-        let seller = &accounts[1];
-        let escrow = &accounts[2];
+        let escrow = &accounts[0];
+        assert_escrow_owner(escrow, program_id)?;
 
-        if !buyer.is_signer() {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        let offset = match data {
+            [lo, hi, ..] => u16::from_le_bytes([*lo, *hi]) as usize,
+            _ => 0,
+        };
 
-        let mut escrow_data = escrow.try_borrow_mut_data()?;
+        let escrow_data = escrow.try_borrow_data()?;
 
-        // Validate
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
         let disc = read_u64(&escrow_data, OFF_DISC);
         if disc != ESCROW_DISC {
             return Err(ProgramError::InvalidAccountData);
         }
-
-        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
-        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
-        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
-            return Err(ProgramError::InvalidAccountData);
+        if offset >= ESCROW_SIZE {
+            return Err(ProgramError::InvalidInstructionData);
         }
 
-        let status = escrow_data[OFF_STATUS];
-        if status != STATUS_DELIVERED {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let end = (offset + EXPORT_CHUNK_SIZE).min(ESCROW_SIZE);
+        let mut ret = [0u8; MAX_RETURN_DATA];
+        ret[0] = EXPORT_SCHEMA_VERSION;
+        ret[1..3].copy_from_slice(&(ESCROW_SIZE as u16).to_le_bytes());
+        ret[3..5].copy_from_slice(&(offset as u16).to_le_bytes());
+        ret[EXPORT_HEADER_SIZE..EXPORT_HEADER_SIZE + end - offset].copy_from_slice(&escrow_data[offset..end]);
+        set_return_data(&ret[..EXPORT_HEADER_SIZE + end - offset]);
 
-        let amount = read_u64(&escrow_data, OFF_AMOUNT);
+        Ok(())
+    }
+}
 
-        // Update status
-        escrow_data[OFF_FLAGS] |= FLAG_BUYER_ACCEPTED;
-        escrow_data[OFF_STATUS] = STATUS_RELEASED;
-        drop(escrow_data);
+// ============================================================================
+// BatchAccept
+// ============================================================================
 
-        // Transfer funds to seller
-        unsafe {
-            let escrow_lamports = escrow.borrow_mut_lamports_unchecked();
-            let seller_lamports = seller.borrow_mut_lamports_unchecked();
-            *seller_lamports = seller_lamports.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
-            *escrow_lamports = escrow_lamports.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+// Whether BatchAccept should run AcceptDelivery on this (buyer, seller,
+// escrow) group. Escrows that can't be accepted right now are skipped; a
+// group naming the wrong buyer or seller is a malformed account list and
+// fails the batch. Escrows with a callback program or a platform fee need
+// that account among their accounts, so they're left for a standalone
+// AcceptDelivery. Token escrows are skipped too: AcceptDelivery only pays
+// out lamports.
+fn batch_acceptable(program_id: &Pubkey, group: &[AccountInfo]) -> Result<bool, ProgramError> {
+    let escrow = &group[2];
+    if escrow.owner() != program_id {
+        return Ok(false);
+    }
+
+    let escrow_data = escrow.try_borrow_data()?;
+    if escrow_data.len() != ESCROW_SIZE || read_u64(&escrow_data, OFF_DISC) != ESCROW_DISC {
+        return Ok(false);
+    }
+    if !pubkey_eq(&escrow_data, OFF_BUYER, group[0].key())
+        || !pubkey_eq(&escrow_data, OFF_SELLER, group[1].key())
+    {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(read_status(&escrow_data)? == EscrowStatus::Delivered
+        && !read_flags(&escrow_data).intersects(Flags::FROZEN)
+        && pubkey_eq(&escrow_data, OFF_CALLBACK_PROGRAM, &[0u8; 32])
+        && escrow_data[OFF_PLATFORM_FEE_BPS..OFF_PLATFORM_FEE_BPS + 2] == [0, 0]
+        && !is_token_escrow(&escrow_data))
+}
+
+pub struct BatchAccept;
+
+impl BatchAccept {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: (buyer, seller, escrow) per escrow, the buyer repeated
+        // The buyer accepts every delivered escrow in the list at once, e.g.
+        // a marketplace cart. Returns the number accepted(1).
+        if accounts.len() < 3 || !accounts.len().is_multiple_of(3) {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let buyer = &accounts[0];
+        if !buyer.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let mut accepted: u8 = 0;
+        for group in accounts.chunks_exact(3) {
+            if group[0].key() != buyer.key() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            if !batch_acceptable(program_id, group)? {
+                continue;
+            }
+            AcceptDelivery::process(program_id, group, &[])?;
+            accepted += 1;
         }
 
+        set_return_data(&[accepted]);
+
         Ok(())
     }
 }
 
 // ============================================================================
-// ReleaseV2
+// ExtendTimeout
 // ============================================================================
 
-pub struct ReleaseV2;
+pub struct ExtendTimeout;
 
-impl ReleaseV2 {
-    pub fn process(accounts: &[AccountInfo]) -> ProgramResult {
+impl ExtendTimeout {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: buyer, seller, escrow
+        // Both parties agree to push the timeout out by `extension` seconds,
+        // e.g. to give the seller more time to deliver. Works the same for a
+        // relative timeout and an absolute deadline.
         if accounts.len() < 3 {
             return Err(ProgramError::NotEnoughAccountKeys);
         }
@@ -325,256 +5153,331 @@ impl ReleaseV2 {
         let seller = &accounts[1];
         let escrow = &accounts[2];
 
-        if !buyer.is_signer() {
-            return Err(ProgramError::MissingRequiredSignature);
+        assert_distinct(&[buyer.key(), seller.key(), escrow.key()])?;
+
+        // Parse instruction data: extension(8)
+        if data.len() < 8 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let extension = read_u64(data, 0);
+        if extension == 0 {
+            return Err(ProgramError::InvalidInstructionData);
         }
 
+        assert_escrow_owner(escrow, program_id)?;
         let mut escrow_data = escrow.try_borrow_mut_data()?;
 
+        assert_escrow_size(&escrow_data)?;
+        assert_not_processing(&escrow_data)?;
         let disc = read_u64(&escrow_data, OFF_DISC);
         if disc != ESCROW_DISC {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
-        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
-        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
+        assert_signer_at(buyer, &escrow_data, OFF_BUYER)?;
+        assert_seller_authority(seller, &escrow_data, accounts)?;
+
+        if read_flags(&escrow_data).intersects(Flags::FROZEN) {
+            return Err(PactError::EscrowFrozen.into());
+        }
+        assert_not_settled(&escrow_data)?;
+        let status = read_status(&escrow_data)?;
+        if status == EscrowStatus::Disputed || status == EscrowStatus::Appealed {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let status = escrow_data[OFF_STATUS];
-        // Can release from Active, Delivered, or Accepted (but not Disputed)
-        if status == STATUS_DISPUTED || status == STATUS_RELEASED || status == STATUS_REFUNDED {
+        // An escrow without a timeout has nothing to extend, and subscription
+        // periods use the timeout as their length
+        let timeout = read_u64(&escrow_data, OFF_TIMEOUT);
+        if timeout == 0 || escrow_data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2] != [0, 0] {
             return Err(ProgramError::InvalidAccountData);
         }
-
-        let amount = read_u64(&escrow_data, OFF_AMOUNT);
-        escrow_data[OFF_STATUS] = STATUS_RELEASED;
-        drop(escrow_data);
-
-        unsafe {
-            let escrow_lamports = escrow.borrow_mut_lamports_unchecked();
-            let seller_lamports = seller.borrow_mut_lamports_unchecked();
-            *seller_lamports = seller_lamports.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
-            *escrow_lamports = escrow_lamports.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+        let timeout = timeout.checked_add(extension).ok_or(ProgramError::ArithmeticOverflow)?;
+        if !read_flags(&escrow_data).intersects(Flags::ABSOLUTE_DEADLINE) {
+            read_u64(&escrow_data, OFF_CREATED_AT)
+                .checked_add(timeout)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
         }
 
+        let clock = Clock::get()?;
+        write_u64(&mut escrow_data, OFF_TIMEOUT, timeout);
+        write_u64(&mut escrow_data, OFF_UPDATED_AT, unix_now(&clock));
+
         Ok(())
     }
 }
 
 // ============================================================================
-// RefundV2
+// Seller reputation (`seller-reputation` feature)
 // ============================================================================
 
-pub struct RefundV2;
+#[cfg(feature = "seller-reputation")]
+fn derive_seller_reputation(seller: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(&[b"rep", seller], program_id)
+}
 
-impl RefundV2 {
-    pub fn process(accounts: &[AccountInfo]) -> ProgramResult {
-        if accounts.len() < 4 {
-            return Err(ProgramError::NotEnoughAccountKeys);
+// Bumps one of the seller's reputation counters if their reputation account
+// was passed anywhere in `accounts`; settling without it records nothing. The
+// account is recognized by owner and discriminator, then its address is
+// checked against the stored bump.
+#[cfg(feature = "seller-reputation")]
+fn record_reputation(
+    accounts: &[AccountInfo],
+    escrow: &AccountInfo,
+    seller: &Pubkey,
+    counter_offset: usize,
+) -> ProgramResult {
+    let program_id = escrow.owner();
+    for account in accounts {
+        if account.owner() != program_id || account.data_len() != REPUTATION_SIZE {
+            continue;
+        }
+        let mut data = account.try_borrow_mut_data()?;
+        if read_u64(&data, OFF_DISC) != REPUTATION_DISC
+            || !pubkey_eq(&data, REPUTATION_OFF_SELLER, seller)
+        {
+            continue;
         }
 
-        let authority = &accounts[0];
-        let buyer = &accounts[1];
-        let seller = &accounts[2];
-        let escrow = &accounts[3];
-
-        if !authority.is_signer() {
-            return Err(ProgramError::MissingRequiredSignature);
+        let bump = [data[REPUTATION_OFF_BUMP]];
+        let expected_pda =
+            pinocchio::pubkey::create_program_address(&[b"rep", seller, &bump], program_id)?;
+        if account.key() != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
         }
 
-        let mut escrow_data = escrow.try_borrow_mut_data()?;
+        let count = read_u64(&data, counter_offset).saturating_add(1);
+        write_u64(&mut data, counter_offset, count);
+        return Ok(());
+    }
+    Ok(())
+}
 
-        let disc = read_u64(&escrow_data, OFF_DISC);
-        if disc != ESCROW_DISC {
-            return Err(ProgramError::InvalidAccountData);
+#[cfg(feature = "seller-reputation")]
+pub struct InitSellerReputation;
+
+#[cfg(feature = "seller-reputation")]
+impl InitSellerReputation {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: payer, seller, reputation, system_program
+        // Anyone may pay to open a seller's reputation account; the counters
+        // only ever move through escrow settlements.
+        if accounts.len() < 4 {
+            return Err(ProgramError::NotEnoughAccountKeys);
         }
 
-        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
-        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
-        let stored_arbitrator = read_pubkey(&escrow_data, OFF_ARBITRATOR);
+        let payer = &accounts[0];
+        let seller = &accounts[1];
+        let reputation = &accounts[2];
+        let system_program = &accounts[3];
 
-        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
-            return Err(ProgramError::InvalidAccountData);
+        assert_distinct(&[payer.key(), reputation.key()])?;
+
+        if !payer.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
         }
 
-        let status = escrow_data[OFF_STATUS];
-        if status == STATUS_RELEASED || status == STATUS_REFUNDED {
-            return Err(ProgramError::InvalidAccountData);
+        let (expected_pda, bump) = derive_seller_reputation(seller.key(), program_id);
+        if reputation.key() != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
         }
 
-        let amount = read_u64(&escrow_data, OFF_AMOUNT);
-        let created_at = read_u64(&escrow_data, OFF_CREATED_AT);
-        let timeout_seconds = read_u64(&escrow_data, OFF_TIMEOUT);
+        let rent = Rent::get()?;
+        let lamports = rent.minimum_balance(REPUTATION_SIZE);
 
-        // Check who can refund
-        let is_seller = authority.key() == &stored_seller;
-        let is_buyer = authority.key() == &stored_buyer;
-        let is_arbitrator = authority.key() == &stored_arbitrator;
+        let bump_bytes = [bump];
+        let signer_seeds = [
+            Seed::from(b"rep".as_slice()),
+            Seed::from(seller.key().as_ref()),
+            Seed::from(bump_bytes.as_ref()),
+        ];
+        let signer = Signer::from(&signer_seeds);
 
-        let clock = Clock::get()?;
-        let now = clock.unix_timestamp as u64;
-        let timeout_reached = timeout_seconds > 0 && now >= created_at + timeout_seconds;
+        let mut create_data = [0u8; 52];
+        create_data[0..4].copy_from_slice(&0u32.to_le_bytes());
+        create_data[4..12].copy_from_slice(&lamports.to_le_bytes());
+        create_data[12..20].copy_from_slice(&(REPUTATION_SIZE as u64).to_le_bytes());
+        create_data[20..52].copy_from_slice(program_id);
 
-        // Seller can always refund
-        // Buyer can refund if: timeout reached OR status is Active (no delivery yet)
-        // Arbitrator can refund if disputed
-        let can_refund = is_seller 
-            || (is_buyer && (timeout_reached || status == STATUS_ACTIVE))
-            || (is_arbitrator && status == STATUS_DISPUTED);
+        let create_accounts = [
+            AccountMeta::writable_signer(payer.key()),
+            AccountMeta::writable_signer(reputation.key()),
+        ];
 
-        if !can_refund {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let create_ix = Instruction {
+            program_id: system_program.key(),
+            accounts: &create_accounts,
+            data: &create_data,
+        };
 
-        escrow_data[OFF_STATUS] = STATUS_REFUNDED;
-        drop(escrow_data);
+        invoke_signed(&create_ix, &[payer, reputation], &[signer])?;
 
-        unsafe {
-            let escrow_lamports = escrow.borrow_mut_lamports_unchecked();
-            let buyer_lamports = buyer.borrow_mut_lamports_unchecked();
-            *buyer_lamports = buyer_lamports.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
-            *escrow_lamports = escrow_lamports.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
-        }
+        let mut reputation_data = reputation.try_borrow_mut_data()?;
+        write_u64(&mut reputation_data, OFF_DISC, REPUTATION_DISC);
+        write_pubkey(&mut reputation_data, REPUTATION_OFF_SELLER, seller.key());
+        reputation_data[REPUTATION_OFF_BUMP] = bump;
+        write_u64(&mut reputation_data, REPUTATION_OFF_COMPLETED, 0);
+        write_u64(&mut reputation_data, REPUTATION_OFF_DISPUTED_AGAINST, 0);
 
         Ok(())
     }
 }
 
 // ============================================================================
-// Dispute
+// Seller allowlist (`seller-allowlist` feature)
 // ============================================================================
 
-pub struct Dispute;
+#[cfg(feature = "seller-allowlist")]
+fn derive_seller_allowlist(program_id: &Pubkey) -> (Pubkey, u8) {
+    find_program_address(&[b"seller_allowlist"], program_id)
+}
 
-impl Dispute {
-    pub fn process(accounts: &[AccountInfo]) -> ProgramResult {
-        if accounts.len() < 2 {
+// Checks `allowlist` is this program's initialized allowlist PDA and names `seller`
+#[cfg(feature = "seller-allowlist")]
+fn assert_seller_allowed(program_id: &Pubkey, allowlist: &AccountInfo, seller: &Pubkey) -> ProgramResult {
+    let (expected_pda, _) = derive_seller_allowlist(program_id);
+    if allowlist.key() != &expected_pda || allowlist.owner() != program_id {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let data = allowlist.try_borrow_data()?;
+    if data.len() < ALLOWLIST_SIZE || read_u64(&data, OFF_DISC) != ALLOWLIST_DISC {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let count = (data[ALLOWLIST_OFF_COUNT] as usize).min(MAX_ALLOWED_SELLERS);
+    let sellers = &data[ALLOWLIST_OFF_SELLERS..ALLOWLIST_OFF_SELLERS + count * 32];
+    if sellers.chunks_exact(32).any(|k| k == seller) {
+        Ok(())
+    } else {
+        Err(PactError::SellerNotAllowed.into())
+    }
+}
+
+#[cfg(feature = "seller-allowlist")]
+pub struct InitSellerAllowlist;
+
+#[cfg(feature = "seller-allowlist")]
+impl InitSellerAllowlist {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        // Accounts: authority, allowlist, system_program
+        // There is one allowlist per deployment, created by the operator
+        // compiled into the build.
+        if accounts.len() < 3 {
             return Err(ProgramError::NotEnoughAccountKeys);
         }
 
         let authority = &accounts[0];
-        let escrow = &accounts[1];
+        let allowlist = &accounts[1];
+        let system_program = &accounts[2];
+
+        assert_distinct(&[authority.key(), allowlist.key()])?;
 
         if !authority.is_signer() {
             return Err(ProgramError::MissingRequiredSignature);
         }
-
-        let mut escrow_data = escrow.try_borrow_mut_data()?;
-
-        let disc = read_u64(&escrow_data, OFF_DISC);
-        if disc != ESCROW_DISC {
+        if authority.key() != &crate::SELLER_ALLOWLIST_OPERATOR {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
-        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
+        let (expected_pda, bump) = derive_seller_allowlist(program_id);
+        if allowlist.key() != &expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
 
-        let is_buyer = authority.key() == &stored_buyer;
-        let is_seller = authority.key() == &stored_seller;
+        let rent = Rent::get()?;
+        let lamports = rent.minimum_balance(ALLOWLIST_SIZE);
 
-        if !is_buyer && !is_seller {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let bump_bytes = [bump];
+        let signer_seeds = [
+            Seed::from(b"seller_allowlist".as_slice()),
+            Seed::from(bump_bytes.as_ref()),
+        ];
+        let signer = Signer::from(&signer_seeds);
 
-        let status = escrow_data[OFF_STATUS];
-        // Can only dispute Active or Delivered
-        if status != STATUS_ACTIVE && status != STATUS_DELIVERED {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let mut create_data = [0u8; 52];
+        create_data[0..4].copy_from_slice(&0u32.to_le_bytes());
+        create_data[4..12].copy_from_slice(&lamports.to_le_bytes());
+        create_data[12..20].copy_from_slice(&(ALLOWLIST_SIZE as u64).to_le_bytes());
+        create_data[20..52].copy_from_slice(program_id);
 
-        // Set dispute flag
-        if is_buyer {
-            escrow_data[OFF_FLAGS] |= FLAG_BUYER_DISPUTED;
-        } else {
-            escrow_data[OFF_FLAGS] |= FLAG_SELLER_DISPUTED;
-        }
-        escrow_data[OFF_STATUS] = STATUS_DISPUTED;
+        let create_accounts = [
+            AccountMeta::writable_signer(authority.key()),
+            AccountMeta::writable_signer(allowlist.key()),
+        ];
+
+        let create_ix = Instruction {
+            program_id: system_program.key(),
+            accounts: &create_accounts,
+            data: &create_data,
+        };
+
+        invoke_signed(&create_ix, &[authority, allowlist], &[signer])?;
+
+        let mut allowlist_data = allowlist.try_borrow_mut_data()?;
+        write_u64(&mut allowlist_data, OFF_DISC, ALLOWLIST_DISC);
+        write_pubkey(&mut allowlist_data, ALLOWLIST_OFF_AUTHORITY, authority.key());
+        allowlist_data[ALLOWLIST_OFF_BUMP] = bump;
+        allowlist_data[ALLOWLIST_OFF_COUNT] = 0;
 
         Ok(())
     }
 }
 
-// ============================================================================
-// Arbitrate
-// ============================================================================
-
-pub struct Arbitrate;
+#[cfg(feature = "seller-allowlist")]
+pub struct SetSellerAllowed;
 
-impl Arbitrate {
-    pub fn process(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
-        if accounts.len() < 4 {
+#[cfg(feature = "seller-allowlist")]
+impl SetSellerAllowed {
+    pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        // Accounts: authority, allowlist
+        if accounts.len() < 2 {
             return Err(ProgramError::NotEnoughAccountKeys);
         }
 
-        let arbitrator = &accounts[0];
-        let buyer = &accounts[1];
-        let seller = &accounts[2];
-        let escrow = &accounts[3];
+        let authority = &accounts[0];
+        let allowlist = &accounts[1];
 
-        if !arbitrator.is_signer() {
-            return Err(ProgramError::MissingRequiredSignature);
-        }
+        assert_distinct(&[authority.key(), allowlist.key()])?;
 
-        // Decision: 0 = refund, 1 = release
-        if data.is_empty() {
+        // Parse instruction data: seller(32) + allowed(1)
+        if data.len() < 33 {
             return Err(ProgramError::InvalidInstructionData);
         }
-        let decision = data[0];
-
-        let mut escrow_data = escrow.try_borrow_mut_data()?;
-
-        let disc = read_u64(&escrow_data, OFF_DISC);
-        if disc != ESCROW_DISC {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let stored_buyer = read_pubkey(&escrow_data, OFF_BUYER);
-        let stored_seller = read_pubkey(&escrow_data, OFF_SELLER);
-        let stored_arbitrator = read_pubkey(&escrow_data, OFF_ARBITRATOR);
-
-        if arbitrator.key() != &stored_arbitrator {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        if buyer.key() != &stored_buyer || seller.key() != &stored_seller {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        let seller = read_pubkey(data, 0);
+        let allowed = data[32] != 0;
 
-        // Check arbitrator is not zero (no arbitrator set)
-        if stored_arbitrator == [0u8; 32] {
-            return Err(ProgramError::InvalidAccountData);
+        if allowlist.owner() != program_id {
+            return Err(ProgramError::InvalidAccountOwner);
         }
 
-        let status = escrow_data[OFF_STATUS];
-        if status != STATUS_DISPUTED {
+        let mut allowlist_data = allowlist.try_borrow_mut_data()?;
+        if allowlist_data.len() < ALLOWLIST_SIZE || read_u64(&allowlist_data, OFF_DISC) != ALLOWLIST_DISC {
             return Err(ProgramError::InvalidAccountData);
         }
+        assert_signer_at(authority, &allowlist_data, ALLOWLIST_OFF_AUTHORITY)?;
 
-        let amount = read_u64(&escrow_data, OFF_AMOUNT);
-
-        if decision == 0 {
-            // Refund to buyer
-            escrow_data[OFF_STATUS] = STATUS_REFUNDED;
-            drop(escrow_data);
+        let count = (allowlist_data[ALLOWLIST_OFF_COUNT] as usize).min(MAX_ALLOWED_SELLERS);
+        let position = (0..count)
+            .find(|i| pubkey_eq(&allowlist_data, ALLOWLIST_OFF_SELLERS + i * 32, &seller));
 
-            unsafe {
-                let escrow_lamports = escrow.borrow_mut_lamports_unchecked();
-                let buyer_lamports = buyer.borrow_mut_lamports_unchecked();
-                *buyer_lamports = buyer_lamports.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
-                *escrow_lamports = escrow_lamports.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+        match (allowed, position) {
+            (true, None) => {
+                if count == MAX_ALLOWED_SELLERS {
+                    return Err(ProgramError::AccountDataTooSmall);
+                }
+                write_pubkey(&mut allowlist_data, ALLOWLIST_OFF_SELLERS + count * 32, &seller);
+                allowlist_data[ALLOWLIST_OFF_COUNT] = (count + 1) as u8;
             }
-        } else {
-            // Release to seller
-            escrow_data[OFF_STATUS] = STATUS_RELEASED;
-            drop(escrow_data);
-
-            unsafe {
-                let escrow_lamports = escrow.borrow_mut_lamports_unchecked();
-                let seller_lamports = seller.borrow_mut_lamports_unchecked();
-                *seller_lamports = seller_lamports.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
-                *escrow_lamports = escrow_lamports.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+            (false, Some(i)) => {
+                // Swap-remove: move the last entry into the freed slot
+                let last = read_pubkey(&allowlist_data, ALLOWLIST_OFF_SELLERS + (count - 1) * 32);
+                write_pubkey(&mut allowlist_data, ALLOWLIST_OFF_SELLERS + i * 32, &last);
+                write_pubkey(&mut allowlist_data, ALLOWLIST_OFF_SELLERS + (count - 1) * 32, &[0u8; 32]);
+                allowlist_data[ALLOWLIST_OFF_COUNT] = (count - 1) as u8;
             }
+            // Already in the requested state
+            _ => {}
         }
 
         Ok(())
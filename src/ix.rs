@@ -0,0 +1,714 @@
+//! Instruction builders for off-chain clients (`client` feature).
+//!
+//! One function per v2 instruction, each returning the accounts in the order
+//! the handler in `instructions_v2.rs` reads them and the discriminator-
+//! prefixed data. Builders list the base accounts only: trailing accounts a
+//! handler takes in some cases (pooled funders, a fee recipient, token
+//! accounts, extra seller signers, an allowlist) are pushed onto
+//! [`Instruction::accounts`] by the caller, and CreateEscrowV2's optional
+//! settings are appended to [`Instruction::data`].
+//!
+//! `program_id` is passed in rather than read from `crate::ID`, so the same
+//! builders work against a local or test deployment.
+
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+
+use pinocchio::pubkey::Pubkey;
+
+use crate::{
+    IX_CREATE_ESCROW, IX_MARK_DELIVERED, IX_ACCEPT_DELIVERY, IX_RELEASE, IX_REFUND, IX_DISPUTE,
+    IX_ARBITRATE, IX_APPEAL, IX_ARBITRATE_FINAL, IX_EXECUTE_RULING, IX_LOOKUP_ESCROW,
+    IX_FREEZE_ON_TIMEOUT, IX_RESIZE_ESCROW, IX_ADD_FUNDS, IX_RELEASE_IF_PRICE,
+    IX_PROPOSE_SETTLEMENT, IX_RATIFY_SETTLEMENT, IX_DRY_RUN_CREATE, IX_CLOSE_ESCROW,
+    IX_SPLIT_ESCROW, IX_MARK_MILESTONE_DELIVERED, IX_ACCEPT_MILESTONE, IX_INSPECT_ESCROW,
+    IX_AUTO_RELEASE, IX_RENEW_ESCROW, IX_PARTIAL_ACCEPT_KEEP_OPEN, IX_CANCEL_ESCROW,
+    IX_REFUND_AND_CLOSE, IX_RELEASE_AND_CLOSE, IX_MARK_SEEN, IX_SIMULATE_ARBITRATE, IX_REASSIGN,
+    IX_EXPORT_STATE, IX_BATCH_ACCEPT, IX_PARTIAL_RELEASE, IX_RELEASE_MILESTONE, IX_EXTEND_TIMEOUT,
+    IX_MIGRATE_V1_TO_V2, IX_CREATE_ESCROW_BATCH, IX_TOP_UP,
+};
+#[cfg(feature = "seller-allowlist")]
+use crate::{IX_INIT_SELLER_ALLOWLIST, IX_SET_SELLER_ALLOWED};
+#[cfg(feature = "seller-reputation")]
+use crate::IX_INIT_SELLER_REPUTATION;
+
+/// An account an instruction reads, with the access it needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountMeta {
+    pub pubkey: Pubkey,
+    pub is_writable: bool,
+    pub is_signer: bool,
+}
+
+impl AccountMeta {
+    pub const fn readonly(pubkey: &Pubkey) -> Self {
+        AccountMeta { pubkey: *pubkey, is_writable: false, is_signer: false }
+    }
+
+    pub const fn writable(pubkey: &Pubkey) -> Self {
+        AccountMeta { pubkey: *pubkey, is_writable: true, is_signer: false }
+    }
+
+    pub const fn readonly_signer(pubkey: &Pubkey) -> Self {
+        AccountMeta { pubkey: *pubkey, is_writable: false, is_signer: true }
+    }
+
+    pub const fn writable_signer(pubkey: &Pubkey) -> Self {
+        AccountMeta { pubkey: *pubkey, is_writable: true, is_signer: true }
+    }
+}
+
+/// A v2 instruction ready to be converted into the client SDK's own type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+fn build(program_id: &Pubkey, accounts: Vec<AccountMeta>, discriminator: u8, args: &[&[u8]]) -> Instruction {
+    let mut data = Vec::with_capacity(1 + args.iter().map(|a| a.len()).sum::<usize>());
+    data.push(discriminator);
+    for arg in args {
+        data.extend_from_slice(arg);
+    }
+    Instruction { program_id: *program_id, accounts, data }
+}
+
+// Accounts and data are the same for CreateEscrowV2 and DryRunCreate
+#[allow(clippy::too_many_arguments)]
+fn create(
+    discriminator: u8,
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    arbitrator: &Pubkey,
+    escrow: &Pubkey,
+    system_program: &Pubkey,
+    amount: u64,
+    seed: u64,
+    timeout_seconds: u64,
+    terms_hash: &[u8; 32],
+) -> Instruction {
+    // [amount(8)] [seed(8)] [timeout_seconds(8)] [terms_hash(32)]
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::readonly(seller),
+        AccountMeta::readonly(arbitrator),
+        AccountMeta::writable(escrow),
+        AccountMeta::readonly(system_program),
+    ];
+    build(
+        program_id,
+        accounts,
+        discriminator,
+        &[&amount.to_le_bytes(), &seed.to_le_bytes(), &timeout_seconds.to_le_bytes(), terms_hash],
+    )
+}
+
+/// `escrow` is the PDA of [`crate::escrow_seeds`] for buyer, seller and `seed`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_escrow_v2(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    arbitrator: &Pubkey,
+    escrow: &Pubkey,
+    system_program: &Pubkey,
+    amount: u64,
+    seed: u64,
+    timeout_seconds: u64,
+    terms_hash: &[u8; 32],
+) -> Instruction {
+    create(
+        IX_CREATE_ESCROW, program_id, buyer, seller, arbitrator, escrow, system_program, amount,
+        seed, timeout_seconds, terms_hash,
+    )
+}
+
+/// Same accounts and data as [`create_escrow_v2`]; returns the error code the
+/// create would fail with when simulated.
+#[allow(clippy::too_many_arguments)]
+pub fn dry_run_create(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    arbitrator: &Pubkey,
+    escrow: &Pubkey,
+    system_program: &Pubkey,
+    amount: u64,
+    seed: u64,
+    timeout_seconds: u64,
+    terms_hash: &[u8; 32],
+) -> Instruction {
+    create(
+        IX_DRY_RUN_CREATE, program_id, buyer, seller, arbitrator, escrow, system_program, amount,
+        seed, timeout_seconds, terms_hash,
+    )
+}
+
+/// `delivery_proof`: hash of the delivery evidence, required when the escrow
+/// was created with `require_delivery_proof`.
+pub fn mark_delivered(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    delivery_proof: Option<&[u8; 32]>,
+) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(seller), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_MARK_DELIVERED, &[delivery_proof.map_or(&[], |p| p)])
+}
+
+/// `terms`: the raw terms to check against the stored `terms_hash`, or empty
+/// to skip the check.
+pub fn accept_delivery(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    terms: &[u8],
+) -> Instruction {
+    // The buyer receives any late-delivery penalty
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_ACCEPT_DELIVERY, &[terms])
+}
+
+/// `authority` is the buyer or the escrow's releaser. `terms` as for
+/// [`accept_delivery`].
+pub fn release_v2(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    terms: &[u8],
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::readonly_signer(authority),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_RELEASE, &[terms])
+}
+
+/// `authority` is the buyer, seller or arbitrator.
+pub fn refund_v2(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+) -> Instruction {
+    build(program_id, refund_accounts(authority, buyer, seller, escrow), IX_REFUND, &[])
+}
+
+fn refund_accounts(authority: &Pubkey, buyer: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::readonly_signer(authority),
+        AccountMeta::writable(buyer),
+        AccountMeta::readonly(seller),
+        AccountMeta::writable(escrow),
+    ]
+}
+
+/// `authority` is the buyer or seller.
+pub fn dispute(program_id: &Pubkey, authority: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(authority), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_DISPUTE, &[])
+}
+
+/// `decision`: 0 = refund, 1 = release. `rationale_hash` is required when the
+/// escrow was created with `require_rationale`.
+pub fn arbitrate(
+    program_id: &Pubkey,
+    arbitrator: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    decision: u8,
+    rationale_hash: Option<&[u8; 32]>,
+) -> Instruction {
+    let rationale: &[u8] = rationale_hash.map_or(&[], |h| h);
+    build(program_id, arbitrate_accounts(arbitrator, buyer, seller, escrow), IX_ARBITRATE, &[&[decision], rationale])
+}
+
+/// Rules a split: `seller_bps` of the amount after the arbitrator's fee goes
+/// to the seller, the rest back to the buyer. Native SOL escrows only.
+pub fn arbitrate_split(
+    program_id: &Pubkey,
+    arbitrator: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    seller_bps: u16,
+    rationale_hash: Option<&[u8; 32]>,
+) -> Instruction {
+    let rationale: &[u8] = rationale_hash.map_or(&[], |h| h);
+    build(
+        program_id,
+        arbitrate_accounts(arbitrator, buyer, seller, escrow),
+        IX_ARBITRATE,
+        &[&seller_bps.to_le_bytes(), rationale],
+    )
+}
+
+fn arbitrate_accounts(arbitrator: &Pubkey, buyer: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Vec<AccountMeta> {
+    // The arbitrator is paid its fee out of the escrow
+    vec![
+        AccountMeta::writable_signer(arbitrator),
+        AccountMeta::writable(buyer),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ]
+}
+
+/// Previews [`arbitrate`] without a signature; the ruling is read from return
+/// data.
+pub fn simulate_arbitrate(
+    program_id: &Pubkey,
+    arbitrator: &Pubkey,
+    escrow: &Pubkey,
+    decision: u8,
+) -> Instruction {
+    let accounts = vec![AccountMeta::readonly(arbitrator), AccountMeta::readonly(escrow)];
+    build(program_id, accounts, IX_SIMULATE_ARBITRATE, &[&[decision]])
+}
+
+/// `authority` is the buyer or seller.
+pub fn appeal(program_id: &Pubkey, authority: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(authority), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_APPEAL, &[])
+}
+
+/// `decision`: 0 = refund, 1 = release.
+pub fn arbitrate_final(
+    program_id: &Pubkey,
+    appeal_arbitrator: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    decision: u8,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::readonly_signer(appeal_arbitrator),
+        AccountMeta::writable(buyer),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_ARBITRATE_FINAL, &[&[decision]])
+}
+
+/// Permissionless crank for an unappealed ruling.
+pub fn execute_ruling(program_id: &Pubkey, buyer: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable(buyer),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_EXECUTE_RULING, &[])
+}
+
+pub fn lookup_escrow(
+    program_id: &Pubkey,
+    escrow: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    seed: u64,
+) -> Instruction {
+    let accounts = vec![AccountMeta::readonly(escrow)];
+    build(program_id, accounts, IX_LOOKUP_ESCROW, &[buyer, seller, &seed.to_le_bytes()])
+}
+
+pub fn freeze_on_timeout(program_id: &Pubkey, escrow: &Pubkey) -> Instruction {
+    build(program_id, vec![AccountMeta::writable(escrow)], IX_FREEZE_ON_TIMEOUT, &[])
+}
+
+/// The buyer pays the rent for the added space.
+pub fn resize_escrow(program_id: &Pubkey, buyer: &Pubkey, escrow: &Pubkey, system_program: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable(escrow),
+        AccountMeta::readonly(system_program),
+    ];
+    build(program_id, accounts, IX_RESIZE_ESCROW, &[])
+}
+
+pub fn add_funds(
+    program_id: &Pubkey,
+    funder: &Pubkey,
+    escrow: &Pubkey,
+    system_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(funder),
+        AccountMeta::writable(escrow),
+        AccountMeta::readonly(system_program),
+    ];
+    build(program_id, accounts, IX_ADD_FUNDS, &[&amount.to_le_bytes()])
+}
+
+/// Permissionless: the stored oracle condition decides.
+pub fn release_if_price(
+    program_id: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    price_oracle: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+        AccountMeta::readonly(price_oracle),
+    ];
+    build(program_id, accounts, IX_RELEASE_IF_PRICE, &[])
+}
+
+pub fn propose_settlement(program_id: &Pubkey, arbitrator: &Pubkey, escrow: &Pubkey, buyer_bps: u16) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(arbitrator), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_PROPOSE_SETTLEMENT, &[&buyer_bps.to_le_bytes()])
+}
+
+pub fn ratify_settlement(program_id: &Pubkey, buyer: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable_signer(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_RATIFY_SETTLEMENT, &[])
+}
+
+/// `seller-allowlist` builds only; `authority` must be
+/// [`crate::SELLER_ALLOWLIST_OPERATOR`].
+#[cfg(feature = "seller-allowlist")]
+pub fn init_seller_allowlist(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    allowlist: &Pubkey,
+    system_program: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(authority),
+        AccountMeta::writable(allowlist),
+        AccountMeta::readonly(system_program),
+    ];
+    build(program_id, accounts, IX_INIT_SELLER_ALLOWLIST, &[])
+}
+
+/// `seller-allowlist` builds only.
+#[cfg(feature = "seller-allowlist")]
+pub fn set_seller_allowed(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    allowlist: &Pubkey,
+    seller: &Pubkey,
+    allowed: bool,
+) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(authority), AccountMeta::writable(allowlist)];
+    build(program_id, accounts, IX_SET_SELLER_ALLOWED, &[seller, &[allowed as u8]])
+}
+
+/// Closed by the buyer; a keeper closing in the buyer's place is appended
+/// with the buyer left unsigned. A token escrow also needs its vault and
+/// token program appended, so the vault is closed with it.
+pub fn close_escrow(program_id: &Pubkey, buyer: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![AccountMeta::writable_signer(buyer), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_CLOSE_ESCROW, &[])
+}
+
+/// Carves `carve_amount` out of `parent` into a new escrow with its own
+/// seller and arbitrator.
+#[allow(clippy::too_many_arguments)]
+pub fn split_escrow(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    parent: &Pubkey,
+    seller: &Pubkey,
+    arbitrator: &Pubkey,
+    child: &Pubkey,
+    system_program: &Pubkey,
+    carve_amount: u64,
+    seed: u64,
+    timeout_seconds: u64,
+    terms_hash: &[u8; 32],
+) -> Instruction {
+    // [carve_amount(8)] [seed(8)] [timeout_seconds(8)] [terms_hash(32)]
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable(parent),
+        AccountMeta::readonly(seller),
+        AccountMeta::readonly(arbitrator),
+        AccountMeta::writable(child),
+        AccountMeta::readonly(system_program),
+    ];
+    build(
+        program_id,
+        accounts,
+        IX_SPLIT_ESCROW,
+        &[&carve_amount.to_le_bytes(), &seed.to_le_bytes(), &timeout_seconds.to_le_bytes(), terms_hash],
+    )
+}
+
+pub fn mark_milestone_delivered(program_id: &Pubkey, seller: &Pubkey, escrow: &Pubkey, index: u8) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(seller), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_MARK_MILESTONE_DELIVERED, &[&[index]])
+}
+
+pub fn accept_milestone(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    index: u8,
+) -> Instruction {
+    build(program_id, buyer_settle_accounts(buyer, seller, escrow), IX_ACCEPT_MILESTONE, &[&[index]])
+}
+
+pub fn release_milestone(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    index: u8,
+) -> Instruction {
+    build(program_id, buyer_settle_accounts(buyer, seller, escrow), IX_RELEASE_MILESTONE, &[&[index]])
+}
+
+pub fn partial_accept_keep_open(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    accepted_amount: u64,
+) -> Instruction {
+    build(
+        program_id,
+        buyer_settle_accounts(buyer, seller, escrow),
+        IX_PARTIAL_ACCEPT_KEEP_OPEN,
+        &[&accepted_amount.to_le_bytes()],
+    )
+}
+
+pub fn partial_release(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    release_amount: u64,
+) -> Instruction {
+    build(
+        program_id,
+        buyer_settle_accounts(buyer, seller, escrow),
+        IX_PARTIAL_RELEASE,
+        &[&release_amount.to_le_bytes()],
+    )
+}
+
+// The buyer signs and the seller is paid
+fn buyer_settle_accounts(buyer: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::readonly_signer(buyer),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ]
+}
+
+pub fn inspect_escrow(program_id: &Pubkey, escrow: &Pubkey) -> Instruction {
+    build(program_id, vec![AccountMeta::readonly(escrow)], IX_INSPECT_ESCROW, &[])
+}
+
+/// Permissionless once the acceptance window has passed.
+pub fn auto_release(program_id: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![AccountMeta::writable(seller), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_AUTO_RELEASE, &[])
+}
+
+/// Permissionless crank for subscription periods.
+pub fn renew_escrow(program_id: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![AccountMeta::writable(seller), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_RENEW_ESCROW, &[])
+}
+
+/// `seller-reputation` builds only.
+#[cfg(feature = "seller-reputation")]
+pub fn init_seller_reputation(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    seller: &Pubkey,
+    reputation: &Pubkey,
+    system_program: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(payer),
+        AccountMeta::readonly(seller),
+        AccountMeta::writable(reputation),
+        AccountMeta::readonly(system_program),
+    ];
+    build(program_id, accounts, IX_INIT_SELLER_REPUTATION, &[])
+}
+
+/// The seller keeps any vested cancellation fee.
+pub fn cancel_escrow(program_id: &Pubkey, buyer: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_CANCEL_ESCROW, &[])
+}
+
+/// Accounts as [`refund_v2`]; a rent treasury, if the escrow pays its rent
+/// there, is appended by the caller.
+pub fn refund_and_close(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+) -> Instruction {
+    build(program_id, refund_accounts(authority, buyer, seller, escrow), IX_REFUND_AND_CLOSE, &[])
+}
+
+/// Accounts as [`release_v2`]. The rent goes back to the buyer by default,
+/// so `authority` is writable; any other rent recipient is appended.
+pub fn release_and_close(program_id: &Pubkey, authority: &Pubkey, seller: &Pubkey, escrow: &Pubkey) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(authority),
+        AccountMeta::writable(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_RELEASE_AND_CLOSE, &[])
+}
+
+/// `party` is the buyer or seller; `bits` are the unseen bits to clear.
+pub fn mark_seen(program_id: &Pubkey, party: &Pubkey, escrow: &Pubkey, bits: u8) -> Instruction {
+    let accounts = vec![AccountMeta::readonly_signer(party), AccountMeta::writable(escrow)];
+    build(program_id, accounts, IX_MARK_SEEN, &[&[bits]])
+}
+
+/// Hands an undelivered escrow to `new_seller` on new terms; all three
+/// parties sign.
+pub fn reassign(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    old_seller: &Pubkey,
+    new_seller: &Pubkey,
+    escrow: &Pubkey,
+    terms_hash: &[u8; 32],
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::readonly_signer(buyer),
+        AccountMeta::readonly_signer(old_seller),
+        AccountMeta::readonly_signer(new_seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_REASSIGN, &[terms_hash])
+}
+
+/// Reads one chunk of the escrow account starting at `offset`.
+pub fn export_state(program_id: &Pubkey, escrow: &Pubkey, offset: u16) -> Instruction {
+    build(program_id, vec![AccountMeta::readonly(escrow)], IX_EXPORT_STATE, &[&offset.to_le_bytes()])
+}
+
+/// Accepts each `(seller, escrow)` for the same buyer in one instruction.
+pub fn batch_accept(program_id: &Pubkey, buyer: &Pubkey, escrows: &[(Pubkey, Pubkey)]) -> Instruction {
+    let accounts = escrows
+        .iter()
+        .flat_map(|(seller, escrow)| {
+            [
+                AccountMeta::writable_signer(buyer),
+                AccountMeta::writable(seller),
+                AccountMeta::writable(escrow),
+            ]
+        })
+        .collect();
+    build(program_id, accounts, IX_BATCH_ACCEPT, &[])
+}
+
+/// Both parties sign.
+pub fn extend_timeout(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    seller: &Pubkey,
+    escrow: &Pubkey,
+    extension: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::readonly_signer(buyer),
+        AccountMeta::readonly_signer(seller),
+        AccountMeta::writable(escrow),
+    ];
+    build(program_id, accounts, IX_EXTEND_TIMEOUT, &[&extension.to_le_bytes()])
+}
+
+/// Converts a v1 escrow to the v2 layout. `seed` is the one the v1 escrow was
+/// created with; the buyer pays the added rent.
+pub fn migrate_v1_to_v2(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    escrow: &Pubkey,
+    system_program: &Pubkey,
+    seed: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable(escrow),
+        AccountMeta::readonly(system_program),
+    ];
+    build(program_id, accounts, IX_MIGRATE_V1_TO_V2, &[&seed.to_le_bytes()])
+}
+
+/// One escrow of a [`create_escrow_batch`]. `escrow` is the PDA of
+/// [`crate::escrow_seeds`] for the buyer, `seller` and `seed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchCreate {
+    pub seller: Pubkey,
+    pub escrow: Pubkey,
+    pub amount: u64,
+    pub seed: u64,
+    pub timeout_seconds: u64,
+    pub terms_hash: [u8; 32],
+}
+
+/// Creates and funds up to 8 escrows for one buyer, each with base terms and
+/// no arbitrator. All of them are created or none is.
+pub fn create_escrow_batch(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    system_program: &Pubkey,
+    escrows: &[BatchCreate],
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::writable_signer(buyer), AccountMeta::readonly(system_program)];
+    let mut data = Vec::with_capacity(2 + escrows.len() * 88);
+    data.push(IX_CREATE_ESCROW_BATCH);
+    data.push(escrows.len() as u8);
+    for record in escrows {
+        accounts.push(AccountMeta::readonly(&record.seller));
+        accounts.push(AccountMeta::writable(&record.escrow));
+        data.extend_from_slice(&record.seller);
+        data.extend_from_slice(&record.amount.to_le_bytes());
+        data.extend_from_slice(&record.seed.to_le_bytes());
+        data.extend_from_slice(&record.timeout_seconds.to_le_bytes());
+        data.extend_from_slice(&record.terms_hash);
+    }
+    Instruction { program_id: *program_id, accounts, data }
+}
+
+/// AddFunds for the escrow's own buyer only.
+pub fn top_up(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    escrow: &Pubkey,
+    system_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::writable_signer(buyer),
+        AccountMeta::writable(escrow),
+        AccountMeta::readonly(system_program),
+    ];
+    build(program_id, accounts, IX_TOP_UP, &[&amount.to_le_bytes()])
+}
@@ -2,35 +2,179 @@
 
 use pinocchio::{
     account_info::AccountInfo,
-    entrypoint,
-    nostd_panic_handler,
     program_error::ProgramError,
     pubkey::Pubkey,
     ProgramResult,
 };
 
-entrypoint!(process_instruction);
-nostd_panic_handler!();
+// Programs that depend on this crate for CPI must not pull in a second
+// entrypoint or panic handler. Off-chain the handler macro only links std,
+// which a `no-entrypoint` host build (a dependent program's tests) still
+// needs to compile the cdylib.
+#[cfg(not(feature = "no-entrypoint"))]
+pinocchio::entrypoint!(process_instruction);
+#[cfg(any(not(feature = "no-entrypoint"), not(target_os = "solana")))]
+pinocchio::nostd_panic_handler!();
 
+pub mod error;
 pub mod instructions;
-pub use instructions::*;
+pub mod instructions_v2;
+#[cfg(feature = "client")]
+pub mod export;
+#[cfg(feature = "client")]
+pub mod ix;
+pub mod status;
+#[cfg(feature = "client")]
+pub mod view;
+#[cfg(feature = "borsh")]
+pub mod pact_client;
+pub use instructions_v2::*;
+
+use error::PactError;
+
+#[cfg(feature = "cpi")]
+pub mod cpi;
 
 // Program ID: S64L6x9bZqDewocv5MrCLeTAq1MKatLqrWfrLpdcDKM
+// v1 and v2 escrows are both served from the v1 deployment, which already
+// owns the live v1 accounts
 pub const ID: Pubkey = [6, 109, 61, 24, 47, 212, 198, 93, 67, 166, 114, 173, 203, 164, 21, 164, 119, 215, 219, 39, 121, 169, 222, 136, 239, 59, 180, 118, 32, 77, 105, 48];
 
-fn process_instruction(
-    _program_id: &Pubkey,
+// The only key InitSellerAllowlist accepts as the allowlist's authority, so
+// nobody can front-run the operator to it. Set it to the operator's key
+// before building a curated deployment; the all-zero default can't sign, so
+// an unconfigured build has no allowlist and refuses every create.
+#[cfg(feature = "seller-allowlist")]
+pub const SELLER_ALLOWLIST_OPERATOR: Pubkey = [0u8; 32];
+
+// Instruction discriminators
+pub const IX_CREATE_ESCROW: u8 = 0;
+pub const IX_MARK_DELIVERED: u8 = 1;
+pub const IX_ACCEPT_DELIVERY: u8 = 2;
+pub const IX_RELEASE: u8 = 3;
+pub const IX_REFUND: u8 = 4;
+pub const IX_DISPUTE: u8 = 5;
+pub const IX_ARBITRATE: u8 = 6;
+pub const IX_APPEAL: u8 = 7;
+pub const IX_ARBITRATE_FINAL: u8 = 8;
+pub const IX_EXECUTE_RULING: u8 = 9;
+pub const IX_LOOKUP_ESCROW: u8 = 10;
+pub const IX_FREEZE_ON_TIMEOUT: u8 = 11;
+pub const IX_RESIZE_ESCROW: u8 = 12;
+pub const IX_ADD_FUNDS: u8 = 13;
+pub const IX_RELEASE_IF_PRICE: u8 = 14;
+pub const IX_PROPOSE_SETTLEMENT: u8 = 15;
+pub const IX_RATIFY_SETTLEMENT: u8 = 16;
+pub const IX_DRY_RUN_CREATE: u8 = 17;
+#[cfg(feature = "seller-allowlist")]
+pub const IX_INIT_SELLER_ALLOWLIST: u8 = 18;
+#[cfg(feature = "seller-allowlist")]
+pub const IX_SET_SELLER_ALLOWED: u8 = 19;
+pub const IX_CLOSE_ESCROW: u8 = 20;
+pub const IX_SPLIT_ESCROW: u8 = 21;
+pub const IX_MARK_MILESTONE_DELIVERED: u8 = 22;
+pub const IX_ACCEPT_MILESTONE: u8 = 23;
+pub const IX_INSPECT_ESCROW: u8 = 24;
+pub const IX_AUTO_RELEASE: u8 = 25;
+pub const IX_RENEW_ESCROW: u8 = 26;
+#[cfg(feature = "seller-reputation")]
+pub const IX_INIT_SELLER_REPUTATION: u8 = 27;
+pub const IX_PARTIAL_ACCEPT_KEEP_OPEN: u8 = 28;
+pub const IX_CANCEL_ESCROW: u8 = 29;
+pub const IX_REFUND_AND_CLOSE: u8 = 30;
+pub const IX_RELEASE_AND_CLOSE: u8 = 31;
+pub const IX_MARK_SEEN: u8 = 32;
+pub const IX_SIMULATE_ARBITRATE: u8 = 33;
+pub const IX_REASSIGN: u8 = 34;
+pub const IX_EXPORT_STATE: u8 = 35;
+pub const IX_BATCH_ACCEPT: u8 = 36;
+pub const IX_PARTIAL_RELEASE: u8 = 37;
+pub const IX_RELEASE_MILESTONE: u8 = 38;
+pub const IX_EXTEND_TIMEOUT: u8 = 39;
+pub const IX_MIGRATE_V1_TO_V2: u8 = 40;
+pub const IX_CREATE_ESCROW_BATCH: u8 = 41;
+pub const IX_TOP_UP: u8 = 42;
+
+// v1 kept discriminators 0-2 for CreateEscrow, Release and Refund. The router
+// sends those to the v1 handlers when the call has a v1 shape: CreateEscrow's
+// data is exactly amount + seed (every v2 create is longer), and Release and
+// Refund name an 81-byte escrow third. Both layouts share the escrow
+// discriminator, so the size is what tells them apart.
+const V1_CREATE_DATA_LEN: usize = 16;
+const V1_ESCROW_SIZE: usize = 81;
+const V1_ESCROW_INDEX: usize = 2;
+
+fn is_v1_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> bool {
+    accounts.get(V1_ESCROW_INDEX).is_some_and(|escrow| {
+        escrow.owner() == program_id && escrow.data_len() == V1_ESCROW_SIZE
+    })
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
     let (discriminator, data) = instruction_data
         .split_first()
-        .ok_or(ProgramError::InvalidInstructionData)?;
+        .ok_or(PactError::MissingDiscriminator)?;
 
-    match discriminator {
-        0 => CreateEscrow::try_from((data, accounts))?.process(),
-        1 => Release::try_from(accounts)?.process(),
-        2 => Refund::try_from(accounts)?.process(),
+    match *discriminator {
+        IX_CREATE_ESCROW if data.len() == V1_CREATE_DATA_LEN => {
+            instructions::CreateEscrow::try_from((data, accounts))?.process()
+        }
+        IX_MARK_DELIVERED if is_v1_escrow(program_id, accounts) => {
+            instructions::Release::try_from(accounts)?.process()
+        }
+        IX_ACCEPT_DELIVERY if is_v1_escrow(program_id, accounts) => {
+            instructions::Refund::try_from(accounts)?.process()
+        }
+        IX_CREATE_ESCROW => CreateEscrowV2::process(program_id, accounts, data),
+        IX_MARK_DELIVERED => MarkDelivered::process(program_id, accounts, data),
+        IX_ACCEPT_DELIVERY => AcceptDelivery::process(program_id, accounts, data),
+        IX_RELEASE => ReleaseV2::process(program_id, accounts, data),
+        IX_REFUND => RefundV2::process(program_id, accounts),
+        IX_DISPUTE => Dispute::process(program_id, accounts),
+        IX_ARBITRATE => Arbitrate::process(program_id, accounts, data),
+        IX_APPEAL => Appeal::process(program_id, accounts),
+        IX_ARBITRATE_FINAL => ArbitrateFinal::process(program_id, accounts, data),
+        IX_EXECUTE_RULING => ExecuteRuling::process(program_id, accounts),
+        IX_LOOKUP_ESCROW => LookupEscrow::process(program_id, accounts, data),
+        IX_FREEZE_ON_TIMEOUT => FreezeOnTimeout::process(program_id, accounts),
+        IX_RESIZE_ESCROW => ResizeEscrow::process(program_id, accounts),
+        IX_ADD_FUNDS => AddFunds::process(program_id, accounts, data),
+        IX_RELEASE_IF_PRICE => ReleaseIfPrice::process(program_id, accounts),
+        IX_PROPOSE_SETTLEMENT => ProposeSettlement::process(program_id, accounts, data),
+        IX_RATIFY_SETTLEMENT => RatifySettlement::process(program_id, accounts),
+        IX_DRY_RUN_CREATE => DryRunCreate::process(program_id, accounts, data),
+        #[cfg(feature = "seller-allowlist")]
+        IX_INIT_SELLER_ALLOWLIST => InitSellerAllowlist::process(program_id, accounts),
+        #[cfg(feature = "seller-allowlist")]
+        IX_SET_SELLER_ALLOWED => SetSellerAllowed::process(program_id, accounts, data),
+        IX_CLOSE_ESCROW => CloseEscrow::process(program_id, accounts),
+        IX_SPLIT_ESCROW => SplitEscrow::process(program_id, accounts, data),
+        IX_MARK_MILESTONE_DELIVERED => MarkMilestoneDelivered::process(program_id, accounts, data),
+        IX_ACCEPT_MILESTONE => AcceptMilestone::process(program_id, accounts, data),
+        IX_INSPECT_ESCROW => InspectEscrow::process(program_id, accounts),
+        IX_AUTO_RELEASE => AutoRelease::process(program_id, accounts),
+        IX_RENEW_ESCROW => RenewEscrow::process(program_id, accounts),
+        #[cfg(feature = "seller-reputation")]
+        IX_INIT_SELLER_REPUTATION => InitSellerReputation::process(program_id, accounts),
+        IX_PARTIAL_ACCEPT_KEEP_OPEN => PartialAcceptKeepOpen::process(program_id, accounts, data),
+        IX_CANCEL_ESCROW => CancelEscrow::process(program_id, accounts),
+        IX_REFUND_AND_CLOSE => RefundAndClose::process(program_id, accounts),
+        IX_RELEASE_AND_CLOSE => ReleaseAndClose::process(program_id, accounts),
+        IX_MARK_SEEN => MarkSeen::process(program_id, accounts, data),
+        IX_SIMULATE_ARBITRATE => SimulateArbitrate::process(program_id, accounts, data),
+        IX_REASSIGN => Reassign::process(program_id, accounts, data),
+        IX_EXPORT_STATE => ExportState::process(program_id, accounts, data),
+        IX_BATCH_ACCEPT => BatchAccept::process(program_id, accounts),
+        IX_PARTIAL_RELEASE => PartialRelease::process(program_id, accounts, data),
+        IX_RELEASE_MILESTONE => ReleaseMilestone::process(program_id, accounts, data),
+        IX_EXTEND_TIMEOUT => ExtendTimeout::process(program_id, accounts, data),
+        IX_MIGRATE_V1_TO_V2 => MigrateV1ToV2::process(program_id, accounts, data),
+        IX_CREATE_ESCROW_BATCH => CreateEscrowBatch::process(program_id, accounts, data),
+        IX_TOP_UP => TopUp::process(program_id, accounts, data),
         _ => Err(ProgramError::InvalidInstructionData),
     }
 }
@@ -0,0 +1,219 @@
+//! Borsh types for off-chain clients (`borsh` feature).
+//!
+//! [`EscrowV2`] reads a fetched v2 escrow account into named fields, and
+//! [`Instruction`] writes the instruction data the v2 router expects: the
+//! discriminator byte, then the handler's arguments. Both follow the on-chain
+//! layout byte for byte, so integrators never touch offsets. The on-chain
+//! program itself doesn't use this module.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use borsh::{io, BorshDeserialize, BorshSerialize};
+use pinocchio::pubkey::Pubkey;
+
+/// "PACTESCR" as a little-endian u64, the first field of every v2 escrow.
+pub const ESCROW_DISCRIMINATOR: u64 = 0x5041435445534352;
+
+/// Size of a v2 escrow account; every field of [`EscrowV2`] together.
+pub const ESCROW_V2_LEN: usize = 1225;
+
+/// One entry of an escrow's funder list.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Funder {
+    pub key: Pubkey,
+    pub amount: u64,
+}
+
+/// A v2 escrow account, field for field in on-chain order.
+///
+/// Byte-sized options keep their raw `u8` so any stored value deserializes;
+/// non-zero means set.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct EscrowV2 {
+    pub discriminator: u64,
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbitrator: Pubkey,
+    /// All zeroes for a native SOL escrow
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub created_at: u64,
+    pub timeout_seconds: u64,
+    pub terms_hash: [u8; 32],
+    pub status: u8,
+    pub flags: u8,
+    pub bump: u8,
+    pub appeal_arbitrator: Pubkey,
+    pub ruling: u8,
+    pub ruled_at: u64,
+    pub delivered_at: u64,
+    pub min_dispute_delay: u64,
+    pub timeout_mode: u8,
+    pub funder_count: u8,
+    pub funders: [Funder; 8],
+    pub delivery_proof: [u8; 32],
+    pub price_oracle: Pubkey,
+    pub price_threshold: i64,
+    pub funded_amount: u64,
+    pub proposed_buyer_bps: u16,
+    pub proposed_at: u64,
+    pub quote_mint: Pubkey,
+    pub quote_amount: u64,
+    pub disputed_at: u64,
+    pub seed: u64,
+    pub milestone_count: u8,
+    pub milestone_delivered: u8,
+    pub milestone_settled: u8,
+    pub milestone_amounts: [u64; 8],
+    pub callback_program: Pubkey,
+    pub acceptance_window_seconds: u64,
+    pub periods_remaining: u16,
+    pub period_amount: u64,
+    pub cancel_fee_cap: u64,
+    pub cancel_fee_ramp_seconds: u64,
+    pub require_rationale: u8,
+    pub rationale_hash: [u8; 32],
+    pub notify: u8,
+    pub seller_threshold: u8,
+    pub seller_signer_count: u8,
+    pub seller_signers: [Pubkey; 5],
+    pub strict_acceptance: u8,
+    pub rent_paid: u64,
+    pub updated_at: u64,
+    pub original_seller: Pubkey,
+    pub delivery_deadline: u64,
+    pub late_penalty_bps: u16,
+    pub mint_decimals: u8,
+    pub policy: u8,
+    pub rent_recipient: u8,
+    pub rent_treasury: Pubkey,
+    pub releaser: Pubkey,
+    pub arbitrator_fee: u64,
+    pub fee_recipient: Pubkey,
+    pub platform_fee_bps: u16,
+    pub arbitration_window: u64,
+}
+
+impl EscrowV2 {
+    /// Reads an escrow from its account data. Fails on a foreign
+    /// discriminator or an account that isn't exactly the v2 size, e.g. one
+    /// still awaiting ResizeEscrow.
+    pub fn unpack(data: &[u8]) -> io::Result<Self> {
+        if data.len() != ESCROW_V2_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a v2 escrow size"));
+        }
+        let escrow = Self::try_from_slice(data)?;
+        if escrow.discriminator != ESCROW_DISCRIMINATOR {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a Pact escrow"));
+        }
+        Ok(escrow)
+    }
+
+    /// The funders actually in use.
+    pub fn active_funders(&self) -> &[Funder] {
+        &self.funders[..(self.funder_count as usize).min(self.funders.len())]
+    }
+}
+
+/// v2 instruction data. Each variant serializes to its discriminator byte
+/// followed by its fields, as the router reads them.
+///
+/// `CreateEscrow`, `DryRunCreate` and `SplitEscrow` carry the base terms
+/// only; CreateEscrowV2's optional settings after `terms_hash` take their
+/// defaults when absent and can be appended to the serialized bytes.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum Instruction {
+    CreateEscrow { amount: u64, seed: u64, timeout_seconds: u64, terms_hash: [u8; 32] } = 0,
+    /// `delivery_proof` all zeroes = none
+    MarkDelivered { delivery_proof: [u8; 32] } = 1,
+    /// The raw terms may be appended to the packed bytes, to be checked
+    /// against `terms_hash`
+    AcceptDelivery = 2,
+    /// Takes the same optional terms suffix as `AcceptDelivery`
+    Release = 3,
+    Refund = 4,
+    Dispute = 5,
+    /// `decision`: 0 = refund, 1 = release. `rationale_hash` all zeroes = none.
+    /// A split ruling is packed by [`Instruction::pack_arbitrate_split`]
+    Arbitrate { decision: u8, rationale_hash: [u8; 32] } = 6,
+    Appeal = 7,
+    ArbitrateFinal { decision: u8 } = 8,
+    ExecuteRuling = 9,
+    LookupEscrow { buyer: Pubkey, seller: Pubkey, seed: u64 } = 10,
+    FreezeOnTimeout = 11,
+    ResizeEscrow = 12,
+    AddFunds { amount: u64 } = 13,
+    ReleaseIfPrice = 14,
+    ProposeSettlement { buyer_bps: u16 } = 15,
+    RatifySettlement = 16,
+    DryRunCreate { amount: u64, seed: u64, timeout_seconds: u64, terms_hash: [u8; 32] } = 17,
+    /// `seller-allowlist` builds only
+    InitSellerAllowlist = 18,
+    /// `seller-allowlist` builds only
+    SetSellerAllowed { seller: Pubkey, allowed: bool } = 19,
+    CloseEscrow = 20,
+    SplitEscrow { carve_amount: u64, seed: u64, timeout_seconds: u64, terms_hash: [u8; 32] } = 21,
+    MarkMilestoneDelivered { index: u8 } = 22,
+    AcceptMilestone { index: u8 } = 23,
+    InspectEscrow = 24,
+    AutoRelease = 25,
+    RenewEscrow = 26,
+    /// `seller-reputation` builds only
+    InitSellerReputation = 27,
+    PartialAcceptKeepOpen { accepted_amount: u64 } = 28,
+    CancelEscrow = 29,
+    RefundAndClose = 30,
+    ReleaseAndClose = 31,
+    /// `bits` to clear, within the signer's own
+    MarkSeen { bits: u8 } = 32,
+    SimulateArbitrate { decision: u8 } = 33,
+    Reassign { terms_hash: [u8; 32] } = 34,
+    ExportState { offset: u16 } = 35,
+    BatchAccept = 36,
+    PartialRelease { release_amount: u64 } = 37,
+    ReleaseMilestone { index: u8 } = 38,
+    ExtendTimeout { extension: u64 } = 39,
+    MigrateV1ToV2 { seed: u64 } = 40,
+    /// `count` [`BatchCreateRecord`]s, at most 8, are appended to the packed
+    /// bytes
+    CreateEscrowBatch { count: u8 } = 41,
+    TopUp { amount: u64 } = 42,
+}
+
+/// One escrow of a `CreateEscrowBatch`, in the order the handler reads it.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchCreateRecord {
+    pub seller: Pubkey,
+    pub amount: u64,
+    pub seed: u64,
+    pub timeout_seconds: u64,
+    pub terms_hash: [u8; 32],
+}
+
+impl Instruction {
+    /// The instruction data to send.
+    pub fn pack(&self) -> Vec<u8> {
+        borsh::to_vec(self).expect("writing to a Vec can't fail")
+    }
+
+    /// Arbitrate data for a split ruling: `seller_bps` of the amount after the
+    /// arbitrator's fee goes to the seller, the rest back to the buyer. It
+    /// shares `Arbitrate`'s discriminator, so it has no variant of its own.
+    /// `rationale_hash` all zeroes = none.
+    pub fn pack_arbitrate_split(seller_bps: u16, rationale_hash: [u8; 32]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(35);
+        data.push(6);
+        data.extend_from_slice(&seller_bps.to_le_bytes());
+        data.extend_from_slice(&rationale_hash);
+        data
+    }
+
+    /// Reads instruction data back, e.g. from a fetched transaction.
+    pub fn unpack(data: &[u8]) -> io::Result<Self> {
+        Self::try_from_slice(data)
+    }
+}
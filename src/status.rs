@@ -0,0 +1,100 @@
+//! Typed escrow status.
+//!
+//! The v2 status byte at the status offset of an escrow account. The v2
+//! handlers read and write it through this type; clients get it with the
+//! `client` feature.
+
+use core::fmt;
+
+use pinocchio::program_error::ProgramError;
+
+/// Lifecycle state of a v2 escrow, as stored in its status byte.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowStatus {
+    /// Funded, awaiting delivery
+    Active = 0,
+    /// Seller marked delivered, awaiting acceptance
+    Delivered = 1,
+    /// Buyer accepted the delivery and the seller was paid. Terminal, like
+    /// Released, which marks every other payout to the seller
+    Accepted = 2,
+    /// A party disputed, awaiting the arbitrator
+    Disputed = 3,
+    /// Paid out to the seller
+    Released = 4,
+    /// Returned to the buyer
+    Refunded = 5,
+    /// A ruling was appealed, awaiting the appeal arbitrator
+    Appealed = 6,
+}
+
+impl EscrowStatus {
+    /// Reads a stored status byte. Unknown values fail with
+    /// `InvalidAccountData` instead of being treated as any known state.
+    pub const fn from_u8(byte: u8) -> Result<Self, ProgramError> {
+        match byte {
+            0 => Ok(EscrowStatus::Active),
+            1 => Ok(EscrowStatus::Delivered),
+            2 => Ok(EscrowStatus::Accepted),
+            3 => Ok(EscrowStatus::Disputed),
+            4 => Ok(EscrowStatus::Released),
+            5 => Ok(EscrowStatus::Refunded),
+            6 => Ok(EscrowStatus::Appealed),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    /// The byte stored on chain.
+    pub const fn as_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// Accepted, Released or Refunded: no funds left to move.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            EscrowStatus::Accepted | EscrowStatus::Released | EscrowStatus::Refunded
+        )
+    }
+}
+
+/// The v2 state machine: whether an escrow in `from` may be moved to `to`.
+/// Every v2 status write after creation is checked against this.
+///
+/// Delivered reopens to Active on PartialAcceptKeepOpen. Only a delivered
+/// escrow can be Accepted. A dispute can only be settled or, once ruled,
+/// appealed. Nothing leaves a terminal state.
+pub const fn can_transition(from: EscrowStatus, to: EscrowStatus) -> bool {
+    use EscrowStatus::*;
+
+    matches!(
+        (from, to),
+        (Active, Delivered | Disputed | Released | Refunded)
+            | (Delivered, Active | Accepted | Disputed | Released | Refunded)
+            | (Disputed, Appealed | Released | Refunded)
+            | (Appealed, Released | Refunded)
+    )
+}
+
+impl TryFrom<u8> for EscrowStatus {
+    type Error = ProgramError;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        Self::from_u8(byte)
+    }
+}
+
+impl fmt::Display for EscrowStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EscrowStatus::Active => "Active",
+            EscrowStatus::Delivered => "Delivered",
+            EscrowStatus::Accepted => "Accepted",
+            EscrowStatus::Disputed => "Disputed",
+            EscrowStatus::Released => "Released",
+            EscrowStatus::Refunded => "Refunded",
+            EscrowStatus::Appealed => "Appealed",
+        })
+    }
+}
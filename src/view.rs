@@ -0,0 +1,107 @@
+//! Typed read-only view of a v2 escrow account (`client` feature).
+//!
+//! Decodes the header fields off-chain clients look at most, so they don't
+//! re-read raw offsets. The offsets mirror `instructions_v2`; the integration
+//! tests pin them against the layout test.
+
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::status::EscrowStatus;
+
+/// "PACTESCR" as a little-endian u64, the first field of every v2 escrow.
+pub const ESCROW_DISCRIMINATOR: u64 = 0x5041435445534352;
+
+/// Size of a current-layout v2 escrow account.
+pub const ESCROW_LEN: usize = 1225;
+
+const OFF_DISC: usize = 0;
+const OFF_BUYER: usize = 8;
+const OFF_SELLER: usize = 40;
+const OFF_ARBITRATOR: usize = 72;
+const OFF_MINT: usize = 104;
+const OFF_AMOUNT: usize = 136;
+const OFF_CREATED_AT: usize = 144;
+const OFF_TIMEOUT: usize = 152;
+const OFF_TERMS_HASH: usize = 160;
+const OFF_STATUS: usize = 192;
+const OFF_FLAGS: usize = 193;
+const OFF_BUMP: usize = 194;
+
+// timeout_seconds holds an absolute unix deadline instead of an offset
+const FLAG_ABSOLUTE_DEADLINE: u8 = 1 << 7;
+
+/// The header fields of a v2 escrow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscrowView {
+    pub buyer: Pubkey,
+    pub seller: Pubkey,
+    pub arbitrator: Pubkey,
+    /// All zeroes for a native SOL escrow
+    pub mint: Pubkey,
+    /// What the escrow still holds for the parties; 0 once settled
+    pub amount: u64,
+    pub created_at: u64,
+    /// Offset from `created_at`, or an absolute deadline when the flags say
+    /// so; 0 = no timeout
+    pub timeout_seconds: u64,
+    pub terms_hash: [u8; 32],
+    pub status: EscrowStatus,
+    pub flags: u8,
+    pub bump: u8,
+}
+
+impl EscrowView {
+    /// Reads an escrow from its account data. Fails with `InvalidAccountData`
+    /// on a foreign discriminator, an unknown status byte, or an account that
+    /// isn't the current layout's size, e.g. one still awaiting ResizeEscrow.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() != ESCROW_LEN || read_u64(data, OFF_DISC) != ESCROW_DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(EscrowView {
+            buyer: read_pubkey(data, OFF_BUYER),
+            seller: read_pubkey(data, OFF_SELLER),
+            arbitrator: read_pubkey(data, OFF_ARBITRATOR),
+            mint: read_pubkey(data, OFF_MINT),
+            amount: read_u64(data, OFF_AMOUNT),
+            created_at: read_u64(data, OFF_CREATED_AT),
+            timeout_seconds: read_u64(data, OFF_TIMEOUT),
+            terms_hash: read_pubkey(data, OFF_TERMS_HASH),
+            status: EscrowStatus::from_u8(data[OFF_STATUS])?,
+            flags: data[OFF_FLAGS],
+            bump: data[OFF_BUMP],
+        })
+    }
+
+    /// Settled: no funds left to move.
+    pub fn is_terminal(&self) -> bool {
+        self.status.is_terminal()
+    }
+
+    /// Seconds until the timeout at unix time `now`, `Some(0)` once it has
+    /// passed, or `None` if the escrow has no timeout.
+    pub fn time_remaining(&self, now: u64) -> Option<u64> {
+        if self.timeout_seconds == 0 {
+            return None;
+        }
+        let deadline = if self.flags & FLAG_ABSOLUTE_DEADLINE != 0 {
+            self.timeout_seconds
+        } else {
+            self.created_at.saturating_add(self.timeout_seconds)
+        };
+        Some(deadline.saturating_sub(now))
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[offset..offset + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&data[offset..offset + 32]);
+    key
+}
@@ -5,6 +5,9 @@
 
 use std::convert::TryInto;
 
+use pact_escrow::error::PactError;
+use pinocchio::program_error::ProgramError;
+
 /// Escrow account layout constants (must match instructions.rs)
 const ESCROW_DISC: u64 = 0x5041435445534352; // "PACTESCR"
 const ESCROW_SIZE: usize = 81;
@@ -16,7 +19,7 @@ const STATUS_REFUNDED: u8 = 2;
 #[test]
 fn test_escrow_data_layout() {
     // Create mock escrow data
-    let mut data = vec![0u8; ESCROW_SIZE];
+    let mut data = [0u8; ESCROW_SIZE];
     
     // Write discriminator
     data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
@@ -92,7 +95,7 @@ fn test_create_instruction_data() {
     let seed: u64 = 1234567890;
     
     // Build instruction data: [discriminator (1)] [amount (8)] [seed (8)] = 17 bytes
-    let mut data = vec![0u8; 17];
+    let mut data = [0u8; 17];
     data[0] = discriminator;
     data[1..9].copy_from_slice(&amount.to_le_bytes());
     data[9..17].copy_from_slice(&seed.to_le_bytes());
@@ -109,7 +112,7 @@ fn test_create_instruction_data() {
 #[test]
 fn test_release_instruction_data() {
     let discriminator: u8 = 1;
-    let data = vec![discriminator];
+    let data = [discriminator];
     
     assert_eq!(data.len(), 1);
     assert_eq!(data[0], 1);
@@ -119,7 +122,7 @@ fn test_release_instruction_data() {
 #[test]
 fn test_refund_instruction_data() {
     let discriminator: u8 = 2;
-    let data = vec![discriminator];
+    let data = [discriminator];
     
     assert_eq!(data.len(), 1);
     assert_eq!(data[0], 2);
@@ -166,6 +169,24 @@ fn test_amount_edge_cases() {
     assert_eq!(parsed, one_sol);
 }
 
+/// CreateEscrow rejects a buyer escrowing funds to themselves
+#[test]
+fn test_self_escrow_rejected() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+
+    // Mirrors CreateEscrow's checks after the buyer signature
+    let create = |amount: u64, buyer: &[u8; 32], seller: &[u8; 32]| {
+        if amount == 0 || buyer == seller {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(())
+    };
+
+    assert_eq!(create(1_000, &buyer, &seller), Ok(()));
+    assert_eq!(create(1_000, &buyer, &buyer), Err(ProgramError::InvalidInstructionData));
+}
+
 /// Test seed edge cases
 #[test]
 fn test_seed_edge_cases() {
@@ -208,3 +229,666 @@ fn test_discriminator_is_valid_ascii() {
     let s: String = bytes.iter().rev().map(|&b| b as char).collect();
     assert_eq!(s, "PACTESCR");
 }
+
+/// Test empty instruction data maps to its own error, distinct from an unknown discriminator
+#[test]
+fn test_missing_discriminator_error() {
+    let missing: ProgramError = PactError::MissingDiscriminator.into();
+    assert_eq!(missing, ProgramError::Custom(PactError::MissingDiscriminator as u32));
+    assert_ne!(missing, ProgramError::InvalidInstructionData);
+}
+
+/// Test DryRunCreate's return code: 0 means valid, anything else is the error
+#[test]
+fn test_dry_run_error_codes() {
+    let duplicate: ProgramError = PactError::DuplicateAccount.into();
+    assert_eq!(u64::from(duplicate), PactError::DuplicateAccount as u64);
+
+    for err in [
+        ProgramError::InvalidSeeds,
+        ProgramError::InvalidInstructionData,
+        ProgramError::MissingRequiredSignature,
+        ProgramError::AccountAlreadyInitialized,
+    ] {
+        assert_ne!(u64::from(err), 0);
+    }
+}
+
+/// Test an empty escrow can't be disputed, and says why
+#[test]
+fn test_nothing_to_dispute_error() {
+    let err: ProgramError = PactError::NothingToDispute.into();
+    assert_eq!(err, ProgramError::Custom(10));
+    assert_ne!(err, ProgramError::InvalidAccountData);
+}
+
+/// Test the payout reconciliation check against rent-adjusted balance
+#[test]
+fn test_balance_mismatch() {
+    let rent_minimum: u64 = 5_791_680;
+    let covers = |lamports: u64, amount: u64| amount <= lamports.saturating_sub(rent_minimum);
+
+    assert!(covers(rent_minimum + 1_000, 1_000));
+    assert!(!covers(rent_minimum + 999, 1_000));
+    assert!(!covers(1_000, 1)); // below rent: nothing spendable
+
+    let err: ProgramError = PactError::BalanceMismatch.into();
+    assert_ne!(err, ProgramError::InsufficientFunds);
+}
+
+/// Test Pact error codes are stable and unique: clients hardcode them
+#[test]
+fn test_error_codes_stable() {
+    let codes = [
+        (PactError::InvalidRecipient, 0),
+        (PactError::EscrowFrozen, 1),
+        (PactError::MissingDiscriminator, 2),
+        (PactError::ContributionMismatch, 3),
+        (PactError::TooManyFunders, 4),
+        (PactError::MissingDeliveryProof, 5),
+        (PactError::DuplicateAccount, 6),
+        (PactError::PriceBelowThreshold, 7),
+        (PactError::OraclePriceUnavailable, 8),
+        (PactError::SettlementPending, 9),
+        (PactError::NothingToDispute, 10),
+        (PactError::SellerNotAllowed, 11),
+        (PactError::BalanceMismatch, 12),
+        (PactError::RecipientNotRentExempt, 13),
+        (PactError::InvalidEscrowSize, 14),
+        (PactError::MissingRationale, 15),
+        (PactError::DisputeFlagsMissing, 16),
+        (PactError::EscrowNotSystemOwned, 17),
+        (PactError::ArbitratorSelfPayout, 18),
+        (PactError::PartialPayoutDisabled, 19),
+        (PactError::AlreadySettled, 20),
+        (PactError::TokenEscrowUnsupported, 21),
+        (PactError::EscrowNotRentExempt, 22),
+        (PactError::AlreadyDelivered, 23),
+        (PactError::DisputeResponsePending, 24),
+        (PactError::StaleOraclePrice, 25),
+    ];
+
+    for (i, (err, code)) in codes.iter().enumerate() {
+        assert_eq!(*err as u32, *code);
+        assert_eq!(ProgramError::from(*err), ProgramError::Custom(*code));
+        for (other, _) in &codes[i + 1..] {
+            assert_ne!(*err as u32, *other as u32);
+        }
+    }
+
+    #[cfg(feature = "client")]
+    {
+        use pact_escrow::error::error_message;
+        for (_, code) in codes {
+            assert_ne!(error_message(code), error_message(u32::MAX));
+        }
+    }
+}
+
+/// Test a payout can't leave a never-funded recipient below rent exemption
+#[test]
+fn test_recipient_not_rent_exempt() {
+    let rent_minimum: u64 = 890_880; // 0-byte System account
+    let rejected = |dest_lamports: u64, amount: u64| {
+        dest_lamports == 0 && amount > 0 && amount < rent_minimum
+    };
+
+    assert!(rejected(0, 5_000));
+    assert!(!rejected(0, rent_minimum)); // enough to bring it into existence
+    assert!(!rejected(1_000_000, 5_000)); // existing account
+    assert!(!rejected(0, 0));
+
+    let err: ProgramError = PactError::RecipientNotRentExempt.into();
+    assert_eq!(err, ProgramError::Custom(13));
+}
+
+/// Test escrow accounts must match the layout size exactly
+#[test]
+fn test_invalid_escrow_size() {
+    let current: usize = 1225;
+    let size_ok = |len: usize| len == current;
+
+    assert!(size_ok(current));
+    assert!(!size_ok(current - 8)); // legacy layout, needs ResizeEscrow
+    assert!(!size_ok(current + 1)); // trailing bytes from a bad realloc
+
+    let err: ProgramError = PactError::InvalidEscrowSize.into();
+    assert_ne!(err, ProgramError::InvalidAccountData);
+    assert_ne!(err, ProgramError::AccountDataTooSmall);
+}
+
+/// Test the router: each known discriminator reaches its handler, which
+/// rejects the empty account list before touching data
+#[test]
+fn test_process_instruction_routing() {
+    use pact_escrow::process_instruction;
+
+    for discriminator in [0u8, 1, 2, 3] {
+        let result = process_instruction(&pact_escrow::ID, &[], &[discriminator]);
+        assert_eq!(result, Err(ProgramError::NotEnoughAccountKeys));
+    }
+
+    // CreateEscrowBatch reads its records before its accounts
+    let mut batch = vec![41u8, 1];
+    batch.resize(2 + 88, 0);
+    assert_eq!(
+        process_instruction(&pact_escrow::ID, &[], &batch),
+        Err(ProgramError::NotEnoughAccountKeys)
+    );
+
+    assert_eq!(
+        process_instruction(&pact_escrow::ID, &[], &[43]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        process_instruction(&pact_escrow::ID, &[], &[u8::MAX]),
+        Err(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+        process_instruction(&pact_escrow::ID, &[], &[]),
+        Err(PactError::MissingDiscriminator.into())
+    );
+}
+
+/// An account as the runtime hands it to the entrypoint
+struct InputAccount {
+    key: [u8; 32],
+    owner: [u8; 32],
+    lamports: u64,
+    data: Vec<u8>,
+    is_signer: bool,
+}
+
+/// Serializes accounts and instruction data in the runtime's input format,
+/// so tests can drive process_instruction with real AccountInfos. Returned
+/// as u64 words to keep the buffer 8-byte aligned.
+fn serialize_input(accounts: &[InputAccount], instruction_data: &[u8]) -> Vec<u64> {
+    const MAX_PERMITTED_DATA_INCREASE: usize = 10 * 1024;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(accounts.len() as u64).to_le_bytes());
+    for account in accounts {
+        // Not a duplicate, signer, writable, not executable, no resize yet
+        bytes.extend_from_slice(&[u8::MAX, account.is_signer as u8, 1, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&account.key);
+        bytes.extend_from_slice(&account.owner);
+        bytes.extend_from_slice(&account.lamports.to_le_bytes());
+        bytes.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&account.data);
+        bytes.resize(bytes.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        bytes.extend_from_slice(&0u64.to_le_bytes()); // rent epoch
+    }
+    bytes.extend_from_slice(&(instruction_data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(instruction_data);
+    bytes.extend_from_slice(&pact_escrow::ID);
+    bytes.resize(bytes.len().next_multiple_of(8), 0);
+
+    bytes
+        .chunks_exact(8)
+        .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+        .collect()
+}
+
+/// Runs one instruction through the entrypoint and hands back the result
+/// with the accounts as it left them
+fn run_instruction(
+    accounts: &[InputAccount],
+    instruction_data: &[u8],
+) -> (Result<(), ProgramError>, Vec<InputAccount>) {
+    use std::mem::MaybeUninit;
+
+    use pinocchio::account_info::AccountInfo;
+
+    let mut input = serialize_input(accounts, instruction_data);
+    let mut infos = [const { MaybeUninit::<AccountInfo>::uninit() }; 8];
+    let (program_id, count, data) =
+        unsafe { pinocchio::entrypoint::deserialize::<8>(input.as_mut_ptr() as *mut u8, &mut infos) };
+    let infos = unsafe { std::slice::from_raw_parts(infos.as_ptr() as *const AccountInfo, count) };
+
+    let result = pact_escrow::process_instruction(program_id, infos, data);
+    let after = infos
+        .iter()
+        .map(|info| InputAccount {
+            key: *info.key(),
+            owner: *info.owner(),
+            lamports: info.lamports(),
+            data: info.try_borrow_data().unwrap().to_vec(),
+            is_signer: info.is_signer(),
+        })
+        .collect();
+    (result, after)
+}
+
+/// Test one entrypoint serves both layouts: the discriminators v1 and v2
+/// share go to the v1 handlers only for an 81-byte escrow (or, for create,
+/// v1's 16-byte payload), and v2 handlers never touch a v1 escrow
+#[test]
+fn test_unified_routing_by_layout() {
+    const V2_ESCROW_SIZE: usize = 1225;
+    const V2_OFF_FLAGS: usize = 193;
+    const V2_FLAG_FROZEN: u8 = 1 << 4;
+
+    let amount = 500_000u64;
+    let party = |key: u8, is_signer: bool| InputAccount {
+        key: [key; 32],
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let escrow = |size: usize| {
+        let mut data = vec![0u8; size];
+        data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[8..40].copy_from_slice(&[1u8; 32]);
+        data[40..72].copy_from_slice(&[2u8; 32]);
+        InputAccount {
+            key: [3u8; 32],
+            owner: pact_escrow::ID,
+            lamports: 2_000_000,
+            data,
+            is_signer: false,
+        }
+    };
+    let v1_escrow = || {
+        let mut account = escrow(ESCROW_SIZE);
+        account.data[72..80].copy_from_slice(&amount.to_le_bytes());
+        account.data[80] = STATUS_ACTIVE;
+        account
+    };
+
+    // v1 Release (1) on a v1 escrow pays the seller and marks it released
+    let (result, after) = run_instruction(&[party(1, true), party(2, false), v1_escrow()], &[1]);
+    assert_eq!(result, Ok(()));
+    assert_eq!(after[1].lamports, 1_000_000 + amount);
+    assert_eq!(after[2].lamports, 2_000_000 - amount);
+    assert_eq!(after[2].data[80], STATUS_RELEASED);
+
+    // v1 Refund (2) on a v1 escrow returns the funds to the buyer
+    let (result, after) = run_instruction(&[party(1, false), party(2, true), v1_escrow()], &[2]);
+    assert_eq!(result, Ok(()));
+    assert_eq!(after[0].lamports, 1_000_000 + amount);
+    assert_eq!(after[2].data[80], STATUS_REFUNDED);
+
+    // v2 ReleaseV2 (3) on a v2 escrow runs the v2 checks: this one is frozen
+    let mut frozen = escrow(V2_ESCROW_SIZE);
+    frozen.data[V2_OFF_FLAGS] = V2_FLAG_FROZEN;
+    let (result, after) = run_instruction(&[party(1, true), party(2, false), frozen], &[3]);
+    assert_eq!(result, Err(PactError::EscrowFrozen.into()));
+    assert_eq!(after[2].lamports, 2_000_000);
+
+    // Discriminator 1 with a v2 escrow is MarkDelivered, never v1 Release
+    let (result, after) =
+        run_instruction(&[party(1, true), party(2, false), escrow(V2_ESCROW_SIZE)], &[1]);
+    assert!(result.is_err());
+    assert_eq!(after[1].lamports, 1_000_000);
+    assert_eq!(after[2].data, escrow(V2_ESCROW_SIZE).data);
+
+    // v2 handlers reject a v1 escrow by size
+    let (result, after) = run_instruction(&[party(1, true), party(2, false), v1_escrow()], &[3]);
+    assert_eq!(result, Err(PactError::InvalidEscrowSize.into()));
+    assert_eq!(after[2].data[80], STATUS_ACTIVE);
+
+    // Create: v1's amount + seed goes to v1 CreateEscrow, which wants the
+    // buyer's signature; anything longer is CreateEscrowV2, which wants more
+    // accounts than v1's four
+    let accounts = [party(1, false), party(2, false), party(3, false), party(4, false)];
+    let mut v1_create = vec![0u8];
+    v1_create.extend_from_slice(&amount.to_le_bytes());
+    v1_create.extend_from_slice(&7u64.to_le_bytes());
+    let (result, _) = run_instruction(&accounts, &v1_create);
+    assert_eq!(result, Err(ProgramError::MissingRequiredSignature));
+    let mut v2_create = v1_create.clone();
+    v2_create.resize(1 + 56, 0);
+    let (result, _) = run_instruction(&accounts, &v2_create);
+    assert_eq!(result, Err(ProgramError::NotEnoughAccountKeys));
+}
+
+/// Test v1 Release and Refund refuse to pay the System Program, like the v2
+/// payouts, leaving the escrow untouched
+#[test]
+fn test_v1_payout_rejects_system_program() {
+    let amount = 500_000u64;
+    let party = |key: u8, is_signer: bool| InputAccount {
+        key: [key; 32],
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let escrow = |buyer: u8, seller: u8| {
+        let mut data = vec![0u8; ESCROW_SIZE];
+        data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[8..40].copy_from_slice(&[buyer; 32]);
+        data[40..72].copy_from_slice(&[seller; 32]);
+        data[72..80].copy_from_slice(&amount.to_le_bytes());
+        data[80] = STATUS_ACTIVE;
+        InputAccount {
+            key: [3u8; 32],
+            owner: pact_escrow::ID,
+            lamports: 2_000_000,
+            data,
+            is_signer: false,
+        }
+    };
+
+    // Release to a seller recorded as the System Program
+    let (result, after) = run_instruction(&[party(1, true), party(0, false), escrow(1, 0)], &[1]);
+    assert_eq!(result, Err(PactError::InvalidRecipient.into()));
+    assert_eq!(after[2].lamports, 2_000_000);
+    assert_eq!(after[2].data[80], STATUS_ACTIVE);
+
+    // Refund to a buyer recorded as the System Program
+    let (result, after) = run_instruction(&[party(0, false), party(2, true), escrow(0, 2)], &[2]);
+    assert_eq!(result, Err(PactError::InvalidRecipient.into()));
+    assert_eq!(after[2].lamports, 2_000_000);
+    assert_eq!(after[2].data[80], STATUS_ACTIVE);
+}
+
+/// Test ReleaseV2 checks appended terms against the stored terms_hash: the
+/// agreed terms get through to the payout, any other terms are refused
+/// before anything moves, and no terms skip the check
+#[test]
+fn test_release_checks_terms() {
+    const V2_ESCROW_SIZE: usize = 1225;
+    const V2_OFF_AMOUNT: usize = 136;
+    const V2_OFF_TERMS_HASH: usize = 160;
+    const V2_OFF_RENT_PAID: usize = 1050;
+    // SHA-256("abc"), the FIPS 180-2 test vector
+    const ABC_SHA256: [u8; 32] = [
+        0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+        0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+    ];
+
+    let amount = 500_000u64;
+    let rent = 9_416_880u64;
+    let party = |key: u8, is_signer: bool| InputAccount {
+        key: [key; 32],
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let escrow = || {
+        let mut data = vec![0u8; V2_ESCROW_SIZE];
+        data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[8..40].copy_from_slice(&[1u8; 32]);
+        data[40..72].copy_from_slice(&[2u8; 32]);
+        data[V2_OFF_AMOUNT..V2_OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+        data[V2_OFF_TERMS_HASH..V2_OFF_TERMS_HASH + 32].copy_from_slice(&ABC_SHA256);
+        data[V2_OFF_RENT_PAID..V2_OFF_RENT_PAID + 8].copy_from_slice(&rent.to_le_bytes());
+        InputAccount {
+            key: [3u8; 32],
+            owner: pact_escrow::ID,
+            lamports: rent + amount,
+            data,
+            is_signer: false,
+        }
+    };
+    let release = |terms: &[u8]| {
+        let mut ix = vec![3u8];
+        ix.extend_from_slice(terms);
+        run_instruction(&[party(1, true), party(2, false), escrow()], &ix)
+    };
+
+    // Mismatched terms stop at the check with the escrow untouched
+    let (result, after) = release(b"abd");
+    assert_eq!(result, Err(ProgramError::InvalidInstructionData));
+    assert_eq!(after[1].lamports, 1_000_000);
+    assert_eq!(after[2].data, escrow().data);
+
+    // The agreed terms, and no terms at all, pass the check and reach the
+    // payout, whose rent check needs the Rent sysvar the host doesn't have
+    for terms in [&b"abc"[..], &[]] {
+        let (result, _) = release(terms);
+        assert_eq!(result, Err(ProgramError::UnsupportedSysvar));
+    }
+}
+
+/// Test SplitEscrow refuses a parent whose protections the child wouldn't
+/// carry, so a buyer can't carve the funds out from under a cancellation
+/// fee or a freeze-on-timeout and refund the child at once
+#[cfg(not(feature = "seller-allowlist"))]
+#[test]
+fn test_split_keeps_protections() {
+    const V2_ESCROW_SIZE: usize = 1225;
+    const V2_OFF_AMOUNT: usize = 136;
+    const V2_OFF_FLAGS: usize = 193;
+    const V2_OFF_MIN_DISPUTE_DELAY: usize = 244;
+    const V2_OFF_TIMEOUT_MODE: usize = 252;
+    const V2_OFF_CALLBACK_PROGRAM: usize = 787;
+    const V2_OFF_CANCEL_FEE_CAP: usize = 837;
+    const V2_OFF_STRICT_ACCEPTANCE: usize = 1049;
+    const V2_OFF_RENT_PAID: usize = 1050;
+    const V2_FLAG_REFUND_REQUIRES_NO_DELIVERY: u8 = 1 << 6;
+
+    let amount = 500_000u64;
+    let rent = 9_416_880u64;
+    let party = |key: u8, is_signer: bool| InputAccount {
+        key: [key; 32],
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    // One lamport short of covering what it keeps, so a split that gets past
+    // the protection checks stops at the balance check instead
+    let parent = |protection: Option<(usize, u8)>| {
+        let mut data = vec![0u8; V2_ESCROW_SIZE];
+        data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[8..40].copy_from_slice(&[1u8; 32]);
+        data[40..72].copy_from_slice(&[2u8; 32]);
+        data[V2_OFF_AMOUNT..V2_OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+        data[V2_OFF_RENT_PAID..V2_OFF_RENT_PAID + 8].copy_from_slice(&rent.to_le_bytes());
+        if let Some((offset, value)) = protection {
+            data[offset] = value;
+        }
+        InputAccount {
+            key: [3u8; 32],
+            owner: pact_escrow::ID,
+            lamports: rent + amount - 1,
+            data,
+            is_signer: false,
+        }
+    };
+    let split = |parent: InputAccount| {
+        let mut ix = vec![21u8];
+        ix.extend_from_slice(&(amount - 1).to_le_bytes());
+        ix.extend_from_slice(&0u64.to_le_bytes());
+        ix.extend_from_slice(&3_600u64.to_le_bytes());
+        ix.extend_from_slice(&[0u8; 32]);
+        let accounts = [party(1, true), parent, party(5, false), party(6, false), party(7, false), party(0, false)];
+        run_instruction(&accounts, &ix)
+    };
+
+    let (result, _) = split(parent(None));
+    assert_eq!(result, Err(ProgramError::InsufficientFunds));
+
+    let protections = [
+        (V2_OFF_CANCEL_FEE_CAP, 1),
+        (V2_OFF_TIMEOUT_MODE, 2),
+        (V2_OFF_MIN_DISPUTE_DELAY, 1),
+        (V2_OFF_CALLBACK_PROGRAM, 9),
+        (V2_OFF_STRICT_ACCEPTANCE, 1),
+        (V2_OFF_FLAGS, V2_FLAG_REFUND_REQUIRES_NO_DELIVERY),
+    ];
+    for (i, protection) in protections.into_iter().enumerate() {
+        let (result, after) = split(parent(Some(protection)));
+        assert_eq!(result, Err(ProgramError::InvalidAccountData), "case {i}");
+        assert_eq!(after[1].data, parent(Some(protection)).data, "case {i}");
+    }
+}
+
+/// Test only the compiled-in operator can create the seller allowlist, so
+/// nobody can claim its authority ahead of them
+#[cfg(feature = "seller-allowlist")]
+#[test]
+fn test_allowlist_init_requires_operator() {
+    assert_ne!(pact_escrow::SELLER_ALLOWLIST_OPERATOR, [1u8; 32]);
+
+    let account = |key: u8, is_signer: bool| InputAccount {
+        key: [key; 32],
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let (result, after) = run_instruction(&[account(1, true), account(2, false), account(0, false)], &[18]);
+    assert_eq!(result, Err(ProgramError::InvalidAccountData));
+    assert_eq!(after[1].lamports, 1_000_000);
+    assert!(after[1].data.is_empty());
+}
+
+/// Test the exported escrow_pda is the derivation the program checks: it
+/// matches the address Solana's own find_program_address gives, and v1
+/// CreateEscrow accepts that address and refuses any other
+#[cfg(feature = "client")]
+#[test]
+fn test_escrow_pda_matches_program() {
+    // Solana's find_program_address for buyer [1; 32], seller [2; 32], seed
+    // 12345: 7GN4JGhgon4kfGe4hJiHxizu8JASTasnjHgqXXzkz5g8, bump 252
+    const EXPECTED_PDA: [u8; 32] = [
+        93, 21, 164, 139, 228, 253, 141, 252, 224, 163, 132, 197, 208, 9, 75, 16, 101, 81, 164, 225,
+        177, 13, 250, 13, 186, 48, 118, 161, 68, 47, 79, 37,
+    ];
+
+    let seed = 12345u64;
+    let (pda, bump) = pact_escrow::escrow_pda(&[1u8; 32], &[2u8; 32], seed, &pact_escrow::ID);
+    assert_eq!((pda, bump), (EXPECTED_PDA, 252));
+
+    let account = |key: [u8; 32], is_signer: bool| InputAccount {
+        key,
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let create = |escrow: [u8; 32]| {
+        let mut ix = vec![0u8];
+        ix.extend_from_slice(&500_000u64.to_le_bytes());
+        ix.extend_from_slice(&seed.to_le_bytes());
+        let accounts = [account([1u8; 32], true), account([2u8; 32], false), account(escrow, false), account([0u8; 32], false)];
+        run_instruction(&accounts, &ix).0
+    };
+
+    // Past the seed check, CreateEscrow needs the Rent sysvar the host
+    // doesn't have
+    assert_eq!(create(pda), Err(ProgramError::UnsupportedSysvar));
+    assert_eq!(create([3u8; 32]), Err(ProgramError::InvalidSeeds));
+}
+
+/// Test TopUp raises an open escrow's amount for its buyer and nobody else
+#[test]
+fn test_top_up_is_buyer_only() {
+    const V2_ESCROW_SIZE: usize = 1225;
+    const V2_OFF_AMOUNT: usize = 136;
+    const V2_OFF_STATUS: usize = 192;
+    const V2_OFF_FUNDER_COUNT: usize = 253;
+    const V2_OFF_FUNDERS: usize = 254;
+    const V2_OFF_FUNDED_AMOUNT: usize = 646;
+    const V2_OFF_RENT_PAID: usize = 1050;
+    const V2_STATUS_DISPUTED: u8 = 3;
+
+    let amount = 500_000u64;
+    let rent = 9_416_880u64;
+    let read = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+    let party = |key: u8, is_signer: bool| InputAccount {
+        key: [key; 32],
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let escrow = |status: u8| {
+        let mut data = vec![0u8; V2_ESCROW_SIZE];
+        data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[8..40].copy_from_slice(&[1u8; 32]);
+        data[40..72].copy_from_slice(&[2u8; 32]);
+        data[V2_OFF_AMOUNT..V2_OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+        data[V2_OFF_STATUS] = status;
+        data[V2_OFF_FUNDER_COUNT] = 1;
+        data[V2_OFF_FUNDERS..V2_OFF_FUNDERS + 32].copy_from_slice(&[1u8; 32]);
+        data[V2_OFF_FUNDERS + 32..V2_OFF_FUNDERS + 40].copy_from_slice(&amount.to_le_bytes());
+        data[V2_OFF_RENT_PAID..V2_OFF_RENT_PAID + 8].copy_from_slice(&rent.to_le_bytes());
+        data[V2_OFF_FUNDED_AMOUNT..V2_OFF_FUNDED_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+        InputAccount {
+            key: [3u8; 32],
+            owner: pact_escrow::ID,
+            lamports: rent + amount,
+            data,
+            is_signer: false,
+        }
+    };
+    let mut top_up = vec![42u8];
+    top_up.extend_from_slice(&250_000u64.to_le_bytes());
+
+    // The buyer raises the amount, funded_amount and their own contribution
+    let (result, after) = run_instruction(&[party(1, true), escrow(STATUS_ACTIVE), party(0, false)], &top_up);
+    assert_eq!(result, Ok(()));
+    assert_eq!(read(&after[1].data, V2_OFF_AMOUNT), amount + 250_000);
+    assert_eq!(read(&after[1].data, V2_OFF_FUNDED_AMOUNT), amount + 250_000);
+    assert_eq!(read(&after[1].data, V2_OFF_FUNDERS + 32), amount + 250_000);
+    assert_eq!(after[1].data[V2_OFF_FUNDER_COUNT], 1);
+
+    // A third party, even signing, can't; AddFunds stays open to them
+    let (result, after) = run_instruction(&[party(9, true), escrow(STATUS_ACTIVE), party(0, false)], &top_up);
+    assert_eq!(result, Err(ProgramError::InvalidAccountData));
+    assert_eq!(after[1].data, escrow(STATUS_ACTIVE).data);
+    // The buyer has to sign
+    let (result, _) = run_instruction(&[party(1, false), escrow(STATUS_ACTIVE), party(0, false)], &top_up);
+    assert_eq!(result, Err(ProgramError::MissingRequiredSignature));
+    // And a disputed escrow takes no more funds
+    let (result, after) =
+        run_instruction(&[party(1, true), escrow(V2_STATUS_DISPUTED), party(0, false)], &top_up);
+    assert_eq!(result, Err(ProgramError::InvalidAccountData));
+    assert_eq!(after[1].data, escrow(V2_STATUS_DISPUTED).data);
+}
+
+/// Test CloseEscrow won't close a token escrow without its vault: the PDA is
+/// the vault's only signer, so closing it alone would strand the vault
+#[test]
+fn test_close_token_escrow_requires_vault() {
+    const V2_ESCROW_SIZE: usize = 1225;
+    const V2_OFF_MINT: usize = 104;
+    const V2_OFF_STATUS: usize = 192;
+    const V2_STATUS_RELEASED: u8 = 4;
+    const TOKEN_PROGRAM_ID: [u8; 32] = [
+        6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133, 237,
+        95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+    ];
+
+    let account = |key: [u8; 32], is_signer: bool| InputAccount {
+        key,
+        owner: [0u8; 32],
+        lamports: 1_000_000,
+        data: Vec::new(),
+        is_signer,
+    };
+    let escrow = || {
+        let mut data = vec![0u8; V2_ESCROW_SIZE];
+        data[0..8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[8..40].copy_from_slice(&[1u8; 32]);
+        data[40..72].copy_from_slice(&[2u8; 32]);
+        data[V2_OFF_MINT..V2_OFF_MINT + 32].copy_from_slice(&[7u8; 32]);
+        data[V2_OFF_STATUS] = V2_STATUS_RELEASED;
+        InputAccount {
+            key: [3u8; 32],
+            owner: pact_escrow::ID,
+            lamports: 9_416_880,
+            data,
+            is_signer: false,
+        }
+    };
+
+    // Neither the vault nor the token program
+    let (result, after) = run_instruction(&[account([1u8; 32], true), escrow()], &[20]);
+    assert_eq!(result, Err(ProgramError::NotEnoughAccountKeys));
+    assert_eq!(after[1].lamports, 9_416_880);
+    assert_eq!(after[1].data, escrow().data);
+
+    // The token program but some other account in place of the vault
+    let accounts = [account([1u8; 32], true), escrow(), account([8u8; 32], false), account(TOKEN_PROGRAM_ID, false)];
+    let (result, after) = run_instruction(&accounts, &[20]);
+    assert_eq!(result, Err(ProgramError::NotEnoughAccountKeys));
+    assert_eq!(after[1].data, escrow().data);
+}
@@ -4,7 +4,7 @@ use std::convert::TryInto;
 
 // Constants (must match instructions_v2.rs)
 const ESCROW_DISC: u64 = 0x5041435445534352; // "PACTESCR"
-const ESCROW_SIZE: usize = 195;
+const ESCROW_SIZE: usize = 1225;
 
 // Status values
 const STATUS_ACTIVE: u8 = 0;
@@ -13,12 +13,27 @@ const STATUS_ACCEPTED: u8 = 2;
 const STATUS_DISPUTED: u8 = 3;
 const STATUS_RELEASED: u8 = 4;
 const STATUS_REFUNDED: u8 = 5;
+const STATUS_APPEALED: u8 = 6;
 
 // Flag bits
 const FLAG_SELLER_DELIVERED: u8 = 1 << 0;
 const FLAG_BUYER_ACCEPTED: u8 = 1 << 1;
 const FLAG_BUYER_DISPUTED: u8 = 1 << 2;
 const FLAG_SELLER_DISPUTED: u8 = 1 << 3;
+const FLAG_FROZEN: u8 = 1 << 4;
+const FLAG_REQUIRE_DELIVERY_PROOF: u8 = 1 << 5;
+const FLAG_REFUND_REQUIRES_NO_DELIVERY: u8 = 1 << 6;
+const FLAG_ABSOLUTE_DEADLINE: u8 = 1 << 7;
+
+// Timeout modes
+const TIMEOUT_MODE_REFUND: u8 = 0;
+const TIMEOUT_MODE_FREEZE: u8 = 2;
+
+// Pending ruling values
+const RULING_NONE: u8 = 0;
+const RULING_REFUND: u8 = 1;
+const RULING_RELEASE: u8 = 2;
+const APPEAL_WINDOW_SECONDS: u64 = 172_800;
 
 // Offsets
 const OFF_DISC: usize = 0;
@@ -33,19 +48,126 @@ const OFF_TERMS_HASH: usize = 160;
 const OFF_STATUS: usize = 192;
 const OFF_FLAGS: usize = 193;
 const OFF_BUMP: usize = 194;
+const OFF_APPEAL_ARBITRATOR: usize = 195;
+const OFF_RULING: usize = 227;
+const OFF_RULED_AT: usize = 228;
+const OFF_DELIVERED_AT: usize = 236;
+const OFF_MIN_DISPUTE_DELAY: usize = 244;
+const OFF_TIMEOUT_MODE: usize = 252;
+const OFF_FUNDER_COUNT: usize = 253;
+const OFF_FUNDERS: usize = 254;
+const OFF_DELIVERY_PROOF: usize = 574;
+const OFF_PRICE_ORACLE: usize = 606;
+const OFF_PRICE_THRESHOLD: usize = 638;
+const OFF_FUNDED_AMOUNT: usize = 646;
+const OFF_PROPOSED_BUYER_BPS: usize = 654;
+const OFF_PROPOSED_AT: usize = 656;
+const OFF_QUOTE_MINT: usize = 664;
+const OFF_QUOTE_AMOUNT: usize = 696;
+const OFF_DISPUTED_AT: usize = 704;
+const OFF_SEED: usize = 712;
+const OFF_MILESTONE_COUNT: usize = 720;
+const OFF_MILESTONE_DELIVERED: usize = 721;
+const OFF_MILESTONE_SETTLED: usize = 722;
+const OFF_MILESTONE_AMOUNTS: usize = 723;
+const OFF_CALLBACK_PROGRAM: usize = 787;
+const OFF_ACCEPTANCE_WINDOW: usize = 819;
+const OFF_PERIODS_REMAINING: usize = 827;
+const OFF_PERIOD_AMOUNT: usize = 829;
+const OFF_CANCEL_FEE_CAP: usize = 837;
+const OFF_CANCEL_FEE_RAMP: usize = 845;
+const OFF_REQUIRE_RATIONALE: usize = 853;
+const OFF_RATIONALE_HASH: usize = 854;
+const OFF_NOTIFY: usize = 886;
+const OFF_SELLER_THRESHOLD: usize = 887;
+const OFF_SELLER_SIGNER_COUNT: usize = 888;
+const OFF_SELLER_SIGNERS: usize = 889;
+const OFF_STRICT_ACCEPTANCE: usize = 1049;
+const OFF_RENT_PAID: usize = 1050;
+const OFF_UPDATED_AT: usize = 1058;
+const OFF_ORIGINAL_SELLER: usize = 1066;
+const OFF_DELIVERY_DEADLINE: usize = 1098;
+const OFF_LATE_PENALTY_BPS: usize = 1106;
+const OFF_MINT_DECIMALS: usize = 1108;
+const OFF_POLICY: usize = 1109;
+const OFF_RENT_RECIPIENT: usize = 1110;
+const OFF_RENT_TREASURY: usize = 1111;
+const OFF_RELEASER: usize = 1143;
+const OFF_ARBITRATOR_FEE: usize = 1175;
+const OFF_FEE_RECIPIENT: usize = 1183;
+const OFF_PLATFORM_FEE_BPS: usize = 1215;
+const OFF_ARBITRATION_WINDOW: usize = 1217;
+
+const MAX_MILESTONES: usize = 8;
+
+const MAX_FUNDERS: usize = 8;
+const FUNDER_ENTRY_SIZE: usize = 40;
 
 #[test]
 fn test_escrow_v2_size() {
     // discriminator(8) + buyer(32) + seller(32) + arbitrator(32) + mint(32) +
     // amount(8) + created_at(8) + timeout_seconds(8) + terms_hash(32) +
-    // status(1) + flags(1) + bump(1) = 195
-    let expected = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 32 + 1 + 1 + 1;
+    // status(1) + flags(1) + bump(1) + appeal_arbitrator(32) + ruling(1) +
+    // ruled_at(8) + delivered_at(8) + min_dispute_delay(8) + timeout_mode(1) +
+    // funder_count(1) + funders(8 * 40) + delivery_proof(32) + price_oracle(32) +
+    // price_threshold(8) + funded_amount(8) + proposed_buyer_bps(2) +
+    // proposed_at(8) + quote_mint(32) + quote_amount(8) + disputed_at(8) + seed(8) +
+    // milestone_count(1) + milestone_delivered(1) + milestone_settled(1) +
+    // milestone_amounts(8 * 8) + callback_program(32) +
+    // acceptance_window_seconds(8) + periods_remaining(2) + period_amount(8) +
+    // cancel_fee_cap(8) + cancel_fee_ramp_seconds(8) + require_rationale(1) +
+    // rationale_hash(32) + notify(1) + seller_threshold(1) +
+    // seller_signer_count(1) + seller_signers(5 * 32) + strict_acceptance(1) +
+    // rent_paid(8) + updated_at(8) + original_seller(32) + delivery_deadline(8) +
+    // late_penalty_bps(2) + mint_decimals(1) + policy(1) + rent_recipient(1) +
+    // rent_treasury(32) + releaser(32) + arbitrator_fee(8) + fee_recipient(32) +
+    // platform_fee_bps(2) + arbitration_window(8) = 1225
+    let expected = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 32 + 1 + 1 + 1 + 32 + 1 + 8 + 8 + 8 + 1
+        + 1 + MAX_FUNDERS * FUNDER_ENTRY_SIZE + 32 + 32 + 8 + 8 + 2 + 8 + 32 + 8 + 8 + 8
+        + 1 + 1 + 1 + MAX_MILESTONES * 8 + 32 + 8 + 2 + 8 + 8 + 8 + 1 + 32 + 1 + 1 + 1 + 5 * 32 + 1 + 8 + 8 + 32 + 8 + 2 + 1 + 1 + 1 + 32 + 32 + 8 + 32 + 2 + 8;
     assert_eq!(expected, ESCROW_SIZE);
+    assert_eq!(OFF_TIMEOUT_MODE + 1, OFF_FUNDER_COUNT);
+    assert_eq!(OFF_FUNDERS + MAX_FUNDERS * FUNDER_ENTRY_SIZE, OFF_DELIVERY_PROOF);
+    assert_eq!(OFF_DELIVERY_PROOF + 32, OFF_PRICE_ORACLE);
+    assert_eq!(OFF_PRICE_THRESHOLD + 8, OFF_FUNDED_AMOUNT);
+    assert_eq!(OFF_FUNDED_AMOUNT + 8, OFF_PROPOSED_BUYER_BPS);
+    assert_eq!(OFF_PROPOSED_AT + 8, OFF_QUOTE_MINT);
+    assert_eq!(OFF_QUOTE_AMOUNT + 8, OFF_DISPUTED_AT);
+    assert_eq!(OFF_DISPUTED_AT + 8, OFF_SEED);
+    assert_eq!(OFF_SEED + 8, OFF_MILESTONE_COUNT);
+    assert_eq!(OFF_MILESTONE_AMOUNTS + MAX_MILESTONES * 8, OFF_CALLBACK_PROGRAM);
+    assert_eq!(OFF_CALLBACK_PROGRAM + 32, OFF_ACCEPTANCE_WINDOW);
+    assert_eq!(OFF_ACCEPTANCE_WINDOW + 8, OFF_PERIODS_REMAINING);
+    assert_eq!(OFF_PERIODS_REMAINING + 2, OFF_PERIOD_AMOUNT);
+    assert_eq!(OFF_PERIOD_AMOUNT + 8, OFF_CANCEL_FEE_CAP);
+    assert_eq!(OFF_CANCEL_FEE_CAP + 8, OFF_CANCEL_FEE_RAMP);
+    assert_eq!(OFF_CANCEL_FEE_RAMP + 8, OFF_REQUIRE_RATIONALE);
+    assert_eq!(OFF_REQUIRE_RATIONALE + 1, OFF_RATIONALE_HASH);
+    assert_eq!(OFF_RATIONALE_HASH + 32, OFF_NOTIFY);
+    assert_eq!(OFF_NOTIFY + 1, OFF_SELLER_THRESHOLD);
+    assert_eq!(OFF_SELLER_THRESHOLD + 1, OFF_SELLER_SIGNER_COUNT);
+    assert_eq!(OFF_SELLER_SIGNER_COUNT + 1, OFF_SELLER_SIGNERS);
+    assert_eq!(OFF_SELLER_SIGNERS + 5 * 32, OFF_STRICT_ACCEPTANCE);
+    assert_eq!(OFF_STRICT_ACCEPTANCE + 1, OFF_RENT_PAID);
+    assert_eq!(OFF_RENT_PAID + 8, OFF_UPDATED_AT);
+    assert_eq!(OFF_UPDATED_AT + 8, OFF_ORIGINAL_SELLER);
+    assert_eq!(OFF_ORIGINAL_SELLER + 32, OFF_DELIVERY_DEADLINE);
+    assert_eq!(OFF_DELIVERY_DEADLINE + 8, OFF_LATE_PENALTY_BPS);
+    assert_eq!(OFF_LATE_PENALTY_BPS + 2, OFF_MINT_DECIMALS);
+    assert_eq!(OFF_MINT_DECIMALS + 1, OFF_POLICY);
+    assert_eq!(OFF_POLICY + 1, OFF_RENT_RECIPIENT);
+    assert_eq!(OFF_RENT_RECIPIENT + 1, OFF_RENT_TREASURY);
+    assert_eq!(OFF_RENT_TREASURY + 32, OFF_RELEASER);
+    assert_eq!(OFF_RELEASER + 32, OFF_ARBITRATOR_FEE);
+    assert_eq!(OFF_ARBITRATOR_FEE + 8, OFF_FEE_RECIPIENT);
+    assert_eq!(OFF_FEE_RECIPIENT + 32, OFF_PLATFORM_FEE_BPS);
+    assert_eq!(OFF_PLATFORM_FEE_BPS + 2, OFF_ARBITRATION_WINDOW);
+    assert_eq!(OFF_ARBITRATION_WINDOW + 8, ESCROW_SIZE);
 }
 
 #[test]
 fn test_escrow_v2_layout() {
-    let mut data = vec![0u8; ESCROW_SIZE];
+    let mut data = [0u8; ESCROW_SIZE];
     
     // Write discriminator
     data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
@@ -131,7 +253,8 @@ fn test_status_values() {
     
     // All unique
     let statuses = [STATUS_ACTIVE, STATUS_DELIVERED, STATUS_ACCEPTED, 
-                    STATUS_DISPUTED, STATUS_RELEASED, STATUS_REFUNDED];
+                    STATUS_DISPUTED, STATUS_RELEASED, STATUS_REFUNDED,
+                    STATUS_APPEALED];
     for i in 0..statuses.len() {
         for j in (i+1)..statuses.len() {
             assert_ne!(statuses[i], statuses[j]);
@@ -145,6 +268,10 @@ fn test_flag_bits() {
     assert_eq!(FLAG_BUYER_ACCEPTED, 0b0010);
     assert_eq!(FLAG_BUYER_DISPUTED, 0b0100);
     assert_eq!(FLAG_SELLER_DISPUTED, 0b1000);
+    assert_eq!(FLAG_FROZEN, 0b1_0000);
+    assert_eq!(FLAG_REQUIRE_DELIVERY_PROOF, 0b10_0000);
+    assert_eq!(FLAG_REFUND_REQUIRES_NO_DELIVERY, 0b100_0000);
+    assert_eq!(FLAG_ABSOLUTE_DEADLINE, 0b1000_0000);
     
     // Flags can be combined
     let combined = FLAG_SELLER_DELIVERED | FLAG_BUYER_DISPUTED;
@@ -166,7 +293,7 @@ fn test_create_escrow_instruction_data() {
     let timeout: u64 = 259200;
     let terms_hash = [0xABu8; 32];
     
-    let mut data = vec![0u8; 57];
+    let mut data = [0u8; 57];
     data[0] = discriminator;
     data[1..9].copy_from_slice(&amount.to_le_bytes());
     data[9..17].copy_from_slice(&seed.to_le_bytes());
@@ -195,8 +322,8 @@ fn test_single_byte_instructions() {
 #[test]
 fn test_arbitrate_instruction_data() {
     // discriminator(1) + decision(1) = 2 bytes
-    let refund_data = vec![6u8, 0]; // Arbitrate with refund
-    let release_data = vec![6u8, 1]; // Arbitrate with release
+    let refund_data = [6u8, 0]; // Arbitrate with refund
+    let release_data = [6u8, 1]; // Arbitrate with release
     
     assert_eq!(refund_data[0], 6);
     assert_eq!(refund_data[1], 0);
@@ -263,11 +390,12 @@ fn test_state_transitions_from_active() {
 #[test]
 fn test_state_transitions_from_delivered() {
     // From Delivered (1), valid next states are:
+    // - Accepted (2) via AcceptDelivery
     // - Disputed (3) via Dispute
-    // - Released (4) via AcceptDelivery or Release
+    // - Released (4) via Release or AutoRelease
     
     let from = STATUS_DELIVERED;
-    let valid_to = [STATUS_DISPUTED, STATUS_RELEASED];
+    let valid_to = [STATUS_ACCEPTED, STATUS_DISPUTED, STATUS_RELEASED];
     
     for to in valid_to {
         assert!(to > from);
@@ -313,6 +441,21 @@ fn test_pda_seeds_structure() {
     assert_eq!(total, 6 + 32 + 32 + 8); // 78 bytes
 }
 
+#[test]
+fn test_exported_escrow_seeds() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let seed_bytes = 12345u64.to_le_bytes();
+
+    // What derive_escrow and escrow_pda hash, in the order the signer seeds
+    // repeat them when the escrow signs for its own transfers
+    let seeds = pact_escrow::escrow_seeds(&buyer, &seller, &seed_bytes);
+    assert_eq!(seeds, [&b"escrow"[..], &buyer[..], &seller[..], &seed_bytes[..]]);
+
+    // Swapping the parties gives a different escrow
+    assert_ne!(pact_escrow::escrow_seeds(&seller, &buyer, &seed_bytes), seeds);
+}
+
 #[test]
 fn test_discriminator_ascii() {
     let disc = ESCROW_DISC;
@@ -322,3 +465,3538 @@ fn test_discriminator_ascii() {
     let s: String = bytes.iter().rev().map(|&b| b as char).collect();
     assert_eq!(s, "PACTESCR");
 }
+
+#[test]
+fn test_appeal_layout() {
+    let mut data = [0u8; ESCROW_SIZE];
+
+    let appeal_arbitrator = [4u8; 32];
+    data[OFF_APPEAL_ARBITRATOR..OFF_APPEAL_ARBITRATOR + 32].copy_from_slice(&appeal_arbitrator);
+    data[OFF_RULING] = RULING_RELEASE;
+    let ruled_at: u64 = 1707544800;
+    data[OFF_RULED_AT..OFF_RULED_AT + 8].copy_from_slice(&ruled_at.to_le_bytes());
+
+    // Appeal fields sit after the original v2 fields
+    assert_eq!(OFF_APPEAL_ARBITRATOR, OFF_BUMP + 1);
+
+    let stored: [u8; 32] = data[OFF_APPEAL_ARBITRATOR..OFF_APPEAL_ARBITRATOR + 32].try_into().unwrap();
+    assert_eq!(stored, appeal_arbitrator);
+    assert_eq!(data[OFF_RULING], RULING_RELEASE);
+    assert_eq!(u64::from_le_bytes(data[OFF_RULED_AT..OFF_RULED_AT + 8].try_into().unwrap()), ruled_at);
+}
+
+#[test]
+fn test_appeal_window() {
+    let ruled_at: u64 = 1707544800;
+    let window_end = ruled_at + APPEAL_WINDOW_SECONDS;
+
+    // Appeal allowed strictly before the window closes
+    let can_appeal = |now: u64| now < window_end;
+    // ExecuteRuling allowed from the moment it closes
+    let can_execute = |now: u64| now >= window_end;
+
+    assert!(can_appeal(ruled_at));
+    assert!(can_appeal(window_end - 1));
+    assert!(!can_execute(window_end - 1));
+
+    assert!(!can_appeal(window_end));
+    assert!(can_execute(window_end));
+
+    // The two paths never overlap
+    for now in [ruled_at, window_end - 1, window_end, window_end + 1] {
+        assert_ne!(can_appeal(now), can_execute(now));
+    }
+}
+
+#[test]
+fn test_appeal_losing_party() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+
+    let loser = |ruling: u8| if ruling == RULING_REFUND { seller } else { buyer };
+
+    assert_eq!(loser(RULING_REFUND), seller);
+    assert_eq!(loser(RULING_RELEASE), buyer);
+    assert_ne!(RULING_NONE, RULING_REFUND);
+    assert_ne!(RULING_NONE, RULING_RELEASE);
+}
+
+#[test]
+fn test_min_dispute_delay() {
+    let mut data = [0u8; ESCROW_SIZE];
+    let delivered_at: u64 = 1707544800;
+    let delay: u64 = 3600; // 1 hour inspection period
+    data[OFF_DELIVERED_AT..OFF_DELIVERED_AT + 8].copy_from_slice(&delivered_at.to_le_bytes());
+    data[OFF_MIN_DISPUTE_DELAY..OFF_MIN_DISPUTE_DELAY + 8].copy_from_slice(&delay.to_le_bytes());
+
+    let stored_delivered_at = u64::from_le_bytes(data[OFF_DELIVERED_AT..OFF_DELIVERED_AT + 8].try_into().unwrap());
+    let stored_delay = u64::from_le_bytes(data[OFF_MIN_DISPUTE_DELAY..OFF_MIN_DISPUTE_DELAY + 8].try_into().unwrap());
+
+    let can_dispute = |status: u8, now: u64| {
+        status == STATUS_ACTIVE
+            || (status == STATUS_DELIVERED && now >= stored_delivered_at.saturating_add(stored_delay))
+    };
+
+    // Delay only applies once delivered
+    assert!(can_dispute(STATUS_ACTIVE, delivered_at));
+    assert!(!can_dispute(STATUS_DELIVERED, delivered_at));
+    assert!(!can_dispute(STATUS_DELIVERED, delivered_at + delay - 1));
+    assert!(can_dispute(STATUS_DELIVERED, delivered_at + delay));
+
+    // A huge delay saturates instead of wrapping to an early deadline
+    let wrapped = stored_delivered_at.saturating_add(u64::MAX);
+    assert_eq!(wrapped, u64::MAX);
+}
+
+#[test]
+fn test_lookup_escrow_data() {
+    // discriminator(1) + buyer(32) + seller(32) + seed(8) = 73 bytes
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let seed: u64 = 42;
+
+    let mut data = [0u8; 73];
+    data[0] = 10;
+    data[1..33].copy_from_slice(&buyer);
+    data[33..65].copy_from_slice(&seller);
+    data[65..73].copy_from_slice(&seed.to_le_bytes());
+
+    let payload = &data[1..];
+    assert_eq!(payload.len(), 72);
+    assert_eq!(payload[0..32], buyer);
+    assert_eq!(payload[32..64], seller);
+    assert_eq!(u64::from_le_bytes(payload[64..72].try_into().unwrap()), seed);
+}
+
+#[test]
+fn test_lookup_escrow_return_data() {
+    const LOOKUP_NOT_FOUND: u8 = 0xFF;
+
+    // Found: status(1) + flags(1) + amount(8) + quote_mint(32) + quote_amount(8) + seed(8) +
+    // acceptance_window_seconds(8) + auto_release_at(8) + mint_decimals(1)
+    let amount: u64 = 500_000_000;
+    let mut data = [0u8; ESCROW_SIZE];
+    let usdc = [5u8; 32];
+    data[OFF_QUOTE_MINT..OFF_QUOTE_MINT + 32].copy_from_slice(&usdc);
+    data[OFF_QUOTE_AMOUNT..OFF_QUOTE_AMOUNT + 8].copy_from_slice(&100_000_000u64.to_le_bytes());
+
+    let seed: u64 = 42;
+    data[OFF_SEED..OFF_SEED + 8].copy_from_slice(&seed.to_le_bytes());
+
+    let window: u64 = 86_400;
+    let delivered_at: u64 = 1_700_000_000;
+    data[OFF_ACCEPTANCE_WINDOW..OFF_ACCEPTANCE_WINDOW + 8].copy_from_slice(&window.to_le_bytes());
+    data[OFF_DELIVERED_AT..OFF_DELIVERED_AT + 8].copy_from_slice(&delivered_at.to_le_bytes());
+    data[OFF_MINT_DECIMALS] = 9;
+
+    let mut ret = [0u8; 75];
+    ret[0] = STATUS_DELIVERED;
+    ret[1] = FLAG_SELLER_DELIVERED;
+    ret[2..10].copy_from_slice(&amount.to_le_bytes());
+    ret[10..50].copy_from_slice(&data[OFF_QUOTE_MINT..OFF_QUOTE_AMOUNT + 8]);
+    ret[50..58].copy_from_slice(&data[OFF_SEED..OFF_SEED + 8]);
+    ret[58..66].copy_from_slice(&data[OFF_ACCEPTANCE_WINDOW..OFF_ACCEPTANCE_WINDOW + 8]);
+    ret[66..74].copy_from_slice(&(delivered_at + window).to_le_bytes());
+    ret[74] = data[OFF_MINT_DECIMALS];
+
+    assert_eq!(ret[0], STATUS_DELIVERED);
+    assert_eq!(u64::from_le_bytes(ret[2..10].try_into().unwrap()), amount);
+    assert_eq!(&ret[10..42], &usdc);
+    assert_eq!(u64::from_le_bytes(ret[42..50].try_into().unwrap()), 100_000_000);
+    assert_eq!(u64::from_le_bytes(ret[50..58].try_into().unwrap()), seed);
+    assert_eq!(u64::from_le_bytes(ret[58..66].try_into().unwrap()), window);
+    assert_eq!(u64::from_le_bytes(ret[66..74].try_into().unwrap()), 1_700_086_400);
+    assert_eq!(ret[74], 9); // native SOL: lamports
+
+    // The sentinel can never be mistaken for a real status
+    let statuses = [STATUS_ACTIVE, STATUS_DELIVERED, STATUS_ACCEPTED, STATUS_DISPUTED,
+                    STATUS_RELEASED, STATUS_REFUNDED, STATUS_APPEALED];
+    assert!(!statuses.contains(&LOOKUP_NOT_FOUND));
+}
+
+#[test]
+fn test_freeze_on_timeout() {
+    let created_at: u64 = 1707544800;
+    let timeout_seconds: u64 = 259200;
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_TIMEOUT_MODE] = TIMEOUT_MODE_FREEZE;
+
+    let can_freeze = |data: &[u8], now: u64| {
+        data[OFF_TIMEOUT_MODE] == TIMEOUT_MODE_FREEZE
+            && data[OFF_FLAGS] & FLAG_FROZEN == 0
+            && now >= created_at.saturating_add(timeout_seconds)
+    };
+
+    assert!(!can_freeze(&data, created_at + timeout_seconds - 1));
+    assert!(can_freeze(&data, created_at + timeout_seconds));
+
+    data[OFF_FLAGS] |= FLAG_FROZEN;
+    // Freezing is one-shot and blocks party actions
+    assert!(!can_freeze(&data, created_at + timeout_seconds));
+    assert!(data[OFF_FLAGS] & FLAG_FROZEN != 0);
+
+    // Refund mode never freezes
+    data[OFF_TIMEOUT_MODE] = TIMEOUT_MODE_REFUND;
+    data[OFF_FLAGS] = 0;
+    assert!(!can_freeze(&data, created_at + timeout_seconds));
+}
+
+#[test]
+fn test_resize_rent_settlement() {
+    // Rent-exempt minimum: (128 + data_len) * 3480 * 2 lamports
+    let minimum_balance = |len: usize| ((128 + len) as u64) * 3480 * 2;
+    let amount: u64 = 1_000_000_000;
+
+    // Shrinking returns exactly the freed rent to the buyer
+    let old_len = ESCROW_SIZE + 64;
+    let lamports = minimum_balance(old_len) + amount;
+    let rent_balance = lamports - amount;
+    let required = minimum_balance(ESCROW_SIZE);
+    assert!(rent_balance > required);
+    let swept = rent_balance - required;
+    assert_eq!(swept, 64 * 3480 * 2);
+    assert_eq!(lamports - swept, required + amount);
+
+    // Growing charges the buyer exactly the missing rent
+    let old_len = 195;
+    let lamports = minimum_balance(old_len) + amount;
+    let rent_balance = lamports - amount;
+    let top_up = required - rent_balance;
+    assert_eq!(lamports + top_up, required + amount);
+
+    // An under-funded account can never be swept below the new minimum
+    let lamports = required + amount - 1;
+    assert!(lamports - amount < required);
+}
+
+#[test]
+fn test_pooled_funders() {
+    let mut data = [0u8; ESCROW_SIZE];
+    let buyer = [1u8; 32];
+    let cofunder = [7u8; 32];
+
+    // CreateEscrowV2 records the buyer as funder 0
+    data[OFF_FUNDER_COUNT] = 1;
+    data[OFF_FUNDERS..OFF_FUNDERS + 32].copy_from_slice(&buyer);
+    data[OFF_FUNDERS + 32..OFF_FUNDERS + 40].copy_from_slice(&600u64.to_le_bytes());
+
+    // AddFunds appends a new funder
+    let entry = OFF_FUNDERS + FUNDER_ENTRY_SIZE;
+    data[entry..entry + 32].copy_from_slice(&cofunder);
+    data[entry + 32..entry + 40].copy_from_slice(&400u64.to_le_bytes());
+    data[OFF_FUNDER_COUNT] = 2;
+    let amount: u64 = 1_000;
+
+    // Refund returns each contribution, and they must sum to the amount
+    let count = data[OFF_FUNDER_COUNT] as usize;
+    let mut total = 0u64;
+    for i in 0..count {
+        let offset = OFF_FUNDERS + i * FUNDER_ENTRY_SIZE;
+        total += u64::from_le_bytes(data[offset + 32..offset + 40].try_into().unwrap());
+    }
+    assert_eq!(total, amount);
+    assert_eq!(&data[entry..entry + 32], &cofunder);
+
+    // The last entry still fits inside the account
+    let last = OFF_FUNDERS + (MAX_FUNDERS - 1) * FUNDER_ENTRY_SIZE;
+    assert_eq!(last + FUNDER_ENTRY_SIZE, OFF_DELIVERY_PROOF);
+}
+
+#[test]
+fn test_add_funds_then_release() {
+    let rent: u64 = 9_416_880; // minimum_balance(ESCROW_SIZE)
+    let buyer = [1u8; 32];
+    let read = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+    let write = |data: &mut [u8], off: usize, v: u64| data[off..off + 8].copy_from_slice(&v.to_le_bytes());
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    write(&mut data, OFF_AMOUNT, 1_000_000);
+    write(&mut data, OFF_FUNDED_AMOUNT, 1_000_000);
+    data[OFF_FUNDER_COUNT] = 1;
+    data[OFF_FUNDERS..OFF_FUNDERS + 32].copy_from_slice(&buyer);
+    write(&mut data, OFF_FUNDERS + 32, 1_000_000);
+    let mut escrow_lamports = rent + 1_000_000;
+
+    // Mirrors AddFunds (and TopUp, its buyer-only form) for a funder already
+    // on the list: only open, undisputed escrows take more funds, and every
+    // total is checked
+    let add_funds = |data: &mut [u8], lamports: &mut u64, contribution: u64| {
+        if contribution == 0 {
+            return Err("InvalidInstructionData");
+        }
+        if data[OFF_STATUS] != STATUS_ACTIVE && data[OFF_STATUS] != STATUS_DELIVERED {
+            return Err("InvalidAccountData");
+        }
+        let amount = read(data, OFF_AMOUNT).checked_add(contribution).ok_or("ArithmeticOverflow")?;
+        let funder = read(data, OFF_FUNDERS + 32).checked_add(contribution).ok_or("ArithmeticOverflow")?;
+        let funded = read(data, OFF_FUNDED_AMOUNT).checked_add(contribution).ok_or("ArithmeticOverflow")?;
+        write(data, OFF_AMOUNT, amount);
+        write(data, OFF_FUNDERS + 32, funder);
+        write(data, OFF_FUNDED_AMOUNT, funded);
+        *lamports += contribution;
+        Ok(())
+    };
+
+    assert!(add_funds(&mut data, &mut escrow_lamports, 250_000).is_ok());
+    assert_eq!(read(&data, OFF_AMOUNT), 1_250_000);
+    assert_eq!(read(&data, OFF_FUNDED_AMOUNT), 1_250_000);
+    assert_eq!(read(&data, OFF_FUNDERS + 32), 1_250_000);
+    assert_eq!(add_funds(&mut data, &mut escrow_lamports, 0), Err("InvalidInstructionData"));
+    assert_eq!(add_funds(&mut data, &mut escrow_lamports, u64::MAX), Err("ArithmeticOverflow"));
+
+    // A full release pays out the increased total and leaves the rent
+    let amount = read(&data, OFF_AMOUNT);
+    escrow_lamports -= amount;
+    let seller_lamports = amount;
+    data[OFF_STATUS] = STATUS_RELEASED;
+    write(&mut data, OFF_AMOUNT, 0);
+    assert_eq!(seller_lamports, 1_250_000);
+    assert_eq!(escrow_lamports, rent);
+
+    // Settled, accepted and disputed escrows take no more funds
+    for status in [STATUS_RELEASED, STATUS_REFUNDED, STATUS_ACCEPTED, STATUS_DISPUTED, STATUS_APPEALED] {
+        data[OFF_STATUS] = status;
+        assert_eq!(add_funds(&mut data, &mut escrow_lamports, 1), Err("InvalidAccountData"));
+    }
+}
+
+#[test]
+fn test_require_delivery_proof() {
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_FLAGS] = FLAG_REQUIRE_DELIVERY_PROOF;
+
+    // MarkDelivered without a proof hash is rejected
+    let proof = [0u8; 32];
+    let accepted = data[OFF_FLAGS] & FLAG_REQUIRE_DELIVERY_PROOF == 0 || proof != [0u8; 32];
+    assert!(!accepted);
+
+    // With a proof hash it goes through and the hash is stored
+    let proof = [0xABu8; 32];
+    let accepted = data[OFF_FLAGS] & FLAG_REQUIRE_DELIVERY_PROOF == 0 || proof != [0u8; 32];
+    assert!(accepted);
+    data[OFF_DELIVERY_PROOF..OFF_DELIVERY_PROOF + 32].copy_from_slice(&proof);
+    data[OFF_FLAGS] |= FLAG_SELLER_DELIVERED;
+    assert_eq!(&data[OFF_DELIVERY_PROOF..OFF_DELIVERY_PROOF + 32], &proof);
+
+    // Simple deals skip the requirement
+    let data = [0u8; ESCROW_SIZE];
+    assert_eq!(data[OFF_FLAGS] & FLAG_REQUIRE_DELIVERY_PROOF, 0);
+}
+
+#[test]
+fn test_refund_requires_no_delivery() {
+    // Mirrors RefundV2's buyer timeout path
+    let buyer_timeout_refund = |flags: u8, timeout_mode: u8| {
+        timeout_mode == TIMEOUT_MODE_REFUND
+            && (flags & FLAG_REFUND_REQUIRES_NO_DELIVERY == 0
+                || flags & FLAG_SELLER_DELIVERED == 0)
+    };
+
+    // Option off: current behavior, delivered or not
+    assert!(buyer_timeout_refund(0, TIMEOUT_MODE_REFUND));
+    assert!(buyer_timeout_refund(FLAG_SELLER_DELIVERED, TIMEOUT_MODE_REFUND));
+
+    // Option on: only before delivery
+    let flags = FLAG_REFUND_REQUIRES_NO_DELIVERY;
+    assert!(buyer_timeout_refund(flags, TIMEOUT_MODE_REFUND));
+    assert!(!buyer_timeout_refund(flags | FLAG_SELLER_DELIVERED, TIMEOUT_MODE_REFUND));
+
+    // Freeze mode never lets the buyer refund on timeout
+    assert!(!buyer_timeout_refund(0, TIMEOUT_MODE_FREEZE));
+}
+
+#[test]
+fn test_assert_distinct() {
+    // Mirrors assert_distinct: returns the first colliding pair
+    fn first_collision(keys: &[&[u8; 32]]) -> Option<(usize, usize)> {
+        for (i, a) in keys.iter().enumerate() {
+            for (j, b) in keys.iter().enumerate().skip(i + 1) {
+                if a == b {
+                    return Some((i, j));
+                }
+            }
+        }
+        None
+    }
+
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let arbitrator = [3u8; 32];
+    let escrow = [4u8; 32];
+
+    assert_eq!(first_collision(&[&buyer, &seller, &arbitrator, &escrow]), None);
+    assert_eq!(first_collision(&[&buyer, &seller, &buyer, &escrow]), Some((0, 2)));
+    assert_eq!(first_collision(&[&buyer, &seller, &arbitrator, &seller]), Some((1, 3)));
+}
+
+#[test]
+fn test_release_if_price() {
+    // Pyth v2 price account: magic, atype, aggregate price and status
+    let mut oracle = [0u8; 240];
+    oracle[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    oracle[8..12].copy_from_slice(&3u32.to_le_bytes());
+    oracle[208..216].copy_from_slice(&2_500_000i64.to_le_bytes());
+    oracle[224..228].copy_from_slice(&1u32.to_le_bytes());
+
+    let mut data = [0u8; ESCROW_SIZE];
+    let oracle_key = [9u8; 32];
+    data[OFF_PRICE_ORACLE..OFF_PRICE_ORACLE + 32].copy_from_slice(&oracle_key);
+    data[OFF_PRICE_THRESHOLD..OFF_PRICE_THRESHOLD + 8].copy_from_slice(&2_000_000i64.to_le_bytes());
+
+    let price = i64::from_le_bytes(oracle[208..216].try_into().unwrap());
+    let threshold = i64::from_le_bytes(data[OFF_PRICE_THRESHOLD..OFF_PRICE_THRESHOLD + 8].try_into().unwrap());
+    assert!(price >= threshold);
+
+    // Below the threshold the funds stay escrowed
+    oracle[208..216].copy_from_slice(&1_999_999i64.to_le_bytes());
+    let price = i64::from_le_bytes(oracle[208..216].try_into().unwrap());
+    assert!(price < threshold);
+
+    // Negative thresholds round-trip through the u64 field
+    data[OFF_PRICE_THRESHOLD..OFF_PRICE_THRESHOLD + 8].copy_from_slice(&(-5i64 as u64).to_le_bytes());
+    let stored = u64::from_le_bytes(data[OFF_PRICE_THRESHOLD..OFF_PRICE_THRESHOLD + 8].try_into().unwrap());
+    assert_eq!(stored as i64, -5);
+
+    // Mirrors read_oracle_price: the aggregate's publish slot may trail the
+    // current slot by at most MAX_ORACLE_AGE_SLOTS
+    const MAX_ORACLE_AGE_SLOTS: u64 = 50;
+    let fresh = |oracle: &[u8], current_slot: u64| {
+        let published = u64::from_le_bytes(oracle[232..240].try_into().unwrap());
+        current_slot.saturating_sub(published) <= MAX_ORACLE_AGE_SLOTS
+    };
+    oracle[232..240].copy_from_slice(&1_000u64.to_le_bytes());
+    assert!(fresh(&oracle, 1_000));
+    assert!(fresh(&oracle, 1_000 + MAX_ORACLE_AGE_SLOTS));
+    assert!(!fresh(&oracle, 1_000 + MAX_ORACLE_AGE_SLOTS + 1));
+    // A publish slot ahead of the clock isn't stale
+    assert!(fresh(&oracle, 999));
+}
+
+#[test]
+fn test_settled_amount_zeroed() {
+    let mut data = [0u8; ESCROW_SIZE];
+    let amount: u64 = 1_000_000;
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+    data[OFF_FUNDED_AMOUNT..OFF_FUNDED_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+
+    // Terminal transition: status flips and the remaining amount drops to zero
+    data[OFF_STATUS] = STATUS_RELEASED;
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&0u64.to_le_bytes());
+
+    let remaining = u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap());
+    let funded = u64::from_le_bytes(data[OFF_FUNDED_AMOUNT..OFF_FUNDED_AMOUNT + 8].try_into().unwrap());
+    assert_eq!(remaining, 0);
+    assert_eq!(funded, amount);
+}
+
+#[test]
+fn test_settlement_proposal() {
+    const SETTLEMENT_RATIFY_WINDOW_SECONDS: u64 = 259_200;
+
+    let mut data = [0u8; ESCROW_SIZE];
+    let proposed_at: u64 = 1_700_000_000;
+    data[OFF_PROPOSED_BUYER_BPS..OFF_PROPOSED_BUYER_BPS + 2].copy_from_slice(&2_500u16.to_le_bytes());
+    data[OFF_PROPOSED_AT..OFF_PROPOSED_AT + 8].copy_from_slice(&proposed_at.to_le_bytes());
+
+    // Split: 25% back to the buyer, the rest to the seller
+    let amount: u64 = 1_000_001;
+    let bps = u16::from_le_bytes([data[OFF_PROPOSED_BUYER_BPS], data[OFF_PROPOSED_BUYER_BPS + 1]]) as u64;
+    let buyer_share = (amount as u128 * bps as u128 / 10_000) as u64;
+    let seller_share = amount - buyer_share;
+    assert_eq!(buyer_share, 250_000);
+    assert_eq!(buyer_share + seller_share, amount);
+
+    // Pooled funders get a pro-rata slice of the buyer share, dust to the buyer
+    let contributions = [600_001u64, 400_000];
+    let cofunder_share = (contributions[1] as u128 * buyer_share as u128 / amount as u128) as u64;
+    let buyer_refund = buyer_share - cofunder_share;
+    assert_eq!(cofunder_share, 99_999);
+    assert_eq!(buyer_refund + cofunder_share, buyer_share);
+
+    // Unilateral Arbitrate is blocked until the ratify window runs out
+    let deadline = proposed_at + SETTLEMENT_RATIFY_WINDOW_SECONDS;
+    assert!(proposed_at + 100 < deadline);
+    assert!(proposed_at + SETTLEMENT_RATIFY_WINDOW_SECONDS >= deadline);
+}
+
+#[test]
+fn test_arbitrate_dispute_origin() {
+    fn sided_with_disputer(flags: u8, decision: u8) -> bool {
+        (decision == 0 && flags & FLAG_BUYER_DISPUTED != 0)
+            || (decision != 0 && flags & FLAG_SELLER_DISPUTED != 0)
+    }
+
+    // Buyer complained: a refund sides with them, a release does not
+    assert!(sided_with_disputer(FLAG_BUYER_DISPUTED, 0));
+    assert!(!sided_with_disputer(FLAG_BUYER_DISPUTED, 1));
+
+    // Seller complained: the reverse
+    assert!(sided_with_disputer(FLAG_SELLER_DISPUTED, 1));
+    assert!(!sided_with_disputer(FLAG_SELLER_DISPUTED, 0));
+
+    // Event data keeps only the dispute bits
+    let flags = FLAG_SELLER_DELIVERED | FLAG_SELLER_DISPUTED;
+    let dispute_flags = flags & (FLAG_BUYER_DISPUTED | FLAG_SELLER_DISPUTED);
+    let ret = [1u8, dispute_flags, sided_with_disputer(dispute_flags, 1) as u8];
+    assert_eq!(ret, [1, FLAG_SELLER_DISPUTED, 1]);
+}
+
+// Mirrors apply_bps: floor(amount * bps / 10_000), bps capped at 10_000
+fn apply_bps(amount: u64, bps: u16) -> u64 {
+    let bps = (bps as u64).min(10_000);
+    (amount as u128)
+        .checked_mul(bps as u128)
+        .and_then(|v| v.checked_div(10_000))
+        .unwrap_or(0) as u64
+}
+
+#[test]
+fn test_apply_bps_rounds_down() {
+    // Floor, never up: the fee side never gains a lamport from rounding
+    assert_eq!(apply_bps(9_999, 1), 0);
+    assert_eq!(apply_bps(10_000, 1), 1);
+    assert_eq!(apply_bps(19_999, 1), 1);
+    assert_eq!(apply_bps(1, 9_999), 0);
+    assert_eq!(apply_bps(3, 3_333), 0);
+    assert_eq!(apply_bps(u64::MAX, 10_000), u64::MAX);
+    assert_eq!(apply_bps(u64::MAX, 5_000), u64::MAX / 2);
+
+    // Out-of-range bps is capped, not amplified
+    assert_eq!(apply_bps(1_000, u16::MAX), 1_000);
+
+    // The share plus the remainder to the principal recipient reconcile
+    // exactly, for every amount and rate
+    for amount in [0u64, 1, 7, 9_999, 10_001, 123_456_789, u64::MAX - 1, u64::MAX] {
+        for bps in [0u16, 1, 2, 333, 2_500, 3_333, 6_667, 9_999, 10_000] {
+            let share = apply_bps(amount, bps);
+            assert!(share <= amount);
+            assert_eq!(share + (amount - share), amount);
+            // Exact floor: share * 10_000 <= amount * bps < (share + 1) * 10_000
+            let exact = amount as u128 * bps as u128;
+            assert!(share as u128 * 10_000 <= exact);
+            assert!(exact < (share as u128 + 1) * 10_000);
+        }
+    }
+}
+
+#[test]
+fn test_split_amount_rounding() {
+    // Mirrors split_amount: buyer rounds down, seller takes the remainder
+    fn split_amount(amount: u64, buyer_bps: u16) -> (u64, u64) {
+        let buyer_share = apply_bps(amount, buyer_bps);
+        (buyer_share, amount - buyer_share)
+    }
+
+    let amounts = [0u64, 1, 3, 9_999, 10_001, 1_000_000_007, u64::MAX];
+    let bps = [0u16, 1, 3_333, 5_000, 6_667, 9_999, 10_000];
+    for &amount in &amounts {
+        for &b in &bps {
+            let (buyer, seller) = split_amount(amount, b);
+            // No fee is withheld, so nothing is lost or created
+            assert_eq!(buyer as u128 + seller as u128, amount as u128);
+        }
+    }
+
+    assert_eq!(split_amount(1_000, 0), (0, 1_000));
+    assert_eq!(split_amount(1_000, 10_000), (1_000, 0));
+    assert_eq!(split_amount(3, 5_000), (1, 2));
+
+    // An arbitrated split is the one payout where a fee reaches the buyer's
+    // side: ruling_payout takes the arbitrator fee off the top, then divides
+    // the rest, so both shares shrink and all three still add up to `amount`
+    fn ruling_payout(amount: u64, seller_bps: u16, arbitrator_fee: u64) -> (u64, u64, u64) {
+        let fee = arbitrator_fee.min(amount);
+        let seller_share = apply_bps(amount - fee, seller_bps);
+        (amount - fee - seller_share, seller_share, fee)
+    }
+    for &amount in &amounts {
+        for &b in &bps {
+            for fee in [0, 1, amount / 3, amount] {
+                let (buyer, seller, paid) = ruling_payout(amount, 10_000 - b, fee);
+                assert_eq!(buyer as u128 + seller as u128 + paid as u128, amount as u128);
+            }
+        }
+    }
+    let (no_fee_buyer, no_fee_seller) = split_amount(1_000_000, 4_000);
+    let (buyer, seller, fee) = ruling_payout(1_000_000, 6_000, 100_000);
+    assert_eq!((buyer, seller, fee), (360_000, 540_000, 100_000));
+    assert!(buyer < no_fee_buyer && seller < no_fee_seller);
+}
+
+#[test]
+fn test_seller_allowlist_membership() {
+    const ALLOWLIST_OFF_COUNT: usize = 41;
+    const ALLOWLIST_OFF_SELLERS: usize = 42;
+    const ALLOWLIST_SIZE: usize = ALLOWLIST_OFF_SELLERS + 32 * 32;
+
+    fn is_allowed(data: &[u8], seller: &[u8; 32]) -> bool {
+        let count = data[ALLOWLIST_OFF_COUNT] as usize;
+        data[ALLOWLIST_OFF_SELLERS..ALLOWLIST_OFF_SELLERS + count * 32]
+            .chunks_exact(32)
+            .any(|k| k == seller)
+    }
+
+    let mut data = [0u8; ALLOWLIST_SIZE];
+    let vetted = [2u8; 32];
+    let other = [3u8; 32];
+
+    assert!(!is_allowed(&data, &vetted));
+
+    data[ALLOWLIST_OFF_SELLERS..ALLOWLIST_OFF_SELLERS + 32].copy_from_slice(&vetted);
+    data[ALLOWLIST_OFF_COUNT] = 1;
+    assert!(is_allowed(&data, &vetted));
+    assert!(!is_allowed(&data, &other));
+
+    // Entries past the count are ignored even if stale bytes remain
+    data[ALLOWLIST_OFF_COUNT] = 0;
+    assert!(!is_allowed(&data, &vetted));
+}
+
+#[test]
+fn test_close_then_recreate() {
+    // Mirrors the CreateEscrowV2 re-init guard
+    fn can_create(lamports: u64, data_len: usize) -> bool {
+        let _ = lamports; // pre-funded addresses take the allocate/assign path
+        data_len == 0
+    }
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+    data[OFF_STATUS] = STATUS_RELEASED;
+    let mut lamports: u64 = 5_791_680; // rent only, the amount was paid out
+    assert!(!can_create(lamports, data.len()));
+
+    // CloseEscrow: wipe data, return every lamport, drop the data length
+    data.fill(0);
+    let returned = lamports;
+    lamports -= returned;
+    let data_len = 0;
+
+    assert_eq!(returned, 5_791_680);
+    assert!(data.iter().all(|&b| b == 0));
+    assert!(can_create(lamports, data_len));
+}
+
+#[test]
+fn test_create_target_preconditions() {
+    const SYSTEM_PROGRAM_ID: [u8; 32] = [0u8; 32];
+    let program_id = [9u8; 32];
+    let rent: u64 = 7_057_920;
+
+    // Mirrors validate_create's account checks and the path it takes
+    #[derive(Debug, PartialEq)]
+    enum Path {
+        CreateAccount,
+        AllocateAssign { top_up: u64 },
+        NotSystemOwned,
+        AlreadyInitialized,
+    }
+    let path = |owner: [u8; 32], lamports: u64, data_len: usize| {
+        if owner != SYSTEM_PROGRAM_ID {
+            Path::NotSystemOwned
+        } else if data_len != 0 {
+            Path::AlreadyInitialized
+        } else if lamports == 0 {
+            Path::CreateAccount
+        } else {
+            Path::AllocateAssign { top_up: rent.saturating_sub(lamports) }
+        }
+    };
+
+    assert_eq!(path(SYSTEM_PROGRAM_ID, 0, 0), Path::CreateAccount);
+    assert_eq!(path(SYSTEM_PROGRAM_ID, 1_000, 0), Path::AllocateAssign { top_up: rent - 1_000 });
+    assert_eq!(path(SYSTEM_PROGRAM_ID, rent * 2, 0), Path::AllocateAssign { top_up: 0 });
+    assert_eq!(path(program_id, rent, ESCROW_SIZE), Path::NotSystemOwned);
+    assert_eq!(path(SYSTEM_PROGRAM_ID, rent, 8), Path::AlreadyInitialized);
+}
+
+#[test]
+fn test_settle_and_close_lamports() {
+    let rent: u64 = 7_057_920; // rent-exempt minimum for the escrow
+    let amount: u64 = 1_000_000_000;
+
+    // RefundAndClose: the buyer gets the refund and the rent back
+    let mut escrow_lamports = amount + rent;
+    let mut buyer: u64 = 10_000_000;
+    escrow_lamports -= amount;
+    buyer += amount;
+    buyer += escrow_lamports; // close_to_rent_recipient sweeps the rest
+    escrow_lamports = 0;
+    assert_eq!(buyer, 10_000_000 + amount + rent);
+    assert_eq!(escrow_lamports, 0);
+
+    // ReleaseAndClose: the seller gets the amount, the buyer still gets the rent
+    let mut escrow_lamports = amount + rent;
+    let mut seller: u64 = 0;
+    let mut buyer: u64 = 10_000_000;
+    escrow_lamports -= amount;
+    seller += amount;
+    buyer += escrow_lamports;
+    assert_eq!(seller, amount);
+    assert_eq!(buyer, 10_000_000 + rent);
+}
+
+#[test]
+fn test_split_escrow_carve_out() {
+    let rent_minimum: u64 = 5_791_680;
+    let amount: u64 = 1_000_000;
+    let parent_lamports = amount + rent_minimum;
+
+    // Mirrors SplitEscrow's checks: nonzero on both sides, parent stays rent-exempt
+    let valid = |carve: u64| {
+        carve > 0
+            && carve < amount
+            && parent_lamports - carve >= rent_minimum + (amount - carve)
+    };
+
+    assert!(valid(400_000));
+    assert!(valid(1));
+    assert!(valid(amount - 1));
+    assert!(!valid(0));
+    assert!(!valid(amount));
+
+    // Lamports are conserved between parent and child
+    let carve = 400_000;
+    let parent_after = parent_lamports - carve;
+    let child_funds = carve;
+    assert_eq!(parent_after + child_funds, parent_lamports);
+    assert_eq!(parent_after - rent_minimum, amount - carve);
+}
+
+#[test]
+fn test_closer_reward_split() {
+    const CLOSER_REWARD_LAMPORTS: u64 = 10_000;
+    let rent: u64 = 5_791_680;
+
+    // A keeper closing takes the reward, the buyer gets the rest
+    let keeper_share = CLOSER_REWARD_LAMPORTS;
+    let buyer_share = rent - keeper_share;
+    assert_eq!(keeper_share + buyer_share, rent);
+
+    // The buyer closing their own escrow gets everything
+    let keeper_share = 0;
+    assert_eq!(rent - keeper_share, rent);
+}
+
+#[test]
+fn test_milestone_bits() {
+    let mut data = [0u8; ESCROW_SIZE];
+    let amounts = [300u64, 200, 500];
+    data[OFF_MILESTONE_COUNT] = amounts.len() as u8;
+    for (i, a) in amounts.iter().enumerate() {
+        let offset = OFF_MILESTONE_AMOUNTS + i * 8;
+        data[offset..offset + 8].copy_from_slice(&a.to_le_bytes());
+    }
+    let mut amount: u64 = amounts.iter().sum();
+    let all_settled = (1u16 << amounts.len()) - 1;
+
+    for index in [1usize, 0, 2] {
+        let bit = 1u8 << index;
+
+        // Not deliverable twice, not acceptable before delivery
+        assert_eq!(data[OFF_MILESTONE_DELIVERED] & bit, 0);
+        data[OFF_MILESTONE_DELIVERED] |= bit;
+
+        // Accept releases exactly that milestone
+        assert_eq!(data[OFF_MILESTONE_SETTLED] & bit, 0);
+        data[OFF_MILESTONE_SETTLED] |= bit;
+        let offset = OFF_MILESTONE_AMOUNTS + index * 8;
+        amount -= u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    }
+
+    assert_eq!(amount, 0);
+    assert_eq!(data[OFF_MILESTONE_SETTLED] as u16, all_settled);
+
+    // Indices at or past the count are out of range
+    assert!(3 >= data[OFF_MILESTONE_COUNT] as usize);
+}
+
+#[test]
+fn test_settlement_callback_data() {
+    const CALLBACK_DISC: u64 = 0x504143545345544C; // "PACTSETL"
+    let mut escrow = [0u8; ESCROW_SIZE];
+    let funded: u64 = 1_500_000;
+    escrow[OFF_FUNDED_AMOUNT..OFF_FUNDED_AMOUNT + 8].copy_from_slice(&funded.to_le_bytes());
+
+    // No callback program stored: nothing to notify
+    assert_eq!(&escrow[OFF_CALLBACK_PROGRAM..OFF_CALLBACK_PROGRAM + 32], &[0u8; 32]);
+
+    let mut data = [0u8; 17];
+    data[0..8].copy_from_slice(&CALLBACK_DISC.to_le_bytes());
+    data[8] = STATUS_RELEASED;
+    data[9..17].copy_from_slice(&escrow[OFF_FUNDED_AMOUNT..OFF_FUNDED_AMOUNT + 8]);
+
+    let disc: String = data[0..8].iter().rev().map(|&b| b as char).collect();
+    assert_eq!(disc, "PACTSETL");
+    assert_eq!(data[8], STATUS_RELEASED);
+    assert_eq!(u64::from_le_bytes(data[9..17].try_into().unwrap()), funded);
+}
+
+#[test]
+fn test_inspect_escrow_invariants() {
+    const INVARIANT_DISCRIMINATOR: u8 = 1 << 0;
+    const INVARIANT_FLAGS: u8 = 1 << 3;
+    const INVARIANT_BUMP: u8 = 1 << 5;
+    const INVARIANTS_ALL: u8 = (1 << 6) - 1;
+    assert_eq!(INVARIANTS_ALL, 0b11_1111);
+    assert_eq!(INVARIANTS_ALL & !INVARIANT_BUMP, 0b01_1111); // legacy escrow without a seed
+
+    // Mirrors flags_consistent
+    let flags_consistent = |status: u8, flags: u8| {
+        let delivered = flags & FLAG_SELLER_DELIVERED != 0;
+        let disputed = flags & (FLAG_BUYER_DISPUTED | FLAG_SELLER_DISPUTED) != 0;
+        match status {
+            STATUS_ACTIVE => !delivered && !disputed,
+            STATUS_DELIVERED => delivered && !disputed,
+            STATUS_DISPUTED | STATUS_APPEALED => disputed,
+            _ => true,
+        }
+    };
+
+    assert!(flags_consistent(STATUS_ACTIVE, 0));
+    assert!(flags_consistent(STATUS_ACTIVE, FLAG_FROZEN));
+    assert!(!flags_consistent(STATUS_ACTIVE, FLAG_SELLER_DELIVERED));
+    assert!(flags_consistent(STATUS_DELIVERED, FLAG_SELLER_DELIVERED));
+    assert!(!flags_consistent(STATUS_DELIVERED, 0));
+    assert!(flags_consistent(STATUS_DISPUTED, FLAG_SELLER_DELIVERED | FLAG_BUYER_DISPUTED));
+    assert!(!flags_consistent(STATUS_DISPUTED, FLAG_SELLER_DELIVERED));
+    assert!(flags_consistent(STATUS_REFUNDED, FLAG_SELLER_DISPUTED));
+
+    // Bits are distinct
+    let bits = [INVARIANT_DISCRIMINATOR, 1 << 1, 1 << 2, INVARIANT_FLAGS, 1 << 4, INVARIANT_BUMP];
+    assert_eq!(bits.iter().fold(0, |acc, b| acc | b), INVARIANTS_ALL);
+}
+
+#[test]
+fn test_auto_release_window() {
+    let mut data = [0u8; ESCROW_SIZE];
+    let delivered_at: u64 = 1_700_000_000;
+    let window: u64 = 7 * 86_400;
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    data[OFF_FLAGS] = FLAG_SELLER_DELIVERED;
+    data[OFF_DELIVERED_AT..OFF_DELIVERED_AT + 8].copy_from_slice(&delivered_at.to_le_bytes());
+    data[OFF_ACCEPTANCE_WINDOW..OFF_ACCEPTANCE_WINDOW + 8].copy_from_slice(&window.to_le_bytes());
+
+    // Mirrors AutoRelease's checks
+    let can_auto_release = |data: &[u8], now: u64| {
+        let read = |off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+        let window = read(OFF_ACCEPTANCE_WINDOW);
+        let delivered_at = read(OFF_DELIVERED_AT);
+        data[OFF_STATUS] == STATUS_DELIVERED
+            && data[OFF_FLAGS] & (FLAG_BUYER_DISPUTED | FLAG_SELLER_DISPUTED) == 0
+            && window != 0
+            && delivered_at != 0
+            && now >= delivered_at.saturating_add(window)
+    };
+
+    assert!(!can_auto_release(&data, delivered_at + window - 1));
+    assert!(can_auto_release(&data, delivered_at + window));
+
+    // A dispute takes it out of the crank's hands
+    let mut disputed = data;
+    disputed[OFF_STATUS] = STATUS_DISPUTED;
+    disputed[OFF_FLAGS] |= FLAG_BUYER_DISPUTED;
+    assert!(!can_auto_release(&disputed, u64::MAX));
+
+    // No window configured: never auto-releases
+    let mut no_window = data;
+    no_window[OFF_ACCEPTANCE_WINDOW..OFF_ACCEPTANCE_WINDOW + 8].copy_from_slice(&0u64.to_le_bytes());
+    assert!(!can_auto_release(&no_window, u64::MAX));
+
+    // Creation caps the window at 30 days
+    const MAX_ACCEPTANCE_WINDOW_SECONDS: u64 = 2_592_000;
+    let window_ok = |w: u64| w <= MAX_ACCEPTANCE_WINDOW_SECONDS;
+    assert!(window_ok(window));
+    assert!(window_ok(30 * 86_400));
+    assert!(!window_ok(30 * 86_400 + 1));
+}
+
+#[test]
+fn test_signer_role() {
+    // Mirrors assert_signer_role: a signature only counts from the stored key
+    #[derive(Debug, PartialEq)]
+    enum Check {
+        Ok,
+        MissingSignature,
+        WrongRole,
+    }
+    let check = |key: [u8; 32], is_signer: bool, stored: [u8; 32]| {
+        if !is_signer {
+            Check::MissingSignature
+        } else if key != stored {
+            Check::WrongRole
+        } else {
+            Check::Ok
+        }
+    };
+
+    let seller = [2u8; 32];
+    let escrow = [9u8; 32];
+    assert_eq!(check(seller, true, seller), Check::Ok);
+    assert_eq!(check(seller, false, seller), Check::MissingSignature);
+    // The escrow passed first by mistake signed nothing it can act on
+    assert_eq!(check(escrow, true, seller), Check::WrongRole);
+}
+
+#[test]
+fn test_absolute_deadline() {
+    // Mirrors timeout_reached
+    fn timeout_reached(data: &[u8], now: u64) -> bool {
+        let read = |off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+        let timeout = read(OFF_TIMEOUT);
+        if timeout == 0 {
+            return false;
+        }
+        if data[OFF_FLAGS] & FLAG_ABSOLUTE_DEADLINE != 0 {
+            return now >= timeout;
+        }
+        now.saturating_sub(read(OFF_CREATED_AT)) >= timeout
+    }
+
+    let created_at: u64 = 1_700_000_000;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_CREATED_AT..OFF_CREATED_AT + 8].copy_from_slice(&created_at.to_le_bytes());
+
+    // Relative: created_at + timeout_seconds
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&3_600u64.to_le_bytes());
+    assert!(!timeout_reached(&data, created_at + 3_599));
+    assert!(timeout_reached(&data, created_at + 3_600));
+
+    // Absolute: the stored deadline itself, regardless of created_at
+    let event_date: u64 = 1_750_000_000;
+    data[OFF_FLAGS] |= FLAG_ABSOLUTE_DEADLINE;
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&event_date.to_le_bytes());
+    assert!(!timeout_reached(&data, event_date - 1));
+    assert!(timeout_reached(&data, event_date));
+
+    // No timeout at all
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&0u64.to_le_bytes());
+    assert!(!timeout_reached(&data, u64::MAX));
+}
+
+#[test]
+fn test_timeout_overflow() {
+    // Mirrors timeout_reached for a relative timeout
+    let timeout_reached = |created_at: u64, timeout: u64, now: u64| {
+        timeout != 0 && now.saturating_sub(created_at) >= timeout
+    };
+    // Mirrors assert_timeout_fits, run by CreateEscrowV2 and SplitEscrow
+    let fits = |now: u64, timeout: u64| now.checked_add(timeout).is_some();
+
+    let created_at: u64 = 1_700_000_000;
+
+    // A plain created_at + u64::MAX wraps to just before created_at, which
+    // would read as long expired
+    assert_eq!(created_at.wrapping_add(u64::MAX), created_at - 1);
+
+    // Comparing elapsed time instead never lets the buyer refund early
+    for now in [created_at, created_at + 1, created_at + 365 * 86_400, u64::MAX - 1] {
+        assert!(!timeout_reached(created_at, u64::MAX, now));
+    }
+
+    // And such a timeout is refused at creation
+    assert!(!fits(created_at, u64::MAX));
+    assert!(!fits(created_at, u64::MAX - created_at + 1));
+    assert!(fits(created_at, u64::MAX - created_at));
+    assert!(fits(created_at, 259_200));
+}
+
+#[test]
+fn test_clock_behind_created_at() {
+    // Mirrors unix_now and elapsed_since
+    let unix_now = |unix_timestamp: i64| unix_timestamp.max(0) as u64;
+    let elapsed_since = |now: u64, since: u64| now.saturating_sub(since);
+
+    let created_at: u64 = 1_700_000_000;
+    let timeout: u64 = 3_600;
+
+    // A clock that reads earlier than created_at: no time has passed
+    let skewed = unix_now(1_699_999_000);
+    assert_eq!(elapsed_since(skewed, created_at), 0);
+    assert!(elapsed_since(skewed, created_at) < timeout);
+
+    // A negative timestamp must not wrap into a far-future "now"
+    assert_eq!(unix_now(-1), 0);
+    assert_ne!(unix_now(-1), -1i64 as u64);
+    assert!(elapsed_since(unix_now(-1), created_at) < timeout);
+
+    // The normal case is unchanged
+    assert_eq!(elapsed_since(unix_now(1_700_003_600), created_at), timeout);
+}
+
+#[test]
+fn test_double_payout_single_transfer() {
+    // Two payouts against one escrow in the same transaction see each other's
+    // writes. Mirrors a payout handler: check, record the settlement, re-check
+    // (assert_settled), then move lamports.
+    fn payout(data: &mut [u8], lamports: &mut u64, dest: &mut u64, terminal: u8) -> Result<(), ()> {
+        let status = data[OFF_STATUS];
+        if status == STATUS_RELEASED || status == STATUS_REFUNDED {
+            return Err(());
+        }
+        let amount = u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap());
+
+        data[OFF_STATUS] = terminal;
+        data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&0u64.to_le_bytes());
+
+        let settled = data[OFF_STATUS] == STATUS_RELEASED || data[OFF_STATUS] == STATUS_REFUNDED;
+        if !settled || data[OFF_AMOUNT..OFF_AMOUNT + 8] != [0u8; 8] {
+            return Err(());
+        }
+        *lamports -= amount;
+        *dest += amount;
+        Ok(())
+    }
+
+    let amount: u64 = 1_000_000;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+    let mut escrow_lamports = amount + 5_791_680;
+    let (mut seller, mut buyer) = (0u64, 0u64);
+
+    assert!(payout(&mut data, &mut escrow_lamports, &mut seller, STATUS_RELEASED).is_ok());
+    assert!(payout(&mut data, &mut escrow_lamports, &mut buyer, STATUS_REFUNDED).is_err());
+    assert!(payout(&mut data, &mut escrow_lamports, &mut seller, STATUS_RELEASED).is_err());
+
+    assert_eq!(seller, amount);
+    assert_eq!(buyer, 0);
+    assert_eq!(escrow_lamports, 5_791_680);
+}
+
+#[test]
+fn test_renew_escrow_periods() {
+    let period: u64 = 30 * 86_400;
+    let period_count: u16 = 3;
+    let amount: u64 = 3_000_000;
+    assert_eq!(amount % period_count as u64, 0);
+
+    let mut data = [0u8; ESCROW_SIZE];
+    let mut created_at: u64 = 1_700_000_000;
+    data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2].copy_from_slice(&period_count.to_le_bytes());
+    data[OFF_PERIOD_AMOUNT..OFF_PERIOD_AMOUNT + 8].copy_from_slice(&(amount / 3).to_le_bytes());
+
+    // Mirrors RenewEscrow, cranked late each time
+    let mut remaining_amount = amount;
+    let mut paid = 0;
+    let mut status = STATUS_ACTIVE;
+    loop {
+        let periods = u16::from_le_bytes([data[OFF_PERIODS_REMAINING], data[OFF_PERIODS_REMAINING + 1]]);
+        if periods == 0 {
+            break;
+        }
+        let period_amount = u64::from_le_bytes(data[OFF_PERIOD_AMOUNT..OFF_PERIOD_AMOUNT + 8].try_into().unwrap())
+            .min(remaining_amount);
+        created_at += period; // schedule doesn't drift with the late crank
+        data[OFF_PERIODS_REMAINING..OFF_PERIODS_REMAINING + 2].copy_from_slice(&(periods - 1).to_le_bytes());
+        remaining_amount -= period_amount;
+        paid += period_amount;
+        if periods == 1 {
+            status = STATUS_RELEASED;
+        }
+    }
+
+    assert_eq!(paid, amount);
+    assert_eq!(remaining_amount, 0);
+    assert_eq!(status, STATUS_RELEASED);
+    assert_eq!(created_at, 1_700_000_000 + 3 * period);
+}
+
+#[test]
+fn test_subscription_revoke_keeps_served_period() {
+    let period: u64 = 30 * 86_400;
+    let period_amount: u64 = 1_000_000;
+    let created_at: u64 = 1_700_000_000;
+
+    // Mirrors RefundV2's split: (seller share, buyer refund), or None if the
+    // crank has to pay out the ended period first
+    let revoke = |amount: u64, periods_remaining: u16, by_buyer: bool, now: u64| {
+        let seller_share = if by_buyer && periods_remaining != 0 {
+            if now - created_at >= period {
+                return None;
+            }
+            period_amount.min(amount)
+        } else {
+            0
+        };
+        Some((seller_share, amount - seller_share))
+    };
+
+    // Revoked a day into the first of three periods: that one is the seller's
+    assert_eq!(revoke(3_000_000, 3, true, created_at + 86_400), Some((1_000_000, 2_000_000)));
+    // ...and right at the start of the last one
+    assert_eq!(revoke(1_000_000, 1, true, created_at), Some((1_000_000, 0)));
+    // Once a period has ended the buyer can't take it back ahead of the crank
+    assert_eq!(revoke(3_000_000, 3, true, created_at + period), None);
+    assert_eq!(revoke(3_000_000, 3, true, created_at + 5 * period), None);
+    // The seller refunding, or a one-off escrow, returns everything
+    assert_eq!(revoke(3_000_000, 3, false, created_at + period), Some((0, 3_000_000)));
+    assert_eq!(revoke(3_000_000, 0, true, created_at + period), Some((0, 3_000_000)));
+}
+
+#[test]
+fn test_seller_reputation_counters() {
+    const REPUTATION_DISC: u64 = 0x5041435452455055;
+    const REPUTATION_OFF_SELLER: usize = 8;
+    const REPUTATION_OFF_BUMP: usize = 40;
+    const REPUTATION_OFF_COMPLETED: usize = 41;
+    const REPUTATION_OFF_DISPUTED_AGAINST: usize = 49;
+    const REPUTATION_SIZE: usize = 57;
+
+    assert_eq!(REPUTATION_SIZE, 8 + 32 + 1 + 8 + 8);
+    assert_eq!(REPUTATION_OFF_BUMP + 1, REPUTATION_OFF_COMPLETED);
+    assert_eq!(REPUTATION_OFF_DISPUTED_AGAINST + 8, REPUTATION_SIZE);
+    let disc: String = REPUTATION_DISC.to_le_bytes().iter().rev().map(|&b| b as char).collect();
+    assert_eq!(disc, "PACTREPU");
+
+    // Never mistaken for an escrow when scanning the accounts
+    assert_ne!(REPUTATION_SIZE, ESCROW_SIZE);
+
+    let seller = [2u8; 32];
+    let mut data = [0u8; REPUTATION_SIZE];
+    data[0..8].copy_from_slice(&REPUTATION_DISC.to_le_bytes());
+    data[REPUTATION_OFF_SELLER..REPUTATION_OFF_SELLER + 32].copy_from_slice(&seller);
+
+    let bump = |data: &mut [u8], offset: usize| {
+        let count = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap()).saturating_add(1);
+        data[offset..offset + 8].copy_from_slice(&count.to_le_bytes());
+    };
+    bump(&mut data, REPUTATION_OFF_COMPLETED); // AcceptDelivery
+    bump(&mut data, REPUTATION_OFF_COMPLETED); // ReleaseV2
+    bump(&mut data, REPUTATION_OFF_DISPUTED_AGAINST); // arbitrated refund
+
+    let read = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+    assert_eq!(read(REPUTATION_OFF_COMPLETED), 2);
+    assert_eq!(read(REPUTATION_OFF_DISPUTED_AGAINST), 1);
+}
+
+#[test]
+fn test_partial_accept_keep_open() {
+    let amount: u64 = 1_000_000;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    data[OFF_FLAGS] = FLAG_SELLER_DELIVERED;
+    data[OFF_DELIVERED_AT..OFF_DELIVERED_AT + 8].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+
+    // Accepted part must be positive and leave something open
+    let valid = |accepted: u64| accepted > 0 && accepted < amount;
+    assert!(!valid(0));
+    assert!(!valid(amount));
+    assert!(valid(400_000));
+
+    // Mirrors PartialAcceptKeepOpen
+    let accepted: u64 = 400_000;
+    let now: u64 = 1_700_100_000;
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&(amount - accepted).to_le_bytes());
+    data[OFF_CREATED_AT..OFF_CREATED_AT + 8].copy_from_slice(&now.to_le_bytes());
+    data[OFF_DELIVERED_AT..OFF_DELIVERED_AT + 8].copy_from_slice(&0u64.to_le_bytes());
+    data[OFF_FLAGS] &= !FLAG_SELLER_DELIVERED;
+    data[OFF_STATUS] = STATUS_ACTIVE;
+
+    assert_eq!(u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap()), 600_000);
+    assert_eq!(data[OFF_FLAGS] & FLAG_SELLER_DELIVERED, 0);
+    // Active again, so the seller can MarkDelivered the rest
+    assert_eq!(data[OFF_STATUS], STATUS_ACTIVE);
+}
+
+#[test]
+fn test_vested_cancel_fee() {
+    // Mirrors vested_cancel_fee
+    fn vested_cancel_fee(cap: u64, ramp: u64, elapsed: u64, amount: u64) -> u64 {
+        let vested = if ramp == 0 || elapsed >= ramp {
+            cap
+        } else {
+            (cap as u128 * elapsed as u128 / ramp as u128) as u64
+        };
+        vested.min(amount)
+    }
+
+    let amount: u64 = 1_000_000;
+    let cap: u64 = 200_000;
+    let ramp: u64 = 10 * 86_400;
+
+    assert_eq!(vested_cancel_fee(cap, ramp, 0, amount), 0);
+    assert_eq!(vested_cancel_fee(cap, ramp, ramp / 2, amount), 100_000);
+    assert_eq!(vested_cancel_fee(cap, ramp, ramp, amount), cap);
+    assert_eq!(vested_cancel_fee(cap, ramp, u64::MAX, amount), cap);
+    assert_eq!(vested_cancel_fee(cap, 0, 0, amount), cap);
+
+    // Clamped to what's left, so fee + refund never exceeds amount
+    let remaining: u64 = 150_000;
+    let fee = vested_cancel_fee(cap, ramp, ramp, remaining);
+    assert_eq!(fee, remaining);
+    assert_eq!(fee + (remaining - fee), remaining);
+
+    // No fee configured: cancelling is a plain refund
+    assert_eq!(vested_cancel_fee(0, ramp, ramp, amount), 0);
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_CANCEL_FEE_CAP..OFF_CANCEL_FEE_CAP + 8].copy_from_slice(&cap.to_le_bytes());
+    data[OFF_CANCEL_FEE_RAMP..OFF_CANCEL_FEE_RAMP + 8].copy_from_slice(&ramp.to_le_bytes());
+    // With a fee set, the buyer's free refund of an Active escrow is off
+    let fee_set = u64::from_le_bytes(data[OFF_CANCEL_FEE_CAP..OFF_CANCEL_FEE_CAP + 8].try_into().unwrap()) != 0;
+    assert!(fee_set);
+}
+
+#[test]
+fn test_arbitration_rationale() {
+    // Mirrors Arbitrate: decision(1) + optional rationale_hash(32)
+    fn rationale(data: &[u8]) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        if data.len() >= 33 {
+            hash.copy_from_slice(&data[1..33]);
+        }
+        hash
+    }
+    let accepted = |required: u8, hash: [u8; 32]| required == 0 || hash != [0u8; 32];
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_REQUIRE_RATIONALE] = 1;
+
+    // Bare decision, or an all-zero hash, is rejected when required
+    assert!(!accepted(data[OFF_REQUIRE_RATIONALE], rationale(&[1])));
+    let mut ix = [0u8; 33];
+    assert!(!accepted(data[OFF_REQUIRE_RATIONALE], rationale(&ix)));
+
+    ix[1..33].copy_from_slice(&[7u8; 32]);
+    let hash = rationale(&ix);
+    assert!(accepted(data[OFF_REQUIRE_RATIONALE], hash));
+    data[OFF_RATIONALE_HASH..OFF_RATIONALE_HASH + 32].copy_from_slice(&hash);
+    assert_eq!(&data[OFF_RATIONALE_HASH..OFF_RATIONALE_HASH + 32], &[7u8; 32]);
+
+    // Not required: a bare decision still rules
+    assert!(accepted(0, rationale(&[0])));
+
+    // Return data carries the hash after the three event bytes
+    let mut ret = [0u8; 35];
+    ret[0] = 1;
+    ret[3..35].copy_from_slice(&hash);
+    assert_eq!(&ret[3..], &[7u8; 32]);
+}
+
+#[test]
+fn test_terms_hash_check() {
+    // SHA-256("abc"), the FIPS 180-2 test vector
+    const ABC_SHA256: [u8; 32] = [
+        0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+        0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+    ];
+    // Stands in for the sol_sha256 syscall on the inputs below
+    let sha256 = |terms: &[u8]| -> [u8; 32] {
+        if terms == b"abc" { ABC_SHA256 } else { [0xEE; 32] }
+    };
+
+    // Mirrors assert_terms: no terms, no check
+    let check = |data: &[u8], terms: &[u8]| {
+        if !terms.is_empty() && sha256(terms) != data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32] {
+            return Err("InvalidInstructionData");
+        }
+        Ok(())
+    };
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&ABC_SHA256);
+
+    assert_eq!(check(&data, b"abc"), Ok(()));
+    assert_eq!(check(&data, b"abd"), Err("InvalidInstructionData"));
+    assert_eq!(check(&data, b""), Ok(()));
+
+    // Reassign replaces the hash; the old terms no longer prove anything
+    data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&[0xAB; 32]);
+    assert_eq!(check(&data, b"abc"), Err("InvalidInstructionData"));
+
+    // The terms follow the discriminator: AcceptDelivery(2) or Release(3)
+    let mut ix = vec![2u8];
+    ix.extend_from_slice(b"abc");
+    assert_eq!(&ix[1..], b"abc");
+}
+
+#[test]
+fn test_dispute_response_window() {
+    const DISPUTE_RESPONSE_WINDOW_SECONDS: u64 = 86_400;
+    let disputed_at: u64 = 1_700_000_000;
+    let read = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+
+    // The buyer disputes
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_DISPUTED;
+    data[OFF_FLAGS] = FLAG_BUYER_DISPUTED;
+    data[OFF_DISPUTED_AT..OFF_DISPUTED_AT + 8].copy_from_slice(&disputed_at.to_le_bytes());
+
+    // Mirrors Dispute on an escrow that is already Disputed: each party files
+    // once, before the ruling, and disputed_at is kept
+    let respond = |data: &mut [u8], is_buyer: bool, now: u64| {
+        let own = if is_buyer { FLAG_BUYER_DISPUTED } else { FLAG_SELLER_DISPUTED };
+        if data[OFF_FLAGS] & own != 0 || data[OFF_RULING] != RULING_NONE {
+            return Err("InvalidAccountData");
+        }
+        data[OFF_FLAGS] |= own;
+        Ok(now)
+    };
+    // Mirrors Arbitrate's response window check
+    let can_rule = |data: &[u8], now: u64| {
+        let status = data[OFF_STATUS];
+        status != STATUS_DISPUTED
+            || now >= read(data, OFF_DISPUTED_AT).saturating_add(DISPUTE_RESPONSE_WINDOW_SECONDS)
+    };
+
+    assert!(!can_rule(&data, disputed_at));
+    assert!(!can_rule(&data, disputed_at + DISPUTE_RESPONSE_WINDOW_SECONDS - 1));
+
+    // The seller answers inside the window; the window doesn't restart
+    assert_eq!(respond(&mut data, true, disputed_at + 60), Err("InvalidAccountData"));
+    assert!(respond(&mut data, false, disputed_at + 3_600).is_ok());
+    assert_eq!(data[OFF_FLAGS], FLAG_BUYER_DISPUTED | FLAG_SELLER_DISPUTED);
+    assert_eq!(read(&data, OFF_DISPUTED_AT), disputed_at);
+    assert_eq!(respond(&mut data, false, disputed_at + 7_200), Err("InvalidAccountData"));
+
+    // From the end of the window the arbitrator may rule
+    assert!(can_rule(&data, disputed_at + DISPUTE_RESPONSE_WINDOW_SECONDS));
+    assert!(can_rule(&data, disputed_at + DISPUTE_RESPONSE_WINDOW_SECONDS + 1));
+
+    // No responses once a ruling is recorded
+    let mut ruled = [0u8; ESCROW_SIZE];
+    ruled[OFF_STATUS] = STATUS_DISPUTED;
+    ruled[OFF_FLAGS] = FLAG_SELLER_DISPUTED;
+    ruled[OFF_RULING] = RULING_REFUND;
+    assert_eq!(respond(&mut ruled, true, disputed_at), Err("InvalidAccountData"));
+
+    // A frozen escrow nobody disputed has no window
+    let mut frozen = [0u8; ESCROW_SIZE];
+    frozen[OFF_STATUS] = STATUS_ACTIVE;
+    frozen[OFF_FLAGS] = FLAG_FROZEN;
+    assert!(can_rule(&frozen, 0));
+}
+
+#[test]
+fn test_arbitrate_requires_dispute_flags() {
+    // Mirrors Arbitrate's gate: Disputed needs a disputing party; a frozen
+    // escrow under review has none and is still resolvable
+    fn can_arbitrate(status: u8, flags: u8) -> bool {
+        let frozen = flags & FLAG_FROZEN != 0;
+        let resolvable_freeze = frozen && status != STATUS_RELEASED && status != STATUS_REFUNDED;
+        if status != STATUS_DISPUTED && !resolvable_freeze {
+            return false;
+        }
+        !(status == STATUS_DISPUTED && flags & (FLAG_BUYER_DISPUTED | FLAG_SELLER_DISPUTED) == 0)
+    }
+
+    assert!(can_arbitrate(STATUS_DISPUTED, FLAG_BUYER_DISPUTED));
+    assert!(can_arbitrate(STATUS_DISPUTED, FLAG_SELLER_DISPUTED | FLAG_SELLER_DELIVERED));
+    assert!(!can_arbitrate(STATUS_DISPUTED, 0));
+    assert!(!can_arbitrate(STATUS_DISPUTED, FLAG_SELLER_DELIVERED));
+    assert!(!can_arbitrate(STATUS_DISPUTED, FLAG_FROZEN));
+    assert!(can_arbitrate(STATUS_ACTIVE, FLAG_FROZEN));
+}
+
+#[test]
+fn test_notification_flags() {
+    const NOTIFY_ENABLED: u8 = 1 << 7;
+    const UNSEEN_BUYER_DELIVERY: u8 = 1 << 0;
+    const UNSEEN_BUYER_DISPUTE: u8 = 1 << 1;
+    const UNSEEN_SELLER_DISPUTE: u8 = 1 << 2;
+    const UNSEEN_BUYER_RULING: u8 = 1 << 3;
+    const UNSEEN_SELLER_RULING: u8 = 1 << 4;
+    const UNSEEN_BUYER_MASK: u8 = UNSEEN_BUYER_DELIVERY | UNSEEN_BUYER_DISPUTE | UNSEEN_BUYER_RULING;
+    const UNSEEN_SELLER_MASK: u8 = UNSEEN_SELLER_DISPUTE | UNSEEN_SELLER_RULING;
+
+    // Mirrors mark_unseen and MarkSeen
+    fn mark_unseen(data: &mut [u8], bits: u8) {
+        if data[OFF_NOTIFY] & NOTIFY_ENABLED != 0 {
+            data[OFF_NOTIFY] |= bits;
+        }
+    }
+    fn mark_seen(data: &mut [u8], mask: u8, ix: &[u8]) {
+        let clear = ix.first().copied().unwrap_or(mask) & mask;
+        data[OFF_NOTIFY] &= !clear;
+    }
+
+    assert_eq!(UNSEEN_BUYER_MASK & UNSEEN_SELLER_MASK, 0);
+    assert_eq!((UNSEEN_BUYER_MASK | UNSEEN_SELLER_MASK) & NOTIFY_ENABLED, 0);
+
+    // Default-off: handlers leave the byte alone
+    let mut off = [0u8; ESCROW_SIZE];
+    mark_unseen(&mut off, UNSEEN_BUYER_DELIVERY);
+    assert_eq!(off[OFF_NOTIFY], 0);
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_NOTIFY] = NOTIFY_ENABLED;
+    mark_unseen(&mut data, UNSEEN_BUYER_DELIVERY);
+    mark_unseen(&mut data, UNSEEN_SELLER_DISPUTE);
+    mark_unseen(&mut data, UNSEEN_BUYER_RULING | UNSEEN_SELLER_RULING);
+
+    // The seller can't clear the buyer's badge, even by asking for its bits
+    mark_seen(&mut data, UNSEEN_SELLER_MASK, &[UNSEEN_BUYER_DELIVERY | UNSEEN_SELLER_DISPUTE]);
+    assert_eq!(data[OFF_NOTIFY], NOTIFY_ENABLED | UNSEEN_BUYER_DELIVERY | UNSEEN_BUYER_RULING | UNSEEN_SELLER_RULING);
+
+    // The buyer clears one event, then the rest; the enabled bit survives
+    mark_seen(&mut data, UNSEEN_BUYER_MASK, &[UNSEEN_BUYER_DELIVERY]);
+    assert_eq!(data[OFF_NOTIFY] & UNSEEN_BUYER_MASK, UNSEEN_BUYER_RULING);
+    mark_seen(&mut data, UNSEEN_BUYER_MASK, &[]);
+    assert_eq!(data[OFF_NOTIFY], NOTIFY_ENABLED | UNSEEN_SELLER_RULING);
+}
+
+#[test]
+fn test_multisig_seller_threshold() {
+    let members = [[1u8; 32], [2u8; 32], [3u8; 32]];
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_SELLER_THRESHOLD] = 2;
+    data[OFF_SELLER_SIGNER_COUNT] = members.len() as u8;
+    for (i, m) in members.iter().enumerate() {
+        data[OFF_SELLER_SIGNERS + i * 32..OFF_SELLER_SIGNERS + (i + 1) * 32].copy_from_slice(m);
+    }
+
+    // Mirrors assert_seller_authority's count: (key, is_signer) per account
+    let approvals = |data: &[u8], accounts: &[([u8; 32], bool)]| {
+        let count = data[OFF_SELLER_SIGNER_COUNT] as usize;
+        (0..count)
+            .filter(|i| {
+                let off = OFF_SELLER_SIGNERS + i * 32;
+                accounts.iter().any(|(k, s)| k[..] == data[off..off + 32] && *s)
+            })
+            .count() as u8
+    };
+    let authorized = |accounts: &[([u8; 32], bool)]| approvals(&data, accounts) >= data[OFF_SELLER_THRESHOLD];
+
+    assert!(authorized(&[(members[0], true), (members[2], true)]));
+    assert!(!authorized(&[(members[0], true)]));
+    // A member listed twice still counts once
+    assert!(!authorized(&[(members[0], true), (members[0], true)]));
+    // Non-signing members and outsiders don't count
+    assert!(!authorized(&[(members[0], true), (members[1], false), ([9u8; 32], true)]));
+
+    // Creation rules: 1 <= threshold <= count <= 5, distinct non-zero members
+    let valid = |threshold: u8, keys: &[[u8; 32]]| {
+        let count = keys.len();
+        count <= 5
+            && ((count == 0 && threshold == 0) || (threshold >= 1 && threshold as usize <= count))
+            && keys.iter().enumerate().all(|(i, k)| k != &[0u8; 32] && !keys[i + 1..].contains(k))
+    };
+    assert!(valid(0, &[]));
+    assert!(valid(2, &members));
+    assert!(!valid(4, &members));
+    assert!(!valid(0, &members));
+    assert!(!valid(1, &[[1u8; 32], [1u8; 32]]));
+    assert!(!valid(1, &[[0u8; 32]]));
+    assert!(!valid(1, &[[1u8; 32]; 6]));
+}
+
+#[test]
+fn test_simulate_arbitrate_payout() {
+    // Mirrors ruling_payout, shared by Arbitrate and SimulateArbitrate. A
+    // decision byte rules 0 or 10_000 seller bps
+    fn ruling_payout(amount: u64, seller_bps: u16, arbitrator_fee: u64) -> (u64, u64, u64) {
+        let fee = arbitrator_fee.min(amount);
+        let seller = ((amount - fee) as u128 * seller_bps as u128 / 10_000) as u64;
+        (amount - fee - seller, seller, fee)
+    }
+
+    let amount: u64 = 2_500_000;
+    assert_eq!(ruling_payout(amount, 0, 0), (amount, 0, 0));
+    assert_eq!(ruling_payout(amount, 10_000, 0), (0, amount, 0));
+    // A fee larger than what is left (after a PartialRelease) takes it all
+    assert_eq!(ruling_payout(1_000, 10_000, 5_000), (0, 0, 1_000));
+    // On a split the fee comes off the top and the rest is divided
+    assert_eq!(ruling_payout(amount, 6_000, 500_000), (800_000, 1_200_000, 500_000));
+
+    // Return data: buyer(8) + seller(8) + arbitrator_fee(8) + bond(1) + deferred(1)
+    let (buyer, seller, fee) = ruling_payout(amount, 10_000, 100_000);
+    let mut ret = [0u8; 26];
+    ret[0..8].copy_from_slice(&buyer.to_le_bytes());
+    ret[8..16].copy_from_slice(&seller.to_le_bytes());
+    ret[16..24].copy_from_slice(&fee.to_le_bytes());
+    ret[25] = 1;
+    let read = |off: usize| u64::from_le_bytes(ret[off..off + 8].try_into().unwrap());
+    assert_eq!(read(0) + read(8) + read(16), amount);
+    assert_eq!(read(16), 100_000);
+    assert_eq!(ret[24], 0);
+}
+
+#[test]
+fn test_split_arbitration() {
+    // Mirrors parse_ruling: decision(1) or seller_bps(2), either optionally
+    // followed by rationale_hash(32)
+    fn parse_ruling(data: &[u8]) -> Result<u16, &'static str> {
+        match data.len() {
+            2 | 34 => {
+                let bps = u16::from_le_bytes([data[0], data[1]]);
+                if bps > 10_000 { Err("InvalidInstructionData") } else { Ok(bps) }
+            }
+            0 => Err("InvalidInstructionData"),
+            _ if data[0] == 0 => Ok(0),
+            _ => Ok(10_000),
+        }
+    }
+    assert_eq!(parse_ruling(&[0]), Ok(0));
+    assert_eq!(parse_ruling(&[1]), Ok(10_000));
+    // Any non-zero decision releases, as before
+    assert_eq!(parse_ruling(&[7]), Ok(10_000));
+    assert_eq!(parse_ruling(&[0u8; 33]), Ok(0));
+    assert_eq!(parse_ruling(&6_000u16.to_le_bytes()), Ok(6_000));
+    let mut with_rationale = [7u8; 34];
+    with_rationale[..2].copy_from_slice(&2_500u16.to_le_bytes());
+    assert_eq!(parse_ruling(&with_rationale), Ok(2_500));
+    assert_eq!(parse_ruling(&10_001u16.to_le_bytes()), Err("InvalidInstructionData"));
+    assert_eq!(parse_ruling(&[]), Err("InvalidInstructionData"));
+
+    // Mirrors Arbitrate's payout with no arbitrator fee. Lamports:
+    // [escrow, buyer, seller]; returns the settled status
+    let rent: u64 = 9_416_880; // minimum_balance(ESCROW_SIZE)
+    let amount: u64 = 1_000_003;
+    let arbitrate = |seller_bps: u16| {
+        let seller = (amount as u128 * seller_bps as u128 / 10_000) as u64;
+        let buyer = amount - seller;
+        let mut lamports = [rent + amount, 0, 0];
+        lamports[0] -= buyer + seller;
+        lamports[1] += buyer;
+        lamports[2] += seller;
+        let status = if seller_bps == 0 { STATUS_REFUNDED } else { STATUS_RELEASED };
+        (lamports, status)
+    };
+
+    // 0%: a plain refund
+    assert_eq!(arbitrate(0), ([rent, amount, 0], STATUS_REFUNDED));
+    // 100%: a plain release
+    assert_eq!(arbitrate(10_000), ([rent, 0, amount], STATUS_RELEASED));
+    // 60/40: the seller's share rounds down and the buyer takes the rest
+    let (lamports, status) = arbitrate(6_000);
+    assert_eq!(lamports, [rent, 400_002, 600_001]);
+    assert_eq!(lamports[1] + lamports[2], amount);
+    assert_eq!(status, STATUS_RELEASED);
+
+    // Return data grows by seller_bps(2) after the rationale hash
+    let mut ret = [0u8; 37];
+    ret[0] = 2; // DECISION_SPLIT
+    ret[35..37].copy_from_slice(&6_000u16.to_le_bytes());
+    assert_eq!(u16::from_le_bytes([ret[35], ret[36]]), 6_000);
+}
+
+#[test]
+fn test_sequential_partial_payouts_stay_in_sync() {
+    let rent: u64 = 8_200_000;
+    let covers = |lamports: u64, amount: u64| amount <= lamports.saturating_sub(rent);
+
+    // Mirrors assert_partial_payout after each transfer
+    let check = |before: u64, after: u64, paid: u64, remaining: u64| {
+        before.checked_sub(after) == Some(paid) && covers(after, remaining)
+    };
+
+    let mut amount: u64 = 1_000_000;
+    let mut lamports = amount + rent;
+    for paid in [100_000u64, 250_000, 1, 649_999] {
+        let before = lamports;
+        lamports -= paid;
+        amount -= paid;
+        assert!(check(before, lamports, paid, amount));
+        assert_eq!(lamports - rent, amount);
+    }
+    assert_eq!(amount, 0);
+    assert_eq!(lamports, rent);
+
+    // A transfer that moved the wrong amount is caught either way
+    assert!(!check(2_000_000, 1_900_001, 100_000, 0));
+    assert!(!check(2_000_000, 1_899_999, 100_000, 0));
+    // So is a stored amount the remaining balance can't back
+    assert!(!check(rent + 600_000, rent + 500_000, 100_000, 500_001));
+}
+
+#[test]
+fn test_strict_acceptance() {
+    // Mirrors ReleaseV2's gate, then AcceptDelivery's
+    let can_release = |data: &[u8]| {
+        let status = data[OFF_STATUS];
+        data[OFF_STRICT_ACCEPTANCE] == 0
+            && !matches!(status, STATUS_DISPUTED | STATUS_APPEALED | STATUS_RELEASED | STATUS_REFUNDED)
+    };
+    let can_accept = |data: &[u8]| data[OFF_STATUS] == STATUS_DELIVERED;
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    assert!(can_release(&data));
+
+    data[OFF_STRICT_ACCEPTANCE] = 1;
+    assert!(!can_release(&data));
+    assert!(!can_accept(&data));
+
+    // Only delivery followed by acceptance pays the seller
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    data[OFF_FLAGS] |= FLAG_SELLER_DELIVERED;
+    assert!(!can_release(&data));
+    assert!(can_accept(&data));
+}
+
+#[test]
+fn test_rent_paid_reserve() {
+    // Mirrors rent_reserve: the recorded rent wins over today's schedule
+    fn rent_reserve(data: &[u8], current_minimum: u64) -> u64 {
+        let rent_paid = u64::from_le_bytes(data[OFF_RENT_PAID..OFF_RENT_PAID + 8].try_into().unwrap());
+        if rent_paid != 0 { rent_paid } else { current_minimum }
+    }
+
+    let rent_at_creation: u64 = 8_254_560;
+    let amount: u64 = 1_000_000;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_RENT_PAID..OFF_RENT_PAID + 8].copy_from_slice(&rent_at_creation.to_le_bytes());
+    let lamports = rent_at_creation + amount;
+
+    // The schedule later doubles: the full amount is still payable
+    let raised = rent_at_creation * 2;
+    let spendable = lamports.saturating_sub(rent_reserve(&data, raised));
+    assert_eq!(spendable, amount);
+
+    // Recomputing against the new schedule would have stranded the payout
+    assert_eq!(lamports.saturating_sub(raised), 0);
+
+    // Nothing recorded: today's minimum
+    let legacy = [0u8; ESCROW_SIZE];
+    assert_eq!(rent_reserve(&legacy, raised), raised);
+}
+
+#[test]
+fn test_reassign_seller_and_terms() {
+    let old_seller = [2u8; 32];
+    let new_seller = [6u8; 32];
+    let new_terms = [0xABu8; 32];
+    let now: u64 = 1_700_000_500;
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&old_seller);
+    data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&[0x11; 32]);
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    data[OFF_MILESTONE_DELIVERED] = 0b001;
+    data[OFF_MILESTONE_SETTLED] = 0b001;
+
+    // Mirrors Reassign's gate: the old seller always consents, and only an
+    // open escrow with nothing delivered but unpaid can change hands
+    let reassign = |data: &[u8], old_seller_signed: bool| {
+        if !old_seller_signed {
+            return Err("MissingRequiredSignature");
+        }
+        if data[OFF_FLAGS] & FLAG_FROZEN != 0 {
+            return Err("EscrowFrozen");
+        }
+        let status = data[OFF_STATUS];
+        if status == STATUS_DELIVERED
+            || data[OFF_FLAGS] & FLAG_SELLER_DELIVERED != 0
+            || data[OFF_MILESTONE_DELIVERED] & !data[OFF_MILESTONE_SETTLED] != 0
+        {
+            return Err("AlreadyDelivered");
+        }
+        if status != STATUS_ACTIVE {
+            return Err("InvalidAccountData");
+        }
+        Ok(())
+    };
+    // The paid milestone doesn't hold the escrow back
+    assert_eq!(reassign(&data, true), Ok(()));
+    assert_eq!(reassign(&data, false), Err("MissingRequiredSignature"));
+
+    let mut delivered = data;
+    delivered[OFF_STATUS] = STATUS_DELIVERED;
+    delivered[OFF_FLAGS] = FLAG_SELLER_DELIVERED;
+    assert_eq!(reassign(&delivered, true), Err("AlreadyDelivered"));
+    // Either delivery record is enough on its own
+    delivered[OFF_STATUS] = STATUS_ACTIVE;
+    assert_eq!(reassign(&delivered, true), Err("AlreadyDelivered"));
+    let mut milestone = data;
+    milestone[OFF_MILESTONE_DELIVERED] = 0b011;
+    assert_eq!(reassign(&milestone, true), Err("AlreadyDelivered"));
+
+    for status in [STATUS_DISPUTED, STATUS_APPEALED, STATUS_ACCEPTED, STATUS_RELEASED, STATUS_REFUNDED] {
+        let mut closed = data;
+        closed[OFF_STATUS] = status;
+        assert_eq!(reassign(&closed, true), Err("InvalidAccountData"));
+    }
+
+    // Seller and terms change together; the PDA's seller seed is kept, so
+    // the escrow stays at the address derived from the old seller
+    data[OFF_ORIGINAL_SELLER..OFF_ORIGINAL_SELLER + 32].copy_from_slice(&old_seller);
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&new_seller);
+    data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&new_terms);
+    data[OFF_UPDATED_AT..OFF_UPDATED_AT + 8].copy_from_slice(&now.to_le_bytes());
+
+    assert_eq!(&data[OFF_SELLER..OFF_SELLER + 32], &new_seller);
+    assert_eq!(&data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32], &new_terms);
+    assert_eq!(&data[OFF_ORIGINAL_SELLER..OFF_ORIGINAL_SELLER + 32], &old_seller);
+    assert_eq!(u64::from_le_bytes(data[OFF_UPDATED_AT..OFF_UPDATED_AT + 8].try_into().unwrap()), now);
+
+    // Mirrors ReleaseV2's seller check: only the stored seller is paid
+    let release_to = |data: &[u8], seller: [u8; 32]| data[OFF_SELLER..OFF_SELLER + 32] == seller;
+    assert!(release_to(&data, new_seller));
+    assert!(!release_to(&data, old_seller));
+}
+
+#[test]
+fn test_arbitrator_self_payout_rejected() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let arbitrator = [3u8; 32];
+
+    // Mirrors Arbitrate's payout re-check: Err(true) = ArbitratorSelfPayout
+    let check = |passed: [u8; 32], stored: [u8; 32], arbitrator: [u8; 32]| {
+        if passed != stored {
+            return Err(false);
+        }
+        if passed == arbitrator {
+            return Err(true);
+        }
+        Ok(())
+    };
+
+    assert_eq!(check(buyer, buyer, arbitrator), Ok(()));
+    assert_eq!(check(seller, seller, arbitrator), Ok(()));
+
+    // Arbitrator in the buyer slot of a correctly configured escrow: key mismatch
+    assert_eq!(check(arbitrator, buyer, arbitrator), Err(false));
+
+    // A misconfigured escrow whose stored seller is the arbitrator: rejected
+    // even though the passed account matches the stored key
+    assert_eq!(check(arbitrator, arbitrator, arbitrator), Err(true));
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_escrow_status_enum_round_trip() {
+    use pact_escrow::status::EscrowStatus;
+
+    let statuses = [STATUS_ACTIVE, STATUS_DELIVERED, STATUS_ACCEPTED, STATUS_DISPUTED,
+                    STATUS_RELEASED, STATUS_REFUNDED, STATUS_APPEALED];
+    for byte in statuses {
+        let status = EscrowStatus::try_from(byte).unwrap();
+        assert_eq!(status as u8, byte);
+        assert_eq!(status.as_u8(), byte);
+        assert_eq!(EscrowStatus::from_u8(byte), Ok(status));
+        assert!(!status.to_string().is_empty());
+    }
+
+    for byte in 7..=u8::MAX {
+        assert!(EscrowStatus::try_from(byte).is_err());
+        assert_eq!(EscrowStatus::from_u8(byte), Err(pinocchio::program_error::ProgramError::InvalidAccountData));
+    }
+
+    assert_eq!(EscrowStatus::try_from(STATUS_DISPUTED).unwrap().to_string(), "Disputed");
+    assert!(EscrowStatus::try_from(STATUS_REFUNDED).unwrap().is_terminal());
+    assert!(!EscrowStatus::try_from(STATUS_APPEALED).unwrap().is_terminal());
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_can_transition_matrix() {
+    use pact_escrow::status::{can_transition, EscrowStatus};
+
+    // Rows: from, columns: to, both in byte order
+    // (Active, Delivered, Accepted, Disputed, Released, Refunded, Appealed)
+    const ALLOWED: [[bool; 7]; 7] = [
+        [false, true, false, true, true, true, false],     // Active
+        [true, false, true, true, true, true, false],      // Delivered
+        [false, false, false, false, false, false, false], // Accepted
+        [false, false, false, false, true, true, true],    // Disputed
+        [false, false, false, false, false, false, false], // Released
+        [false, false, false, false, false, false, false], // Refunded
+        [false, false, false, false, true, true, false],   // Appealed
+    ];
+
+    for from in 0..7u8 {
+        for to in 0..7u8 {
+            let allowed = can_transition(
+                EscrowStatus::from_u8(from).unwrap(),
+                EscrowStatus::from_u8(to).unwrap(),
+            );
+            assert_eq!(allowed, ALLOWED[from as usize][to as usize], "{} -> {}", from, to);
+        }
+    }
+
+    // Terminal states never reopen
+    for to in 0..7u8 {
+        let to = EscrowStatus::from_u8(to).unwrap();
+        assert!(!can_transition(EscrowStatus::Accepted, to));
+        assert!(!can_transition(EscrowStatus::Released, to));
+        assert!(!can_transition(EscrowStatus::Refunded, to));
+    }
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_accepted_is_terminal() {
+    use pact_escrow::status::{can_transition, EscrowStatus};
+
+    // AcceptDelivery settles into Accepted; every other payout to the
+    // seller settles into Released
+    assert!(can_transition(EscrowStatus::Delivered, EscrowStatus::Accepted));
+    assert!(!can_transition(EscrowStatus::Active, EscrowStatus::Accepted));
+    assert!(!can_transition(EscrowStatus::Disputed, EscrowStatus::Accepted));
+    assert!(EscrowStatus::Accepted.is_terminal());
+
+    // An accepted escrow is already paid: no release or refund on top
+    assert!(!can_transition(EscrowStatus::Accepted, EscrowStatus::Released));
+    assert!(!can_transition(EscrowStatus::Accepted, EscrowStatus::Refunded));
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_escrow_view_round_trip() {
+    use pact_escrow::status::EscrowStatus;
+    use pact_escrow::view::{EscrowView, ESCROW_DISCRIMINATOR, ESCROW_LEN};
+    use pinocchio::program_error::ProgramError;
+
+    assert_eq!(ESCROW_LEN, ESCROW_SIZE);
+    assert_eq!(ESCROW_DISCRIMINATOR, ESCROW_DISC);
+
+    // Laid out as in test_escrow_v2_layout, with every field distinct
+    let created_at: u64 = 1707544800;
+    let timeout: u64 = 259200;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+    data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&[1u8; 32]);
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&[2u8; 32]);
+    data[OFF_ARBITRATOR..OFF_ARBITRATOR + 32].copy_from_slice(&[3u8; 32]);
+    data[OFF_MINT..OFF_MINT + 32].copy_from_slice(&[4u8; 32]);
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&1_000_000_000u64.to_le_bytes());
+    data[OFF_CREATED_AT..OFF_CREATED_AT + 8].copy_from_slice(&created_at.to_le_bytes());
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&timeout.to_le_bytes());
+    data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&[0xABu8; 32]);
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    data[OFF_FLAGS] = FLAG_SELLER_DELIVERED;
+    data[OFF_BUMP] = 254;
+
+    let view = EscrowView::from_bytes(&data).unwrap();
+    assert_eq!(
+        view,
+        EscrowView {
+            buyer: [1u8; 32],
+            seller: [2u8; 32],
+            arbitrator: [3u8; 32],
+            mint: [4u8; 32],
+            amount: 1_000_000_000,
+            created_at,
+            timeout_seconds: timeout,
+            terms_hash: [0xABu8; 32],
+            status: EscrowStatus::Delivered,
+            flags: FLAG_SELLER_DELIVERED,
+            bump: 254,
+        }
+    );
+    assert!(!view.is_terminal());
+
+    // Relative timeout, counted from created_at
+    assert_eq!(view.time_remaining(created_at), Some(timeout));
+    assert_eq!(view.time_remaining(created_at + timeout - 1), Some(1));
+    assert_eq!(view.time_remaining(created_at + timeout), Some(0));
+    assert_eq!(view.time_remaining(u64::MAX), Some(0));
+
+    // Absolute deadline
+    let deadline = created_at + 100;
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&deadline.to_le_bytes());
+    data[OFF_FLAGS] |= FLAG_ABSOLUTE_DEADLINE;
+    assert_eq!(EscrowView::from_bytes(&data).unwrap().time_remaining(created_at + 40), Some(60));
+
+    // No timeout
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(EscrowView::from_bytes(&data).unwrap().time_remaining(created_at), None);
+
+    for status in [STATUS_ACCEPTED, STATUS_RELEASED, STATUS_REFUNDED] {
+        data[OFF_STATUS] = status;
+        assert!(EscrowView::from_bytes(&data).unwrap().is_terminal());
+    }
+
+    // Rejected: unknown status, foreign discriminator, wrong size
+    let mut bad = data;
+    bad[OFF_STATUS] = 7;
+    assert_eq!(EscrowView::from_bytes(&bad), Err(ProgramError::InvalidAccountData));
+    let mut bad = data;
+    bad[OFF_DISC] ^= 1;
+    assert_eq!(EscrowView::from_bytes(&bad), Err(ProgramError::InvalidAccountData));
+    assert_eq!(EscrowView::from_bytes(&data[..ESCROW_SIZE - 1]), Err(ProgramError::InvalidAccountData));
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn test_ix_builders_match_handlers() {
+    use pact_escrow::ix::{self, AccountMeta};
+
+    let program_id = [9u8; 32];
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let arbitrator = [3u8; 32];
+    let escrow = [4u8; 32];
+    let system = [0u8; 32];
+    let terms_hash = [0xABu8; 32];
+
+    // (builder output, discriminator, payload length the handler parses)
+    let cases = [
+        (ix::create_escrow_v2(&program_id, &buyer, &seller, &arbitrator, &escrow, &system, 1, 2, 3, &terms_hash), 0, 56),
+        (ix::mark_delivered(&program_id, &seller, &escrow, None), 1, 0),
+        (ix::mark_delivered(&program_id, &seller, &escrow, Some(&[5; 32])), 1, 32),
+        (ix::accept_delivery(&program_id, &buyer, &seller, &escrow, b"abc"), 2, 3),
+        (ix::release_v2(&program_id, &buyer, &seller, &escrow, &[]), 3, 0),
+        (ix::refund_v2(&program_id, &buyer, &buyer, &seller, &escrow), 4, 0),
+        (ix::dispute(&program_id, &buyer, &escrow), 5, 0),
+        (ix::arbitrate(&program_id, &arbitrator, &buyer, &seller, &escrow, 1, None), 6, 1),
+        (ix::arbitrate(&program_id, &arbitrator, &buyer, &seller, &escrow, 1, Some(&[6; 32])), 6, 33),
+        (ix::arbitrate_split(&program_id, &arbitrator, &buyer, &seller, &escrow, 5_000, None), 6, 2),
+        (ix::arbitrate_split(&program_id, &arbitrator, &buyer, &seller, &escrow, 5_000, Some(&[6; 32])), 6, 34),
+        (ix::appeal(&program_id, &seller, &escrow), 7, 0),
+        (ix::arbitrate_final(&program_id, &arbitrator, &buyer, &seller, &escrow, 0), 8, 1),
+        (ix::execute_ruling(&program_id, &buyer, &seller, &escrow), 9, 0),
+        (ix::lookup_escrow(&program_id, &escrow, &buyer, &seller, 7), 10, 72),
+        (ix::freeze_on_timeout(&program_id, &escrow), 11, 0),
+        (ix::resize_escrow(&program_id, &buyer, &escrow, &system), 12, 0),
+        (ix::add_funds(&program_id, &buyer, &escrow, &system, 10), 13, 8),
+        (ix::release_if_price(&program_id, &seller, &escrow, &[8; 32]), 14, 0),
+        (ix::propose_settlement(&program_id, &arbitrator, &escrow, 2_500), 15, 2),
+        (ix::ratify_settlement(&program_id, &buyer, &seller, &escrow), 16, 0),
+        (ix::dry_run_create(&program_id, &buyer, &seller, &arbitrator, &escrow, &system, 1, 2, 3, &terms_hash), 17, 56),
+        (ix::close_escrow(&program_id, &buyer, &escrow), 20, 0),
+        (ix::split_escrow(&program_id, &buyer, &escrow, &seller, &arbitrator, &[5; 32], &system, 1, 2, 3, &terms_hash), 21, 56),
+        (ix::mark_milestone_delivered(&program_id, &seller, &escrow, 0), 22, 1),
+        (ix::accept_milestone(&program_id, &buyer, &seller, &escrow, 0), 23, 1),
+        (ix::inspect_escrow(&program_id, &escrow), 24, 0),
+        (ix::auto_release(&program_id, &seller, &escrow), 25, 0),
+        (ix::renew_escrow(&program_id, &seller, &escrow), 26, 0),
+        (ix::partial_accept_keep_open(&program_id, &buyer, &seller, &escrow, 1), 28, 8),
+        (ix::cancel_escrow(&program_id, &buyer, &seller, &escrow), 29, 0),
+        (ix::refund_and_close(&program_id, &buyer, &buyer, &seller, &escrow), 30, 0),
+        (ix::release_and_close(&program_id, &buyer, &seller, &escrow), 31, 0),
+        (ix::mark_seen(&program_id, &buyer, &escrow, 1), 32, 1),
+        (ix::simulate_arbitrate(&program_id, &arbitrator, &escrow, 1), 33, 1),
+        (ix::reassign(&program_id, &buyer, &seller, &[5; 32], &escrow, &terms_hash), 34, 32),
+        (ix::export_state(&program_id, &escrow, 0), 35, 2),
+        (ix::batch_accept(&program_id, &buyer, &[(seller, escrow)]), 36, 0),
+        (ix::partial_release(&program_id, &buyer, &seller, &escrow, 1), 37, 8),
+        (ix::release_milestone(&program_id, &buyer, &seller, &escrow, 0), 38, 1),
+        (ix::extend_timeout(&program_id, &buyer, &seller, &escrow, 86_400), 39, 8),
+        (ix::migrate_v1_to_v2(&program_id, &buyer, &escrow, &system, 7), 40, 8),
+        (ix::create_escrow_batch(&program_id, &buyer, &system, &[]), 41, 1),
+        (ix::top_up(&program_id, &buyer, &escrow, &system, 10), 42, 8),
+    ];
+    for (i, (instruction, discriminator, payload_len)) in cases.iter().enumerate() {
+        assert_eq!(instruction.program_id, program_id, "case {i}");
+        assert_eq!(instruction.data[0], *discriminator, "case {i}");
+        assert_eq!(instruction.data.len(), 1 + payload_len, "case {i}");
+    }
+
+    // Feature-gated instructions build only alongside their handlers
+    #[cfg(feature = "seller-allowlist")]
+    {
+        let init = ix::init_seller_allowlist(&program_id, &buyer, &escrow, &system);
+        assert_eq!(init.data, vec![18]);
+        let set = ix::set_seller_allowed(&program_id, &buyer, &escrow, &seller, true);
+        assert_eq!(set.data[0], 19);
+        assert_eq!(&set.data[1..33], &seller);
+        assert_eq!(set.data[33], 1);
+    }
+    #[cfg(feature = "seller-reputation")]
+    {
+        let init = ix::init_seller_reputation(&program_id, &buyer, &seller, &escrow, &system);
+        assert_eq!(init.data, vec![27]);
+    }
+
+    // Create: same bytes as test_create_escrow_instruction_data
+    let create = ix::create_escrow_v2(
+        &program_id, &buyer, &seller, &arbitrator, &escrow, &system, 100_000_000, 1234567890, 259200, &terms_hash,
+    );
+    assert_eq!(&create.data[1..9], &100_000_000u64.to_le_bytes());
+    assert_eq!(&create.data[9..17], &1234567890u64.to_le_bytes());
+    assert_eq!(&create.data[17..25], &259200u64.to_le_bytes());
+    assert_eq!(&create.data[25..57], &terms_hash);
+    assert_eq!(
+        create.accounts,
+        vec![
+            AccountMeta::writable_signer(&buyer),
+            AccountMeta::readonly(&seller),
+            AccountMeta::readonly(&arbitrator),
+            AccountMeta::writable(&escrow),
+            AccountMeta::readonly(&system),
+        ]
+    );
+
+    // Arbitrate: arbitrator, buyer, seller, escrow; the arbitrator collects
+    // its fee so it's writable, and parse_ruling reads 2 bytes as bps
+    let split = ix::arbitrate_split(&program_id, &arbitrator, &buyer, &seller, &escrow, 5_000, None);
+    assert_eq!(
+        split.accounts,
+        vec![
+            AccountMeta::writable_signer(&arbitrator),
+            AccountMeta::writable(&buyer),
+            AccountMeta::writable(&seller),
+            AccountMeta::writable(&escrow),
+        ]
+    );
+    assert_eq!(&split.data[1..], &5_000u16.to_le_bytes());
+
+    // Read-only instructions never ask for a signature or a write lock
+    for read in [
+        ix::lookup_escrow(&program_id, &escrow, &buyer, &seller, 7),
+        ix::inspect_escrow(&program_id, &escrow),
+        ix::export_state(&program_id, &escrow, 0),
+        ix::simulate_arbitrate(&program_id, &arbitrator, &escrow, 1),
+    ] {
+        assert!(read.accounts.iter().all(|a| !a.is_signer && !a.is_writable));
+    }
+
+    // BatchAccept repeats the buyer per escrow
+    let batch = ix::batch_accept(&program_id, &buyer, &[(seller, escrow), ([5; 32], [6; 32])]);
+    assert_eq!(batch.accounts.len(), 6);
+    assert_eq!(batch.accounts[3], AccountMeta::writable_signer(&buyer));
+    assert_eq!(batch.accounts[5], AccountMeta::writable(&[6; 32]));
+
+    // CreateEscrowBatch: buyer and system program once, then seller and
+    // escrow per record; each record is the create payload behind its seller
+    let record = ix::BatchCreate { seller, escrow, amount: 1, seed: 2, timeout_seconds: 3, terms_hash };
+    let two = ix::create_escrow_batch(&program_id, &buyer, &system, &[record, record]);
+    assert_eq!(two.accounts.len(), 6);
+    assert_eq!(two.accounts[4], AccountMeta::readonly(&seller));
+    assert_eq!(two.accounts[5], AccountMeta::writable(&escrow));
+    assert_eq!(two.data[1], 2);
+    assert_eq!(two.data.len(), 2 + 2 * 88);
+    let single = ix::create_escrow_v2(&program_id, &buyer, &seller, &system, &escrow, &system, 1, 2, 3, &terms_hash);
+    assert_eq!(&two.data[2..34], &seller);
+    assert_eq!(&two.data[34..90], &single.data[1..]);
+}
+
+#[test]
+fn test_late_delivery_penalty() {
+    let deadline: u64 = 1_700_000_000;
+    let amount: u64 = 1_000_003;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_DELIVERY_DEADLINE..OFF_DELIVERY_DEADLINE + 8].copy_from_slice(&deadline.to_le_bytes());
+    data[OFF_LATE_PENALTY_BPS..OFF_LATE_PENALTY_BPS + 2].copy_from_slice(&1_500u16.to_le_bytes());
+
+    // Mirrors AcceptDelivery: (to buyer, to seller)
+    let settle = |data: &[u8], delivered_at: u64| {
+        let deadline = u64::from_le_bytes(data[OFF_DELIVERY_DEADLINE..OFF_DELIVERY_DEADLINE + 8].try_into().unwrap());
+        let bps = u16::from_le_bytes([data[OFF_LATE_PENALTY_BPS], data[OFF_LATE_PENALTY_BPS + 1]]);
+        let penalty = if deadline != 0 && delivered_at > deadline { apply_bps(amount, bps) } else { 0 };
+        (penalty, amount - penalty)
+    };
+
+    // On time, including exactly at the deadline: no penalty
+    assert_eq!(settle(&data, deadline - 1), (0, amount));
+    assert_eq!(settle(&data, deadline), (0, amount));
+
+    // Late: 15% to the buyer, rounded down, the rest to the seller
+    let (to_buyer, to_seller) = settle(&data, deadline + 1);
+    assert_eq!(to_buyer, 150_000);
+    assert_eq!(to_buyer + to_seller, amount);
+
+    // No deadline: never penalized
+    let no_sla = [0u8; ESCROW_SIZE];
+    assert_eq!(settle(&no_sla, u64::MAX), (0, amount));
+
+    // Creation rules: bps <= 10_000, and a penalty needs a deadline
+    let valid = |deadline: u64, bps: u16| bps <= 10_000 && !(bps > 0 && deadline == 0);
+    assert!(valid(deadline, 10_000));
+    assert!(!valid(deadline, 10_001));
+    assert!(!valid(0, 1));
+    assert!(valid(0, 0));
+}
+
+/// Mirrors the `IX_*` table in lib.rs, including the feature-gated entries.
+/// Most handlers need the runtime, so this pins the mapping instead: one
+/// handler per discriminator, no gaps, no reused values.
+#[test]
+fn test_v2_router_discriminators() {
+    const ROUTES: [(u8, &str); 43] = [
+        (0, "CreateEscrowV2"),
+        (1, "MarkDelivered"),
+        (2, "AcceptDelivery"),
+        (3, "ReleaseV2"),
+        (4, "RefundV2"),
+        (5, "Dispute"),
+        (6, "Arbitrate"),
+        (7, "Appeal"),
+        (8, "ArbitrateFinal"),
+        (9, "ExecuteRuling"),
+        (10, "LookupEscrow"),
+        (11, "FreezeOnTimeout"),
+        (12, "ResizeEscrow"),
+        (13, "AddFunds"),
+        (14, "ReleaseIfPrice"),
+        (15, "ProposeSettlement"),
+        (16, "RatifySettlement"),
+        (17, "DryRunCreate"),
+        (18, "InitSellerAllowlist"),
+        (19, "SetSellerAllowed"),
+        (20, "CloseEscrow"),
+        (21, "SplitEscrow"),
+        (22, "MarkMilestoneDelivered"),
+        (23, "AcceptMilestone"),
+        (24, "InspectEscrow"),
+        (25, "AutoRelease"),
+        (26, "RenewEscrow"),
+        (27, "InitSellerReputation"),
+        (28, "PartialAcceptKeepOpen"),
+        (29, "CancelEscrow"),
+        (30, "RefundAndClose"),
+        (31, "ReleaseAndClose"),
+        (32, "MarkSeen"),
+        (33, "SimulateArbitrate"),
+        (34, "Reassign"),
+        (35, "ExportState"),
+        (36, "BatchAccept"),
+        (37, "PartialRelease"),
+        (38, "ReleaseMilestone"),
+        (39, "ExtendTimeout"),
+        (40, "MigrateV1ToV2"),
+        (41, "CreateEscrowBatch"),
+        (42, "TopUp"),
+    ];
+
+    // Mirrors process_instruction's match: empty data has its own error,
+    // anything past the table is InvalidInstructionData
+    let route = |data: &[u8]| -> Result<&str, &str> {
+        let (discriminator, _) = data.split_first().ok_or("MissingDiscriminator")?;
+        ROUTES
+            .iter()
+            .find(|(ix, _)| ix == discriminator)
+            .map(|(_, handler)| *handler)
+            .ok_or("InvalidInstructionData")
+    };
+
+    for (i, (ix, handler)) in ROUTES.iter().enumerate() {
+        assert_eq!(*ix as usize, i);
+        assert_eq!(route(&[*ix]), Ok(*handler));
+        for (_, other) in &ROUTES[i + 1..] {
+            assert_ne!(handler, other);
+        }
+    }
+
+    assert_eq!(route(&[ROUTES.len() as u8]), Err("InvalidInstructionData"));
+    assert_eq!(route(&[u8::MAX]), Err("InvalidInstructionData"));
+    assert_eq!(route(&[]), Err("MissingDiscriminator"));
+}
+
+#[test]
+fn test_disallow_partial() {
+    const POLICY_DISALLOW_PARTIAL: u8 = 1 << 0;
+
+    // Mirrors PartialAcceptKeepOpen's policy gate
+    let can_accept_part = |data: &[u8]| {
+        data[OFF_STATUS] == STATUS_DELIVERED && data[OFF_POLICY] & POLICY_DISALLOW_PARTIAL == 0
+    };
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    assert!(can_accept_part(&data)); // default: partials allowed
+
+    data[OFF_POLICY] |= POLICY_DISALLOW_PARTIAL;
+    assert!(!can_accept_part(&data));
+
+    // Creation rule: milestones and periods are partial by design
+    let valid = |disallow: bool, milestones: u8, periods: u16| {
+        !(disallow && (milestones > 0 || periods > 0))
+    };
+    assert!(valid(true, 0, 0));
+    assert!(!valid(true, 2, 0));
+    assert!(!valid(true, 0, 12));
+    assert!(valid(false, 2, 0));
+}
+
+#[test]
+fn test_already_settled_race() {
+    #[derive(Debug, PartialEq)]
+    enum Outcome { AlreadySettled, InvalidAccountData, Ok }
+
+    // Mirrors assert_not_settled ahead of each resolution path's own gate
+    let resolve = |data: &[u8], allowed: &[u8]| {
+        let status = data[OFF_STATUS];
+        if status == STATUS_RELEASED || status == STATUS_REFUNDED {
+            Outcome::AlreadySettled
+        } else if !allowed.contains(&status) {
+            Outcome::InvalidAccountData
+        } else {
+            Outcome::Ok
+        }
+    };
+    let arbitrate = [STATUS_DISPUTED];
+    let cancel = [STATUS_ACTIVE];
+
+    // The cancel lands first: the ruling loses with a clear error
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_REFUNDED;
+    assert_eq!(resolve(&data, &arbitrate), Outcome::AlreadySettled);
+
+    // A ratified settlement paid the seller first: a late cancel says so too
+    data[OFF_STATUS] = STATUS_RELEASED;
+    assert_eq!(resolve(&data, &cancel), Outcome::AlreadySettled);
+
+    // Live but in the wrong state is still a plain state error
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    assert_eq!(resolve(&data, &arbitrate), Outcome::InvalidAccountData);
+    assert_eq!(resolve(&data, &cancel), Outcome::Ok);
+}
+
+#[test]
+fn test_processing_guard() {
+    // Lives in the policy byte: the flags byte has no free bit
+    const POLICY_DISALLOW_PARTIAL: u8 = 1 << 0;
+    const POLICY_PROCESSING: u8 = 1 << 7;
+
+    // Mirrors assert_not_processing, which every handler runs right after
+    // its size check
+    let enter = |data: &[u8]| -> Result<(), &str> {
+        if data[OFF_POLICY] & POLICY_PROCESSING != 0 {
+            return Err("InvalidAccountData");
+        }
+        Ok(())
+    };
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    data[OFF_POLICY] = POLICY_DISALLOW_PARTIAL;
+    assert_eq!(enter(&data), Ok(()));
+
+    // Mirrors while_processing around a payout whose CPI re-enters Pact on
+    // the same escrow: the nested call bails before reading any state
+    let mut nested = None;
+    let mut payout = |data: &mut [u8]| {
+        data[OFF_POLICY] |= POLICY_PROCESSING;
+        nested = Some(enter(data));
+        data[OFF_POLICY] &= !POLICY_PROCESSING;
+    };
+    payout(&mut data);
+    assert_eq!(nested, Some(Err("InvalidAccountData")));
+
+    // Cleared afterwards, and the real policy bits are untouched
+    assert_eq!(enter(&data), Ok(()));
+    assert_eq!(data[OFF_POLICY], POLICY_DISALLOW_PARTIAL);
+}
+
+#[test]
+fn test_rent_recipient_policy() {
+    const RENT_TO_BUYER: u8 = 0;
+    const RENT_TO_SELLER: u8 = 1;
+    const RENT_TO_TREASURY: u8 = 2;
+
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let treasury = [9u8; 32];
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&seller);
+
+    // Mirrors close_to_rent_recipient: resolve the key, then find it among
+    // the instruction's accounts
+    let recipient = |data: &[u8], accounts: &[[u8; 32]]| -> Option<[u8; 32]> {
+        let offset = match data[OFF_RENT_RECIPIENT] {
+            RENT_TO_SELLER => OFF_SELLER,
+            RENT_TO_TREASURY => OFF_RENT_TREASURY,
+            _ => OFF_BUYER,
+        };
+        let key: [u8; 32] = data[offset..offset + 32].try_into().unwrap();
+        accounts.iter().find(|a| **a == key).copied()
+    };
+
+    // Default: the buyer who paid the rent
+    assert_eq!(recipient(&data, &[buyer, seller]), Some(buyer));
+
+    data[OFF_RENT_RECIPIENT] = RENT_TO_SELLER;
+    assert_eq!(recipient(&data, &[buyer, seller]), Some(seller));
+
+    data[OFF_RENT_RECIPIENT] = RENT_TO_TREASURY;
+    data[OFF_RENT_TREASURY..OFF_RENT_TREASURY + 32].copy_from_slice(&treasury);
+    assert_eq!(recipient(&data, &[buyer, seller]), None); // treasury not passed
+    assert_eq!(recipient(&data, &[buyer, seller, treasury]), Some(treasury));
+
+    // Creation rules: a known policy, and a real treasury key for mode 2
+    let valid = |mode: u8, treasury: [u8; 32]| {
+        mode <= RENT_TO_TREASURY && !(mode == RENT_TO_TREASURY && treasury == [0u8; 32])
+    };
+    assert!(valid(RENT_TO_BUYER, [0u8; 32]));
+    assert!(valid(RENT_TO_TREASURY, treasury));
+    assert!(!valid(RENT_TO_TREASURY, [0u8; 32]));
+    assert!(!valid(3, treasury));
+}
+
+#[test]
+fn test_export_state_chunks() {
+    const MAX_RETURN_DATA: usize = 1024;
+    const EXPORT_SCHEMA_VERSION: u8 = 5;
+    const EXPORT_HEADER_SIZE: usize = 5;
+    const EXPORT_CHUNK_SIZE: usize = MAX_RETURN_DATA - EXPORT_HEADER_SIZE;
+
+    // The account doesn't fit in one return
+    const { assert!(ESCROW_SIZE > EXPORT_CHUNK_SIZE) };
+
+    let mut account = [0u8; ESCROW_SIZE];
+    for (i, byte) in account.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    // Mirrors ExportState
+    let export = |offset: usize| -> Vec<u8> {
+        let end = (offset + EXPORT_CHUNK_SIZE).min(ESCROW_SIZE);
+        let mut ret = vec![EXPORT_SCHEMA_VERSION];
+        ret.extend_from_slice(&(ESCROW_SIZE as u16).to_le_bytes());
+        ret.extend_from_slice(&(offset as u16).to_le_bytes());
+        ret.extend_from_slice(&account[offset..end]);
+        ret
+    };
+
+    // Mirrors the client loop over ExportChunk::parse / next_offset
+    let mut restored = vec![0u8; ESCROW_SIZE];
+    let mut offset = 0;
+    let mut calls = 0;
+    loop {
+        let ret = export(offset);
+        assert!(ret.len() <= MAX_RETURN_DATA);
+        assert_eq!(ret[0], EXPORT_SCHEMA_VERSION);
+        let total_len = u16::from_le_bytes([ret[1], ret[2]]) as usize;
+        let at = u16::from_le_bytes([ret[3], ret[4]]) as usize;
+        let bytes = &ret[EXPORT_HEADER_SIZE..];
+        restored[at..at + bytes.len()].copy_from_slice(bytes);
+        calls += 1;
+        if at + bytes.len() >= total_len {
+            break;
+        }
+        offset = at + bytes.len();
+    }
+
+    assert_eq!(calls, 2);
+    assert_eq!(restored, account.to_vec());
+}
+
+#[test]
+fn test_batch_accept_eligibility() {
+    #[derive(Debug, PartialEq)]
+    enum Group { Accept, Skip, Malformed }
+
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+
+    // Mirrors batch_acceptable
+    let classify = |data: &[u8], group_buyer: [u8; 32], group_seller: [u8; 32]| {
+        if data[OFF_BUYER..OFF_BUYER + 32] != group_buyer || data[OFF_SELLER..OFF_SELLER + 32] != group_seller {
+            return Group::Malformed;
+        }
+        if data[OFF_STATUS] == STATUS_DELIVERED
+            && data[OFF_FLAGS] & FLAG_FROZEN == 0
+            && data[OFF_CALLBACK_PROGRAM..OFF_CALLBACK_PROGRAM + 32] == [0u8; 32]
+            && data[OFF_PLATFORM_FEE_BPS..OFF_PLATFORM_FEE_BPS + 2] == [0, 0]
+        {
+            Group::Accept
+        } else {
+            Group::Skip
+        }
+    };
+
+    let escrow = |status: u8| {
+        let mut data = [0u8; ESCROW_SIZE];
+        data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+        data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&seller);
+        data[OFF_STATUS] = status;
+        data
+    };
+
+    // A cart: two delivered, one still active, one already released
+    let cart = [escrow(STATUS_DELIVERED), escrow(STATUS_ACTIVE), escrow(STATUS_DELIVERED), escrow(STATUS_RELEASED)];
+    let accepted = cart.iter().filter(|d| classify(&d[..], buyer, seller) == Group::Accept).count();
+    assert_eq!(accepted, 2);
+
+    // Frozen and callback escrows are left for a standalone AcceptDelivery
+    let mut frozen = escrow(STATUS_DELIVERED);
+    frozen[OFF_FLAGS] |= FLAG_FROZEN;
+    assert_eq!(classify(&frozen, buyer, seller), Group::Skip);
+    let mut callback = escrow(STATUS_DELIVERED);
+    callback[OFF_CALLBACK_PROGRAM..OFF_CALLBACK_PROGRAM + 32].copy_from_slice(&[7u8; 32]);
+    assert_eq!(classify(&callback, buyer, seller), Group::Skip);
+    // So are escrows whose platform fee recipient isn't in the group
+    let mut fee = escrow(STATUS_DELIVERED);
+    fee[OFF_PLATFORM_FEE_BPS..OFF_PLATFORM_FEE_BPS + 2].copy_from_slice(&250u16.to_le_bytes());
+    assert_eq!(classify(&fee, buyer, seller), Group::Skip);
+
+    // Someone else's escrow fails the whole batch
+    assert_eq!(classify(&escrow(STATUS_DELIVERED), [3u8; 32], seller), Group::Malformed);
+    assert_eq!(classify(&escrow(STATUS_DELIVERED), buyer, [3u8; 32]), Group::Malformed);
+}
+
+#[test]
+fn test_releaser_authority() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let platform = [8u8; 32];
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+
+    // Mirrors ReleaseV2's authority check: the releaser if it's the one
+    // signing, else the buyer
+    let may_release = |data: &[u8], authority: [u8; 32], signed: bool| {
+        let releaser: [u8; 32] = data[OFF_RELEASER..OFF_RELEASER + 32].try_into().unwrap();
+        let stored: [u8; 32] = if releaser != [0u8; 32] && authority == releaser {
+            releaser
+        } else {
+            data[OFF_BUYER..OFF_BUYER + 32].try_into().unwrap()
+        };
+        signed && authority == stored
+    };
+
+    // Default: the buyer alone
+    assert!(may_release(&data, buyer, true));
+    assert!(!may_release(&data, platform, true));
+    assert!(!may_release(&data, [0u8; 32], true));
+
+    // With a releaser: either key, as long as it signed
+    data[OFF_RELEASER..OFF_RELEASER + 32].copy_from_slice(&platform);
+    assert!(may_release(&data, platform, true));
+    assert!(may_release(&data, buyer, true));
+    assert!(!may_release(&data, platform, false));
+    assert!(!may_release(&data, seller, true));
+
+    // Creation rule: the seller can't release to itself
+    let valid = |releaser: [u8; 32]| releaser == [0u8; 32] || releaser != seller;
+    assert!(valid(platform));
+    assert!(!valid(seller));
+}
+
+// SPL token account layout, as read by the token escrow paths
+const TOKEN_ACCOUNT_SIZE: usize = 165;
+const TOKEN_OFF_MINT: usize = 0;
+const TOKEN_OFF_OWNER: usize = 32;
+const TOKEN_OFF_AMOUNT: usize = 64;
+const TOKEN_OFF_STATE: usize = 108;
+
+fn token_account(mint: [u8; 32], owner: [u8; 32], amount: u64) -> [u8; TOKEN_ACCOUNT_SIZE] {
+    let mut data = [0u8; TOKEN_ACCOUNT_SIZE];
+    data[TOKEN_OFF_MINT..TOKEN_OFF_MINT + 32].copy_from_slice(&mint);
+    data[TOKEN_OFF_OWNER..TOKEN_OFF_OWNER + 32].copy_from_slice(&owner);
+    data[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+    data[TOKEN_OFF_STATE] = 1;
+    data
+}
+
+// Mirrors token_account_balance: initialized, right mint, right owner
+fn token_balance(data: &[u8], mint: &[u8; 32], owner: &[u8; 32]) -> Option<u64> {
+    if data[TOKEN_OFF_STATE] != 1
+        || &data[TOKEN_OFF_MINT..TOKEN_OFF_MINT + 32] != mint
+        || &data[TOKEN_OFF_OWNER..TOKEN_OFF_OWNER + 32] != owner
+    {
+        return None;
+    }
+    Some(u64::from_le_bytes(data[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].try_into().unwrap()))
+}
+
+// Mirrors TransferChecked's effect on the two token accounts
+fn move_tokens(source: &mut [u8], dest: &mut [u8], amount: u64) {
+    let from = u64::from_le_bytes(source[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].try_into().unwrap());
+    let to = u64::from_le_bytes(dest[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].try_into().unwrap());
+    source[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].copy_from_slice(&(from - amount).to_le_bytes());
+    dest[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].copy_from_slice(&(to + amount).to_le_bytes());
+}
+
+// Mirrors CreateEscrowV2 with the token accounts: buyer_token must cover the
+// amount, the vault must belong to the escrow, and the mint is recorded
+fn create_token_escrow(
+    data: &mut [u8],
+    buyer_token: &mut [u8],
+    vault: &mut [u8],
+    mint: [u8; 32],
+    decimals: u8,
+    amount: u64,
+) -> bool {
+    let buyer: [u8; 32] = data[OFF_BUYER..OFF_BUYER + 32].try_into().unwrap();
+    let escrow = [7u8; 32];
+    match token_balance(buyer_token, &mint, &buyer) {
+        Some(balance) if balance >= amount => {}
+        _ => return false,
+    }
+    if token_balance(vault, &mint, &escrow).is_none() {
+        return false;
+    }
+    data[OFF_MINT..OFF_MINT + 32].copy_from_slice(&mint);
+    data[OFF_MINT_DECIMALS] = decimals;
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    move_tokens(buyer_token, vault, amount);
+    true
+}
+
+// Mirrors TokenPayout::parse + pay: stored mint, vault balance covers the
+// amount, destination owned by the recipient; then the settlement is written
+fn token_payout(
+    data: &mut [u8],
+    vault: &mut [u8],
+    dest: &mut [u8],
+    recipient: [u8; 32],
+    status: u8,
+) -> bool {
+    let mint: [u8; 32] = data[OFF_MINT..OFF_MINT + 32].try_into().unwrap();
+    let amount = u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap());
+    match token_balance(vault, &mint, &[7u8; 32]) {
+        Some(balance) if balance >= amount => {}
+        _ => return false,
+    }
+    if token_balance(dest, &mint, &recipient).is_none() {
+        return false;
+    }
+    data[OFF_STATUS] = status;
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&0u64.to_le_bytes());
+    move_tokens(vault, dest, amount);
+    true
+}
+
+#[test]
+fn test_token_escrow_create_release() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let escrow = [7u8; 32];
+    let mint = [9u8; 32];
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&seller);
+
+    let mut buyer_token = token_account(mint, buyer, 5_000_000);
+    let mut vault = token_account(mint, escrow, 0);
+    let mut seller_token = token_account(mint, seller, 0);
+
+    // Not enough tokens, or a vault for another mint: nothing is created
+    let mut short = token_account(mint, buyer, 999);
+    assert!(!create_token_escrow(&mut data, &mut short, &mut vault, mint, 6, 1_000));
+    let mut wrong_vault = token_account([3u8; 32], escrow, 0);
+    assert!(!create_token_escrow(&mut data, &mut buyer_token, &mut wrong_vault, mint, 6, 1_000));
+    assert_eq!(&data[OFF_MINT..OFF_MINT + 32], &[0u8; 32]);
+
+    assert!(create_token_escrow(&mut data, &mut buyer_token, &mut vault, mint, 6, 1_000_000));
+    assert_eq!(&data[OFF_MINT..OFF_MINT + 32], &mint);
+    assert_eq!(data[OFF_MINT_DECIMALS], 6);
+    assert_eq!(token_balance(&buyer_token, &mint, &buyer), Some(4_000_000));
+    assert_eq!(token_balance(&vault, &mint, &escrow), Some(1_000_000));
+
+    // Release can't pay a token account the seller doesn't own
+    let mut buyer_owned = token_account(mint, buyer, 0);
+    assert!(!token_payout(&mut data, &mut vault, &mut buyer_owned, seller, STATUS_RELEASED));
+    assert_eq!(data[OFF_STATUS], STATUS_ACTIVE);
+
+    assert!(token_payout(&mut data, &mut vault, &mut seller_token, seller, STATUS_RELEASED));
+    assert_eq!(data[OFF_STATUS], STATUS_RELEASED);
+    assert_eq!(u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap()), 0);
+    assert_eq!(token_balance(&vault, &mint, &escrow), Some(0));
+    assert_eq!(token_balance(&seller_token, &mint, &seller), Some(1_000_000));
+}
+
+#[test]
+fn test_token_escrow_create_refund() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let escrow = [7u8; 32];
+    let mint = [9u8; 32];
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&seller);
+
+    let mut buyer_token = token_account(mint, buyer, 250);
+    let mut vault = token_account(mint, escrow, 0);
+    assert!(create_token_escrow(&mut data, &mut buyer_token, &mut vault, mint, 0, 250));
+    assert_eq!(token_balance(&buyer_token, &mint, &buyer), Some(0));
+
+    // A vault that lost tokens no longer covers the amount: BalanceMismatch
+    let mut drained = vault;
+    drained[TOKEN_OFF_AMOUNT..TOKEN_OFF_AMOUNT + 8].copy_from_slice(&100u64.to_le_bytes());
+    assert!(!token_payout(&mut data, &mut drained, &mut buyer_token, buyer, STATUS_REFUNDED));
+
+    assert!(token_payout(&mut data, &mut vault, &mut buyer_token, buyer, STATUS_REFUNDED));
+    assert_eq!(data[OFF_STATUS], STATUS_REFUNDED);
+    assert_eq!(token_balance(&buyer_token, &mint, &buyer), Some(250));
+    assert_eq!(token_balance(&vault, &mint, &escrow), Some(0));
+}
+
+#[test]
+fn test_close_escrow_reclaims_rent() {
+    let escrow_rent: u64 = 9_069_360; // rent-exempt minimum for ESCROW_SIZE
+    let vault_rent: u64 = 2_039_280; // rent-exempt minimum for a token account
+
+    // Mirrors CloseEscrow: terminal statuses only, then every lamport in the
+    // escrow (and a token escrow's vault, when passed) goes to the buyer
+    let close = |data: &mut [u8], escrow: &mut u64, vault: Option<&mut u64>, buyer: &mut u64| {
+        let status = data[OFF_STATUS];
+        if status != STATUS_RELEASED && status != STATUS_REFUNDED {
+            return false;
+        }
+        let token_escrow = data[OFF_MINT..OFF_MINT + 32] != [0u8; 32];
+        if let (true, Some(vault)) = (token_escrow, vault) {
+            *buyer += *vault;
+            *vault = 0;
+        }
+        data.fill(0);
+        *buyer += *escrow;
+        *escrow = 0;
+        true
+    };
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+    let mut escrow = escrow_rent;
+    let mut buyer: u64 = 1_000_000;
+    for status in [STATUS_ACTIVE, STATUS_DELIVERED, STATUS_DISPUTED, STATUS_APPEALED] {
+        data[OFF_STATUS] = status;
+        assert!(!close(&mut data, &mut escrow, None, &mut buyer));
+    }
+    assert_eq!(buyer, 1_000_000);
+    assert_eq!(escrow, escrow_rent);
+
+    data[OFF_STATUS] = STATUS_RELEASED;
+    assert!(close(&mut data, &mut escrow, None, &mut buyer));
+    assert_eq!(buyer, 1_000_000 + escrow_rent);
+    assert_eq!(escrow, 0);
+    assert!(data.iter().all(|&b| b == 0));
+
+    // A token escrow also returns its vault's rent
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_MINT..OFF_MINT + 32].copy_from_slice(&[9u8; 32]);
+    data[OFF_STATUS] = STATUS_REFUNDED;
+    let mut escrow = escrow_rent;
+    let mut vault = vault_rent;
+    let mut buyer: u64 = 0;
+    assert!(close(&mut data, &mut escrow, Some(&mut vault), &mut buyer));
+    assert_eq!(buyer, escrow_rent + vault_rent);
+    assert_eq!(vault, 0);
+}
+
+#[test]
+fn test_escrow_owner_check() {
+    #[derive(Debug, PartialEq)]
+    enum Invalid {
+        AccountOwner,
+        EscrowSize,
+        AccountData,
+    }
+
+    let program_id = [9u8; 32];
+    let attacker_program = [6u8; 32];
+
+    // Mirrors the prelude every v2 escrow handler runs before trusting the
+    // data: assert_escrow_owner, then size, then discriminator
+    let load = |owner: [u8; 32], data: &[u8]| {
+        if owner != program_id {
+            return Err(Invalid::AccountOwner);
+        }
+        if data.len() != ESCROW_SIZE {
+            return Err(Invalid::EscrowSize);
+        }
+        if u64::from_le_bytes(data[OFF_DISC..OFF_DISC + 8].try_into().unwrap()) != ESCROW_DISC {
+            return Err(Invalid::AccountData);
+        }
+        Ok(())
+    };
+
+    // A spoofed account: right size, discriminator and keys, wrong owner
+    let mut spoofed = [0u8; ESCROW_SIZE];
+    spoofed[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+    spoofed[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&[1u8; 32]);
+    spoofed[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&[2u8; 32]);
+    assert_eq!(load(attacker_program, &spoofed), Err(Invalid::AccountOwner));
+    assert_eq!(load([0u8; 32], &spoofed), Err(Invalid::AccountOwner));
+
+    // The owner check comes first, whatever the data looks like
+    assert_eq!(load(attacker_program, &[0u8; 8]), Err(Invalid::AccountOwner));
+
+    assert_eq!(load(program_id, &spoofed), Ok(()));
+    assert_eq!(load(program_id, &[0u8; 8]), Err(Invalid::EscrowSize));
+    assert_eq!(load(program_id, &[0u8; ESCROW_SIZE]), Err(Invalid::AccountData));
+}
+
+#[test]
+fn test_partial_release() {
+    const POLICY_DISALLOW_PARTIAL: u8 = 1 << 0;
+    let rent: u64 = 9_069_360;
+
+    // Mirrors PartialRelease: Active or Delivered, 0 < release <= amount, the
+    // balance covering amount + rent; the status only moves at zero
+    let release = |data: &mut [u8], lamports: &mut u64, release_amount: u64| {
+        let status = data[OFF_STATUS];
+        if status != STATUS_ACTIVE && status != STATUS_DELIVERED {
+            return false;
+        }
+        let amount = u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap());
+        if amount > lamports.saturating_sub(rent) || release_amount == 0 || release_amount > amount {
+            return false;
+        }
+        let remaining = amount - release_amount;
+        if remaining > 0 && data[OFF_POLICY] & POLICY_DISALLOW_PARTIAL != 0 {
+            return false;
+        }
+        data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&remaining.to_le_bytes());
+        if remaining == 0 {
+            data[OFF_STATUS] = STATUS_RELEASED;
+        }
+        *lamports -= release_amount;
+        true
+    };
+    let amount = |data: &[u8]| u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap());
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&1_000u64.to_le_bytes());
+    let mut lamports = rent + 1_000;
+
+    assert!(!release(&mut data, &mut lamports, 0));
+    assert!(!release(&mut data, &mut lamports, 1_001));
+
+    // Staged payouts keep the escrow open, in whatever state it was in
+    assert!(release(&mut data, &mut lamports, 300));
+    assert_eq!((amount(&data), data[OFF_STATUS]), (700, STATUS_ACTIVE));
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    assert!(release(&mut data, &mut lamports, 200));
+    assert_eq!((amount(&data), data[OFF_STATUS]), (500, STATUS_DELIVERED));
+    assert_eq!(lamports, rent + 500);
+
+    // A short balance would dip into rent: refused
+    let mut short = rent + 499;
+    assert!(!release(&mut data, &mut short, 100));
+
+    // The last part settles the escrow and leaves exactly the rent
+    assert!(release(&mut data, &mut lamports, 500));
+    assert_eq!((amount(&data), data[OFF_STATUS]), (0, STATUS_RELEASED));
+    assert_eq!(lamports, rent);
+    assert!(!release(&mut data, &mut lamports, 1));
+
+    // All-or-nothing escrows only release the whole amount
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&1_000u64.to_le_bytes());
+    data[OFF_POLICY] = POLICY_DISALLOW_PARTIAL;
+    let mut lamports = rent + 1_000;
+    assert!(!release(&mut data, &mut lamports, 999));
+    assert!(release(&mut data, &mut lamports, 1_000));
+    assert_eq!(data[OFF_STATUS], STATUS_RELEASED);
+}
+
+#[test]
+fn test_release_milestone() {
+    const MAX_MILESTONES: usize = 8;
+    let amounts = [300u64, 200, 500];
+
+    // Mirrors settle_milestone: `accept` (AcceptMilestone) needs the delivered
+    // bit, a plain release (ReleaseMilestone) doesn't; neither pays twice
+    let settle = |data: &mut [u8], index: usize, accept: bool| -> Option<u64> {
+        let count = data[OFF_MILESTONE_COUNT] as usize;
+        if data[OFF_STATUS] != STATUS_ACTIVE || index >= count {
+            return None;
+        }
+        let bit = 1u8 << index;
+        if data[OFF_MILESTONE_SETTLED] & bit != 0
+            || (accept && data[OFF_MILESTONE_DELIVERED] & bit == 0)
+        {
+            return None;
+        }
+        let offset = OFF_MILESTONE_AMOUNTS + index * 8;
+        let paid = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let amount = u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap());
+        data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&(amount - paid).to_le_bytes());
+        data[OFF_MILESTONE_SETTLED] |= bit;
+        if data[OFF_MILESTONE_SETTLED] as u16 == (1u16 << count) - 1 {
+            data[OFF_STATUS] = STATUS_RELEASED;
+        }
+        Some(paid)
+    };
+
+    // The schedule fits between the settled bitmask and the callback program
+    assert_eq!(OFF_MILESTONE_SETTLED + 1, OFF_MILESTONE_AMOUNTS);
+    assert_eq!(OFF_MILESTONE_AMOUNTS + MAX_MILESTONES * 8, OFF_CALLBACK_PROGRAM);
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_MILESTONE_COUNT] = amounts.len() as u8;
+    for (i, a) in amounts.iter().enumerate() {
+        let offset = OFF_MILESTONE_AMOUNTS + i * 8;
+        data[offset..offset + 8].copy_from_slice(&a.to_le_bytes());
+    }
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&1_000u64.to_le_bytes());
+
+    // Accepting needs a delivery; releasing doesn't
+    assert_eq!(settle(&mut data, 1, true), None);
+    assert_eq!(settle(&mut data, 1, false), Some(200));
+    assert_eq!(settle(&mut data, 1, false), None);
+    assert_eq!(settle(&mut data, 3, false), None);
+    assert_eq!(data[OFF_STATUS], STATUS_ACTIVE);
+
+    // Mixing both paths still settles once every milestone is paid
+    data[OFF_MILESTONE_DELIVERED] |= 1 << 0;
+    assert_eq!(settle(&mut data, 0, true), Some(300));
+    assert_eq!(settle(&mut data, 2, false), Some(500));
+    assert_eq!(data[OFF_STATUS], STATUS_RELEASED);
+    assert_eq!(u64::from_le_bytes(data[OFF_AMOUNT..OFF_AMOUNT + 8].try_into().unwrap()), 0);
+    assert_eq!(settle(&mut data, 0, false), None);
+}
+
+#[test]
+fn test_payout_keeps_escrow_rent_exempt() {
+    let rent: u64 = 9_416_880; // minimum_balance(ESCROW_SIZE)
+
+    // Mirrors transfer_from_escrow: move, then the escrow must still hold at
+    // least the rent-exempt minimum. sweep_from_escrow (closing) skips it.
+    let transfer = |escrow: &mut u64, dest: &mut u64, amount: u64, closing: bool| {
+        let after = escrow.checked_sub(amount).ok_or("InsufficientFunds")?;
+        if !closing && after < rent {
+            return Err("EscrowNotRentExempt");
+        }
+        *escrow = after;
+        *dest += amount;
+        Ok(())
+    };
+
+    let amount: u64 = 2_000_000;
+    let mut escrow = rent + amount;
+    let mut seller: u64 = 0;
+
+    // An amount that (wrongly) includes rent is refused, nothing moves
+    assert_eq!(transfer(&mut escrow, &mut seller, amount + rent, false), Err("EscrowNotRentExempt"));
+    assert_eq!(transfer(&mut escrow, &mut seller, amount + 1, false), Err("EscrowNotRentExempt"));
+    assert_eq!((escrow, seller), (rent + amount, 0));
+
+    // The escrowed amount itself leaves exactly the rent
+    assert_eq!(transfer(&mut escrow, &mut seller, amount, false), Ok(()));
+    assert_eq!((escrow, seller), (rent, amount));
+
+    // Closing hands out the rent too
+    let mut buyer: u64 = 0;
+    assert_eq!(transfer(&mut escrow, &mut buyer, rent, true), Ok(()));
+    assert_eq!((escrow, buyer), (0, rent));
+}
+
+#[test]
+fn test_arbitrator_fee() {
+    let rent: u64 = 9_416_880; // minimum_balance(ESCROW_SIZE)
+    let read = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+
+    // Mirrors validate_create: the fee fits in the amount, needs an
+    // arbitrator, and can't ride on appealable rulings
+    let valid = |amount: u64, fee: u64, arbitrator: bool, appeal: bool| {
+        fee <= amount && (fee == 0 || (arbitrator && !appeal))
+    };
+    assert!(valid(1_000, 0, false, true));
+    assert!(valid(1_000, 1_000, true, false));
+    assert!(!valid(1_000, 1_001, true, false));
+    assert!(!valid(1_000, 10, false, false));
+    assert!(!valid(1_000, 10, true, true));
+
+    // Mirrors Arbitrate: the winner is paid amount - fee, then the
+    // arbitrator gets the fee; the escrow keeps only its rent
+    let arbitrate = |data: &mut [u8], lamports: &mut [u64; 4], decision: u8, writable: bool| {
+        let amount = read(data, OFF_AMOUNT);
+        let fee = read(data, OFF_ARBITRATOR_FEE).min(amount);
+        if fee > 0 && !writable {
+            return Err("Immutable");
+        }
+        data[OFF_STATUS] = if decision == 0 { STATUS_REFUNDED } else { STATUS_RELEASED };
+        data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&0u64.to_le_bytes());
+        // [escrow, buyer, seller, arbitrator]
+        let winner = if decision == 0 { 1 } else { 2 };
+        lamports[0] -= amount;
+        lamports[winner] += amount - fee;
+        lamports[3] += fee;
+        Ok(())
+    };
+
+    let amount: u64 = 2_000_000;
+    let fee: u64 = 150_000;
+    let funded = || {
+        let mut data = [0u8; ESCROW_SIZE];
+        data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+        data[OFF_ARBITRATOR_FEE..OFF_ARBITRATOR_FEE + 8].copy_from_slice(&fee.to_le_bytes());
+        data[OFF_STATUS] = STATUS_DISPUTED;
+        data
+    };
+
+    // Release with a fee: seller and arbitrator split the amount
+    let mut data = funded();
+    let mut lamports = [rent + amount, 0, 0, 0];
+    assert_eq!(arbitrate(&mut data, &mut lamports, 1, false), Err("Immutable"));
+    assert_eq!(data[OFF_STATUS], STATUS_DISPUTED);
+    assert_eq!(arbitrate(&mut data, &mut lamports, 1, true), Ok(()));
+    assert_eq!(lamports, [rent, 0, amount - fee, fee]);
+    assert_eq!(data[OFF_STATUS], STATUS_RELEASED);
+
+    // Refund with a fee: the buyer gets the amount less the fee
+    let mut data = funded();
+    let mut lamports = [rent + amount, 0, 0, 0];
+    assert_eq!(arbitrate(&mut data, &mut lamports, 0, true), Ok(()));
+    assert_eq!(lamports, [rent, amount - fee, 0, fee]);
+    assert_eq!(data[OFF_STATUS], STATUS_REFUNDED);
+
+    // No fee: a read-only arbitrator is fine and the winner takes it all
+    let mut data = funded();
+    data[OFF_ARBITRATOR_FEE..OFF_ARBITRATOR_FEE + 8].copy_from_slice(&0u64.to_le_bytes());
+    let mut lamports = [rent + amount, 0, 0, 0];
+    assert_eq!(arbitrate(&mut data, &mut lamports, 0, false), Ok(()));
+    assert_eq!(lamports, [rent, amount, 0, 0]);
+
+    // Mirrors SplitEscrow: the fee follows the funds, rounding toward the parent
+    let child_fee = (fee as u128 * 700_000 / amount as u128) as u64;
+    assert_eq!(child_fee, 52_500);
+    assert_eq!(fee - child_fee, 97_500);
+}
+
+#[test]
+fn test_arbitrator_fee_time_scaled() {
+    const DISPUTE_RESPONSE_WINDOW_SECONDS: u64 = 86_400;
+    let read = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+
+    // Mirrors validate_create: a window only scales a fee
+    let valid = |fee: u64, window: u64| window == 0 || fee > 0;
+    assert!(valid(0, 0));
+    assert!(valid(150_000, 0));
+    assert!(valid(150_000, 604_800));
+    assert!(!valid(0, 604_800));
+
+    // Mirrors earned_arbitrator_fee: the whole fee when the arbitrator may
+    // first rule, decaying linearly to nothing at the deadline a window later
+    let earned = |data: &[u8], now: u64| {
+        let fee = read(data, OFF_ARBITRATOR_FEE);
+        let window = read(data, OFF_ARBITRATION_WINDOW);
+        let disputed_at = read(data, OFF_DISPUTED_AT);
+        if window == 0 || disputed_at == 0 {
+            return fee;
+        }
+        let opens_at = disputed_at.saturating_add(DISPUTE_RESPONSE_WINDOW_SECONDS);
+        let remaining = window - now.saturating_sub(opens_at).min(window);
+        (fee as u128 * remaining as u128 / window as u128) as u64
+    };
+    // Mirrors ruling_payout with the earned fee: the unearned rest is divided
+    // like the rest of the amount, i.e. it goes to the favoured side
+    let ruling_payout = |amount: u64, seller_bps: u16, fee: u64| {
+        let fee = fee.min(amount);
+        let seller_share = ((amount - fee) as u128 * seller_bps as u128 / 10_000) as u64;
+        (amount - fee - seller_share, seller_share, fee)
+    };
+
+    let amount: u64 = 2_000_000;
+    let fee: u64 = 150_000;
+    let window: u64 = 604_800;
+    let disputed_at: u64 = 1_700_000_000;
+    let opens_at = disputed_at + DISPUTE_RESPONSE_WINDOW_SECONDS;
+    let deadline = opens_at + window;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+    data[OFF_ARBITRATOR_FEE..OFF_ARBITRATOR_FEE + 8].copy_from_slice(&fee.to_le_bytes());
+    data[OFF_ARBITRATION_WINDOW..OFF_ARBITRATION_WINDOW + 8].copy_from_slice(&window.to_le_bytes());
+    data[OFF_DISPUTED_AT..OFF_DISPUTED_AT + 8].copy_from_slice(&disputed_at.to_le_bytes());
+
+    // Instant resolution: the whole fee
+    assert_eq!(earned(&data, opens_at), fee);
+    assert_eq!(ruling_payout(amount, 0, earned(&data, opens_at)), (amount - fee, 0, fee));
+    // One second in already rounds below it
+    assert_eq!(earned(&data, opens_at + 1), fee - 1);
+
+    // Halfway: half the fee, the other half to the winner
+    let half = earned(&data, opens_at + window / 2);
+    assert_eq!(half, fee / 2);
+    assert_eq!(ruling_payout(amount, 10_000, half), (0, amount - fee / 2, fee / 2));
+    // A split divides the unearned part by the ruling's shares
+    let (buyer, seller, paid) = ruling_payout(amount, 6_000, half);
+    assert_eq!((buyer, seller, paid), (770_000, 1_155_000, 75_000));
+    assert_eq!(buyer + seller + paid, amount);
+
+    // At the deadline, and any time after it, nothing is earned and the
+    // favoured side gets it all
+    assert_eq!(earned(&data, deadline), 0);
+    assert_eq!(earned(&data, deadline + 1), 0);
+    assert_eq!(earned(&data, deadline + 10 * window), 0);
+    assert_eq!(ruling_payout(amount, 0, earned(&data, deadline)), (amount, 0, 0));
+    assert_eq!(ruling_payout(amount, 10_000, earned(&data, deadline + 1)), (0, amount, 0));
+
+    // No window: the flat fee, whenever the ruling lands
+    let mut flat = data;
+    flat[OFF_ARBITRATION_WINDOW..OFF_ARBITRATION_WINDOW + 8].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(earned(&flat, opens_at), fee);
+    // A frozen escrow nobody disputed has no clock: the flat fee too
+    let mut frozen = data;
+    frozen[OFF_DISPUTED_AT..OFF_DISPUTED_AT + 8].copy_from_slice(&0u64.to_le_bytes());
+    assert_eq!(earned(&frozen, opens_at), fee);
+}
+
+#[test]
+fn test_platform_fee() {
+    const MAX_PLATFORM_FEE_BPS: u16 = 1_000;
+    let rent: u64 = 9_416_880; // minimum_balance(ESCROW_SIZE)
+    let recipient = [9u8; 32];
+
+    // Mirrors validate_create: capped at 10%, with a recipient
+    let valid = |bps: u16, recipient: [u8; 32]| {
+        bps <= MAX_PLATFORM_FEE_BPS && (bps == 0 || recipient != [0u8; 32])
+    };
+    assert!(valid(0, [0u8; 32]));
+    assert!(valid(250, recipient));
+    assert!(valid(MAX_PLATFORM_FEE_BPS, recipient));
+    assert!(!valid(MAX_PLATFORM_FEE_BPS + 1, recipient));
+    assert!(!valid(250, [0u8; 32]));
+
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_FEE_RECIPIENT..OFF_FEE_RECIPIENT + 32].copy_from_slice(&recipient);
+    data[OFF_PLATFORM_FEE_BPS..OFF_PLATFORM_FEE_BPS + 2].copy_from_slice(&250u16.to_le_bytes());
+
+    // Mirrors pay_seller: the fee rounds down and the seller takes the rest.
+    // Lamports: [escrow, seller, fee recipient]
+    let pay_seller = |data: &[u8], lamports: &mut [u64; 3], amount: u64| {
+        let bps = u16::from_le_bytes([data[OFF_PLATFORM_FEE_BPS], data[OFF_PLATFORM_FEE_BPS + 1]]);
+        let fee = (amount as u128 * bps as u128 / 10_000) as u64;
+        lamports[0] -= amount;
+        lamports[1] += amount - fee;
+        lamports[2] += fee;
+    };
+
+    for amount in [1_000_000u64, 999_999, 39, 1] {
+        let mut lamports = [rent + amount, 0, 0];
+        pay_seller(&data, &mut lamports, amount);
+        assert_eq!(lamports[0], rent);
+        assert_eq!(lamports[1] + lamports[2], amount);
+    }
+    let mut lamports = [rent + 1_000_000, 0, 0];
+    pay_seller(&data, &mut lamports, 1_000_000);
+    assert_eq!(lamports, [rent, 975_000, 25_000]);
+}
+
+#[test]
+fn test_extend_timeout() {
+    let read = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+
+    // Mirrors ExtendTimeout, after both signatures are checked
+    let extend = |data: &mut [u8], extension: u64| {
+        let status = data[OFF_STATUS];
+        if extension == 0 {
+            return Err("InvalidInstructionData");
+        }
+        if matches!(status, STATUS_RELEASED | STATUS_REFUNDED) {
+            return Err("AlreadySettled");
+        }
+        let timeout = read(data, OFF_TIMEOUT);
+        if status == STATUS_DISPUTED || timeout == 0 {
+            return Err("InvalidAccountData");
+        }
+        let timeout = timeout.checked_add(extension).ok_or("ArithmeticOverflow")?;
+        data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&timeout.to_le_bytes());
+        Ok(())
+    };
+    // Mirrors the buyer's timeout refund in RefundV2
+    let buyer_can_refund = |data: &[u8], now: u64| {
+        let timeout = read(data, OFF_TIMEOUT);
+        timeout != 0 && now.saturating_sub(read(data, OFF_CREATED_AT)) >= timeout
+    };
+
+    let created_at: u64 = 1_700_000_000;
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_CREATED_AT..OFF_CREATED_AT + 8].copy_from_slice(&created_at.to_le_bytes());
+    data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&3_600u64.to_le_bytes());
+    data[OFF_STATUS] = STATUS_ACTIVE;
+
+    // The refund is open at the original deadline...
+    let now = created_at + 3_600;
+    assert!(buyer_can_refund(&data, now));
+
+    // ...until both parties agree to another day
+    assert_eq!(extend(&mut data, 86_400), Ok(()));
+    assert_eq!(read(&data, OFF_TIMEOUT), 90_000);
+    assert!(!buyer_can_refund(&data, now));
+    assert!(!buyer_can_refund(&data, created_at + 89_999));
+    assert!(buyer_can_refund(&data, created_at + 90_000));
+
+    // Overflow and empty extensions leave the timeout as it was
+    assert_eq!(extend(&mut data, u64::MAX), Err("ArithmeticOverflow"));
+    assert_eq!(extend(&mut data, 0), Err("InvalidInstructionData"));
+    assert_eq!(read(&data, OFF_TIMEOUT), 90_000);
+
+    // Disputed and settled escrows can't be extended
+    data[OFF_STATUS] = STATUS_DISPUTED;
+    assert_eq!(extend(&mut data, 60), Err("InvalidAccountData"));
+    data[OFF_STATUS] = STATUS_REFUNDED;
+    assert_eq!(extend(&mut data, 60), Err("AlreadySettled"));
+}
+
+#[test]
+fn test_cancel_before_delivery() {
+    // Mirrors CancelEscrow's gate: Active with nothing delivered
+    let cancel = |data: &[u8]| {
+        if matches!(data[OFF_STATUS], STATUS_RELEASED | STATUS_REFUNDED) {
+            return Err("AlreadySettled");
+        }
+        if data[OFF_STATUS] == STATUS_DELIVERED
+            || data[OFF_FLAGS] & FLAG_SELLER_DELIVERED != 0
+            || data[OFF_MILESTONE_DELIVERED] != 0
+        {
+            return Err("AlreadyDelivered");
+        }
+        if data[OFF_STATUS] != STATUS_ACTIVE {
+            return Err("InvalidAccountData");
+        }
+        Ok(STATUS_REFUNDED)
+    };
+
+    // A fresh escrow the seller never touched cancels straight away
+    let mut data = [0u8; ESCROW_SIZE];
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    assert_eq!(cancel(&data), Ok(STATUS_REFUNDED));
+
+    // Once the seller delivers, the buyer needs RefundV2 or a dispute
+    data[OFF_STATUS] = STATUS_DELIVERED;
+    data[OFF_FLAGS] |= FLAG_SELLER_DELIVERED;
+    assert_eq!(cancel(&data), Err("AlreadyDelivered"));
+    // Either delivery record is enough on its own
+    data[OFF_STATUS] = STATUS_ACTIVE;
+    assert_eq!(cancel(&data), Err("AlreadyDelivered"));
+    data[OFF_FLAGS] = 0;
+    data[OFF_MILESTONE_DELIVERED] = 1 << 2;
+    assert_eq!(cancel(&data), Err("AlreadyDelivered"));
+
+    data[OFF_MILESTONE_DELIVERED] = 0;
+    data[OFF_STATUS] = STATUS_DISPUTED;
+    assert_eq!(cancel(&data), Err("InvalidAccountData"));
+    data[OFF_STATUS] = STATUS_REFUNDED;
+    assert_eq!(cancel(&data), Err("AlreadySettled"));
+}
+
+#[test]
+fn test_create_parties_distinct() {
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let arbitrator = [3u8; 32];
+    let escrow = [4u8; 32];
+    let none = [0u8; 32];
+
+    // Mirrors validate_create: assert_distinct over the accounts, then the
+    // appeal arbitrator from instruction data
+    let check = |buyer: &[u8; 32], seller: &[u8; 32], arbitrator: &[u8; 32], appeal: &[u8; 32]| {
+        let keys = [buyer, seller, arbitrator, &escrow];
+        for (i, a) in keys.iter().enumerate() {
+            if keys[i + 1..].contains(a) {
+                return Err("DuplicateAccount");
+            }
+        }
+        if appeal != &none && (appeal == buyer || appeal == seller) {
+            return Err("DuplicateAccount");
+        }
+        Ok(())
+    };
+
+    assert_eq!(check(&buyer, &seller, &arbitrator, &none), Ok(()));
+    assert_eq!(check(&buyer, &seller, &arbitrator, &[5u8; 32]), Ok(()));
+    // Self-escrow
+    assert_eq!(check(&buyer, &buyer, &arbitrator, &none), Err("DuplicateAccount"));
+    // An arbitrator who is also a party
+    assert_eq!(check(&buyer, &seller, &buyer, &none), Err("DuplicateAccount"));
+    assert_eq!(check(&buyer, &seller, &seller, &none), Err("DuplicateAccount"));
+    assert_eq!(check(&buyer, &seller, &arbitrator, &buyer), Err("DuplicateAccount"));
+    assert_eq!(check(&buyer, &seller, &arbitrator, &seller), Err("DuplicateAccount"));
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn test_borsh_escrow_matches_layout() {
+    use pact_escrow::pact_client::{EscrowV2, ESCROW_V2_LEN};
+
+    assert_eq!(ESCROW_V2_LEN, ESCROW_SIZE);
+
+    // Every byte distinct enough that a field read at the wrong offset shows
+    let mut data = [0u8; ESCROW_SIZE];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = (i * 7 + i / 251) as u8;
+    }
+    data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+
+    let escrow = EscrowV2::unpack(&data).unwrap();
+    let u64_at = |off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+    let u16_at = |off: usize| u16::from_le_bytes([data[off], data[off + 1]]);
+    let key_at = |off: usize| -> [u8; 32] { data[off..off + 32].try_into().unwrap() };
+
+    assert_eq!(escrow.discriminator, ESCROW_DISC);
+    assert_eq!(escrow.buyer, key_at(OFF_BUYER));
+    assert_eq!(escrow.seller, key_at(OFF_SELLER));
+    assert_eq!(escrow.arbitrator, key_at(OFF_ARBITRATOR));
+    assert_eq!(escrow.mint, key_at(OFF_MINT));
+    assert_eq!(escrow.amount, u64_at(OFF_AMOUNT));
+    assert_eq!(escrow.created_at, u64_at(OFF_CREATED_AT));
+    assert_eq!(escrow.timeout_seconds, u64_at(OFF_TIMEOUT));
+    assert_eq!(escrow.terms_hash, key_at(OFF_TERMS_HASH));
+    assert_eq!(escrow.status, data[OFF_STATUS]);
+    assert_eq!(escrow.flags, data[OFF_FLAGS]);
+    assert_eq!(escrow.bump, data[OFF_BUMP]);
+    assert_eq!(escrow.appeal_arbitrator, key_at(OFF_APPEAL_ARBITRATOR));
+    assert_eq!(escrow.ruling, data[OFF_RULING]);
+    assert_eq!(escrow.ruled_at, u64_at(OFF_RULED_AT));
+    assert_eq!(escrow.delivered_at, u64_at(OFF_DELIVERED_AT));
+    assert_eq!(escrow.min_dispute_delay, u64_at(OFF_MIN_DISPUTE_DELAY));
+    assert_eq!(escrow.timeout_mode, data[OFF_TIMEOUT_MODE]);
+    assert_eq!(escrow.funder_count, data[OFF_FUNDER_COUNT]);
+    for (i, funder) in escrow.funders.iter().enumerate() {
+        let off = OFF_FUNDERS + i * FUNDER_ENTRY_SIZE;
+        assert_eq!(funder.key, key_at(off));
+        assert_eq!(funder.amount, u64_at(off + 32));
+    }
+    assert_eq!(escrow.delivery_proof, key_at(OFF_DELIVERY_PROOF));
+    assert_eq!(escrow.price_oracle, key_at(OFF_PRICE_ORACLE));
+    assert_eq!(escrow.price_threshold, u64_at(OFF_PRICE_THRESHOLD) as i64);
+    assert_eq!(escrow.funded_amount, u64_at(OFF_FUNDED_AMOUNT));
+    assert_eq!(escrow.proposed_buyer_bps, u16_at(OFF_PROPOSED_BUYER_BPS));
+    assert_eq!(escrow.proposed_at, u64_at(OFF_PROPOSED_AT));
+    assert_eq!(escrow.quote_mint, key_at(OFF_QUOTE_MINT));
+    assert_eq!(escrow.quote_amount, u64_at(OFF_QUOTE_AMOUNT));
+    assert_eq!(escrow.disputed_at, u64_at(OFF_DISPUTED_AT));
+    assert_eq!(escrow.seed, u64_at(OFF_SEED));
+    assert_eq!(escrow.milestone_count, data[OFF_MILESTONE_COUNT]);
+    assert_eq!(escrow.milestone_delivered, data[OFF_MILESTONE_DELIVERED]);
+    assert_eq!(escrow.milestone_settled, data[OFF_MILESTONE_SETTLED]);
+    for (i, amount) in escrow.milestone_amounts.iter().enumerate() {
+        assert_eq!(*amount, u64_at(OFF_MILESTONE_AMOUNTS + i * 8));
+    }
+    assert_eq!(escrow.callback_program, key_at(OFF_CALLBACK_PROGRAM));
+    assert_eq!(escrow.acceptance_window_seconds, u64_at(OFF_ACCEPTANCE_WINDOW));
+    assert_eq!(escrow.periods_remaining, u16_at(OFF_PERIODS_REMAINING));
+    assert_eq!(escrow.period_amount, u64_at(OFF_PERIOD_AMOUNT));
+    assert_eq!(escrow.cancel_fee_cap, u64_at(OFF_CANCEL_FEE_CAP));
+    assert_eq!(escrow.cancel_fee_ramp_seconds, u64_at(OFF_CANCEL_FEE_RAMP));
+    assert_eq!(escrow.require_rationale, data[OFF_REQUIRE_RATIONALE]);
+    assert_eq!(escrow.rationale_hash, key_at(OFF_RATIONALE_HASH));
+    assert_eq!(escrow.notify, data[OFF_NOTIFY]);
+    assert_eq!(escrow.seller_threshold, data[OFF_SELLER_THRESHOLD]);
+    assert_eq!(escrow.seller_signer_count, data[OFF_SELLER_SIGNER_COUNT]);
+    for (i, signer) in escrow.seller_signers.iter().enumerate() {
+        assert_eq!(*signer, key_at(OFF_SELLER_SIGNERS + i * 32));
+    }
+    assert_eq!(escrow.strict_acceptance, data[OFF_STRICT_ACCEPTANCE]);
+    assert_eq!(escrow.rent_paid, u64_at(OFF_RENT_PAID));
+    assert_eq!(escrow.updated_at, u64_at(OFF_UPDATED_AT));
+    assert_eq!(escrow.original_seller, key_at(OFF_ORIGINAL_SELLER));
+    assert_eq!(escrow.delivery_deadline, u64_at(OFF_DELIVERY_DEADLINE));
+    assert_eq!(escrow.late_penalty_bps, u16_at(OFF_LATE_PENALTY_BPS));
+    assert_eq!(escrow.mint_decimals, data[OFF_MINT_DECIMALS]);
+    assert_eq!(escrow.policy, data[OFF_POLICY]);
+    assert_eq!(escrow.rent_recipient, data[OFF_RENT_RECIPIENT]);
+    assert_eq!(escrow.rent_treasury, key_at(OFF_RENT_TREASURY));
+    assert_eq!(escrow.releaser, key_at(OFF_RELEASER));
+    assert_eq!(escrow.arbitrator_fee, u64_at(OFF_ARBITRATOR_FEE));
+    assert_eq!(escrow.fee_recipient, key_at(OFF_FEE_RECIPIENT));
+    assert_eq!(escrow.platform_fee_bps, u16_at(OFF_PLATFORM_FEE_BPS));
+    assert_eq!(escrow.arbitration_window, u64_at(OFF_ARBITRATION_WINDOW));
+
+    // Round trip: serializing gives the account bytes back
+    assert_eq!(borsh::to_vec(&escrow).unwrap(), data.to_vec());
+
+    // Foreign or mis-sized accounts are refused
+    let mut foreign = data;
+    foreign[OFF_DISC] ^= 1;
+    assert!(EscrowV2::unpack(&foreign).is_err());
+    assert!(EscrowV2::unpack(&data[..ESCROW_SIZE - 8]).is_err());
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn test_borsh_instruction_matches_layout() {
+    use pact_escrow::pact_client::Instruction;
+
+    // Same bytes as test_create_escrow_instruction_data
+    let amount: u64 = 100_000_000;
+    let seed: u64 = 1234567890;
+    let timeout: u64 = 259200;
+    let terms_hash = [0xABu8; 32];
+    let mut create = [0u8; 57];
+    create[1..9].copy_from_slice(&amount.to_le_bytes());
+    create[9..17].copy_from_slice(&seed.to_le_bytes());
+    create[17..25].copy_from_slice(&timeout.to_le_bytes());
+    create[25..57].copy_from_slice(&terms_hash);
+
+    // discriminator(1) + decision(1) + rationale_hash(32)
+    let mut arbitrate = [6u8; 34];
+    arbitrate[1] = 1;
+    arbitrate[2..34].copy_from_slice(&[0xCDu8; 32]);
+
+    let mut lookup = vec![10u8];
+    lookup.extend_from_slice(&[1u8; 32]);
+    lookup.extend_from_slice(&[2u8; 32]);
+    lookup.extend_from_slice(&seed.to_le_bytes());
+
+    let mut allowed = vec![19u8];
+    allowed.extend_from_slice(&[3u8; 32]);
+    allowed.push(1);
+
+    let cases: Vec<(Instruction, Vec<u8>)> = vec![
+        (
+            Instruction::CreateEscrow { amount, seed, timeout_seconds: timeout, terms_hash },
+            create.to_vec(),
+        ),
+        (Instruction::Release, vec![3]),
+        (Instruction::Arbitrate { decision: 1, rationale_hash: [0xCD; 32] }, arbitrate.to_vec()),
+        (Instruction::LookupEscrow { buyer: [1; 32], seller: [2; 32], seed }, lookup),
+        (Instruction::ProposeSettlement { buyer_bps: 2_500 }, vec![15, 0xC4, 0x09]),
+        (Instruction::SetSellerAllowed { seller: [3; 32], allowed: true }, allowed),
+        (Instruction::AcceptMilestone { index: 2 }, vec![23, 2]),
+        (Instruction::ExportState { offset: 1_019 }, vec![35, 0xFB, 0x03]),
+        (Instruction::PartialRelease { release_amount: 1 }, vec![37, 1, 0, 0, 0, 0, 0, 0, 0]),
+        (Instruction::ExtendTimeout { extension: 86_400 }, [&[39u8][..], &86_400u64.to_le_bytes()].concat()),
+        (Instruction::CreateEscrowBatch { count: 3 }, vec![41, 3]),
+        (Instruction::TopUp { amount: 1 }, vec![42, 1, 0, 0, 0, 0, 0, 0, 0]),
+    ];
+    for (ix, bytes) in cases {
+        assert_eq!(ix.pack(), bytes, "{ix:?}");
+        assert_eq!(Instruction::unpack(&bytes).unwrap(), ix);
+    }
+
+    // A batch record is the seller, then the create payload
+    let record = pact_escrow::pact_client::BatchCreateRecord {
+        seller: [2; 32],
+        amount,
+        seed,
+        timeout_seconds: timeout,
+        terms_hash,
+    };
+    let packed = borsh::to_vec(&record).unwrap();
+    assert_eq!(packed.len(), 88);
+    assert_eq!(&packed[..32], &[2; 32]);
+    assert_eq!(&packed[32..], &create[1..]);
+
+    // A split ruling: discriminator(1) + seller_bps(2) + rationale_hash(32),
+    // the form parse_ruling reads as seller_bps
+    let split = Instruction::pack_arbitrate_split(6_000, [0xCD; 32]);
+    assert_eq!(split.len(), 35);
+    assert_eq!(&split[..3], &[6, 0x70, 0x17]);
+    assert_eq!(&split[3..], &[0xCD; 32]);
+
+    // Unknown discriminators and short data don't parse
+    assert!(Instruction::unpack(&[40]).is_err());
+    assert!(Instruction::unpack(&[13, 1, 2]).is_err());
+}
+
+#[test]
+fn test_migrate_v1_to_v2() {
+    // The v1 layout: disc(8) + buyer(32) + seller(32) + amount(8) + status(1)
+    const V1_ESCROW_SIZE: usize = 81;
+    const V1_OFF_AMOUNT: usize = 72;
+    const V1_OFF_STATUS: usize = 80;
+    const NATIVE_DECIMALS: u8 = 9;
+
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let v1 = |amount: u64, status: u8| {
+        let mut data = [0u8; V1_ESCROW_SIZE];
+        data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+        data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+        data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&seller);
+        data[V1_OFF_AMOUNT..V1_OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+        data[V1_OFF_STATUS] = status;
+        data
+    };
+
+    // Mirrors MigrateV1ToV2: resize, clear everything past the seller, then
+    // write the v2 fields
+    let migrate = |v1: &[u8], seed: u64, bump: u8, now: u64| -> Result<Vec<u8>, &str> {
+        if v1.len() != V1_ESCROW_SIZE {
+            return Err("InvalidEscrowSize");
+        }
+        let v1_amount = u64::from_le_bytes(v1[V1_OFF_AMOUNT..V1_OFF_AMOUNT + 8].try_into().unwrap());
+        let status = match v1[V1_OFF_STATUS] {
+            0 => STATUS_ACTIVE,
+            1 => STATUS_RELEASED,
+            2 => STATUS_REFUNDED,
+            _ => return Err("InvalidAccountData"),
+        };
+        let amount = if status == STATUS_ACTIVE { v1_amount } else { 0 };
+
+        let mut data = v1.to_vec();
+        data.resize(ESCROW_SIZE, 0xEE);
+        data[V1_OFF_AMOUNT..].fill(0);
+        let mut put = |off: usize, v: u64| data[off..off + 8].copy_from_slice(&v.to_le_bytes());
+        put(OFF_AMOUNT, amount);
+        put(OFF_CREATED_AT, now);
+        put(OFF_FUNDERS + 32, amount);
+        put(OFF_FUNDED_AMOUNT, v1_amount);
+        put(OFF_SEED, seed);
+        put(OFF_UPDATED_AT, now);
+        data[OFF_STATUS] = status;
+        data[OFF_BUMP] = bump;
+        data[OFF_FUNDER_COUNT] = 1;
+        data[OFF_FUNDERS..OFF_FUNDERS + 32].copy_from_slice(&v1[OFF_BUYER..OFF_BUYER + 32]);
+        data[OFF_MINT_DECIMALS] = NATIVE_DECIMALS;
+        Ok(data)
+    };
+
+    let u64_at = |data: &[u8], off: usize| u64::from_le_bytes(data[off..off + 8].try_into().unwrap());
+    let now: u64 = 1_700_000_000;
+
+    // A live v1 escrow keeps its parties and amount, and reads as Active
+    let data = migrate(&v1(500_000_000, 0), 42, 253, now).unwrap();
+    assert_eq!(data.len(), ESCROW_SIZE);
+    assert_eq!(u64_at(&data, OFF_DISC), ESCROW_DISC);
+    assert_eq!(&data[OFF_BUYER..OFF_BUYER + 32], &buyer);
+    assert_eq!(&data[OFF_SELLER..OFF_SELLER + 32], &seller);
+    // The old amount bytes are now the arbitrator: none
+    assert_eq!(&data[OFF_ARBITRATOR..OFF_ARBITRATOR + 32], &[0u8; 32]);
+    assert_eq!(&data[OFF_MINT..OFF_MINT + 32], &[0u8; 32]);
+    assert_eq!(u64_at(&data, OFF_AMOUNT), 500_000_000);
+    assert_eq!(u64_at(&data, OFF_FUNDED_AMOUNT), 500_000_000);
+    assert_eq!(u64_at(&data, OFF_TIMEOUT), 0);
+    assert_eq!(u64_at(&data, OFF_CREATED_AT), now);
+    assert_eq!(data[OFF_STATUS], STATUS_ACTIVE);
+    assert_eq!(data[OFF_FLAGS], 0);
+    assert_eq!(data[OFF_BUMP], 253);
+    assert_eq!(u64_at(&data, OFF_SEED), 42);
+    assert_eq!(data[OFF_FUNDER_COUNT], 1);
+    assert_eq!(&data[OFF_FUNDERS..OFF_FUNDERS + 32], &buyer);
+    assert_eq!(u64_at(&data, OFF_FUNDERS + 32), 500_000_000);
+    assert_eq!(data[OFF_MINT_DECIMALS], NATIVE_DECIMALS);
+    assert_eq!(data[OFF_RENT_RECIPIENT], 0);
+    // Nothing left over from the resize
+    assert!(data[OFF_RENT_TREASURY..].iter().all(|b| *b == 0));
+
+    #[cfg(feature = "client")]
+    {
+        let view = pact_escrow::view::EscrowView::from_bytes(&data).unwrap();
+        assert_eq!(view.amount, 500_000_000);
+        assert_eq!(view.time_remaining(now), None);
+        assert!(!view.is_terminal());
+    }
+
+    // Settled v1 escrows keep their outcome and hold nothing
+    for (v1_status, v2_status) in [(1, STATUS_RELEASED), (2, STATUS_REFUNDED)] {
+        let data = migrate(&v1(500_000_000, v1_status), 42, 253, now).unwrap();
+        assert_eq!(data[OFF_STATUS], v2_status);
+        assert_eq!(u64_at(&data, OFF_AMOUNT), 0);
+        assert_eq!(u64_at(&data, OFF_FUNDED_AMOUNT), 500_000_000);
+    }
+
+    // Unknown v1 statuses and accounts that aren't v1-sized are refused
+    assert_eq!(migrate(&v1(1, 3), 42, 253, now), Err("InvalidAccountData"));
+    assert_eq!(migrate(&[0u8; ESCROW_SIZE], 42, 253, now), Err("InvalidEscrowSize"));
+}
+
+/// Test the in-place key comparison the handlers use for their party checks
+/// agrees with comparing a copied key, including ReleaseV2's releaser branch
+#[test]
+fn test_pubkey_eq_in_place() {
+    // Mirrors pubkey_eq in instructions_v2.rs
+    fn pubkey_eq(data: &[u8], offset: usize, key: &[u8; 32]) -> bool {
+        data[offset..offset + 32] == key[..]
+    }
+    let read_pubkey = |data: &[u8], offset: usize| -> [u8; 32] {
+        data[offset..offset + 32].try_into().unwrap()
+    };
+
+    let buyer = [1u8; 32];
+    let seller = [2u8; 32];
+    let mut data = vec![0u8; ESCROW_SIZE];
+    data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+    data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(&seller);
+
+    for key in [buyer, seller, [0u8; 32], [9u8; 32]] {
+        for offset in [OFF_BUYER, OFF_SELLER, OFF_RELEASER] {
+            assert_eq!(pubkey_eq(&data, offset, &key), read_pubkey(&data, offset) == key);
+        }
+    }
+
+    // A key that differs only in its last byte is still a mismatch
+    let mut almost = buyer;
+    almost[31] ^= 1;
+    assert!(!pubkey_eq(&data, OFF_BUYER, &almost));
+
+    // No releaser set: nobody counts as the releaser, the buyer must sign
+    let by_releaser = |data: &[u8], authority: &[u8; 32]| {
+        pubkey_eq(data, OFF_RELEASER, authority) && !pubkey_eq(data, OFF_RELEASER, &[0u8; 32])
+    };
+    assert!(!by_releaser(&data, &buyer));
+    assert!(!by_releaser(&data, &[0u8; 32]));
+
+    let releaser = [7u8; 32];
+    data[OFF_RELEASER..OFF_RELEASER + 32].copy_from_slice(&releaser);
+    assert!(by_releaser(&data, &releaser));
+    assert!(!by_releaser(&data, &buyer));
+}
+
+#[test]
+fn test_create_escrow_batch() {
+    const MAX_BATCH_CREATE: usize = 8;
+    const RECORD_LEN: usize = 88;
+
+    let buyer = [1u8; 32];
+    let record = |seller: [u8; 32], amount: u64, seed: u64| {
+        let mut r = Vec::with_capacity(RECORD_LEN);
+        r.extend_from_slice(&seller);
+        r.extend_from_slice(&amount.to_le_bytes());
+        r.extend_from_slice(&seed.to_le_bytes());
+        r.extend_from_slice(&86_400u64.to_le_bytes());
+        r.extend_from_slice(&[0xABu8; 32]);
+        r
+    };
+    let batch = |records: &[Vec<u8>]| [vec![records.len() as u8], records.concat()].concat();
+
+    // Mirrors CreateEscrowBatch over a ledger of escrow address -> data:
+    // each group goes through CreateEscrowV2, and a failure anywhere leaves
+    // the ledger as it was, like a failed transaction
+    type Ledger = std::collections::HashMap<[u8; 32], Vec<u8>>;
+    let run = |ledger: &mut Ledger, groups: &[([u8; 32], [u8; 32])], data: &[u8]| -> Result<(), &str> {
+        let (&count, records) = data.split_first().ok_or("InvalidInstructionData")?;
+        let count = count as usize;
+        if count == 0 || count > MAX_BATCH_CREATE || records.len() != count * RECORD_LEN {
+            return Err("InvalidInstructionData");
+        }
+        if groups.len() < count {
+            return Err("NotEnoughAccountKeys");
+        }
+
+        let mut after = ledger.clone();
+        for (record, (seller, escrow)) in records.chunks_exact(RECORD_LEN).zip(groups) {
+            if &record[..32] != seller {
+                return Err("InvalidAccountData");
+            }
+            // The parts of CreateEscrowV2 that matter here
+            let amount = u64::from_le_bytes(record[32..40].try_into().unwrap());
+            if amount == 0 || seller == &buyer {
+                return Err("InvalidInstructionData");
+            }
+            if after.contains_key(escrow) {
+                return Err("AccountAlreadyInUse");
+            }
+            let mut data = vec![0u8; ESCROW_SIZE];
+            data[OFF_DISC..OFF_DISC + 8].copy_from_slice(&ESCROW_DISC.to_le_bytes());
+            data[OFF_BUYER..OFF_BUYER + 32].copy_from_slice(&buyer);
+            data[OFF_SELLER..OFF_SELLER + 32].copy_from_slice(seller);
+            data[OFF_AMOUNT..OFF_AMOUNT + 8].copy_from_slice(&amount.to_le_bytes());
+            data[OFF_TIMEOUT..OFF_TIMEOUT + 8].copy_from_slice(&record[48..56]);
+            data[OFF_TERMS_HASH..OFF_TERMS_HASH + 32].copy_from_slice(&record[56..88]);
+            data[OFF_SEED..OFF_SEED + 8].copy_from_slice(&record[40..48]);
+            data[OFF_STATUS] = STATUS_ACTIVE;
+            after.insert(*escrow, data);
+        }
+        *ledger = after;
+        Ok(())
+    };
+
+    // Three orders, three sellers, one call: all three escrows initialized
+    let groups = [([2u8; 32], [12u8; 32]), ([3u8; 32], [13u8; 32]), ([4u8; 32], [14u8; 32])];
+    let records: Vec<Vec<u8>> =
+        groups.iter().enumerate().map(|(i, (seller, _))| record(*seller, 1_000 * (i as u64 + 1), i as u64)).collect();
+    let mut ledger = Ledger::new();
+    assert_eq!(run(&mut ledger, &groups, &batch(&records)), Ok(()));
+    assert_eq!(ledger.len(), 3);
+    for (i, (seller, escrow)) in groups.iter().enumerate() {
+        let data = &ledger[escrow];
+        assert_eq!(&data[OFF_DISC..OFF_DISC + 8], &ESCROW_DISC.to_le_bytes());
+        assert_eq!(&data[OFF_SELLER..OFF_SELLER + 32], seller);
+        assert_eq!(&data[OFF_AMOUNT..OFF_AMOUNT + 8], &(1_000 * (i as u64 + 1)).to_le_bytes());
+        assert_eq!(&data[OFF_SEED..OFF_SEED + 8], &(i as u64).to_le_bytes());
+        assert_eq!(data[OFF_STATUS], STATUS_ACTIVE);
+        // No arbitrator: the system program's key stood in for it
+        assert_eq!(&data[OFF_ARBITRATOR..OFF_ARBITRATOR + 32], &[0u8; 32]);
+    }
+
+    // Atomic: the third record fails, so the first two aren't created either
+    let mut bad = records.clone();
+    bad[2] = record([4u8; 32], 0, 2);
+    let mut fresh = Ledger::new();
+    assert_eq!(run(&mut fresh, &groups, &batch(&bad)), Err("InvalidInstructionData"));
+    assert!(fresh.is_empty());
+
+    // Re-running the same batch hits the existing escrows
+    assert_eq!(run(&mut ledger, &groups, &batch(&records)), Err("AccountAlreadyInUse"));
+    assert_eq!(ledger.len(), 3);
+
+    // A record's seller must be the account in its group
+    let swapped = [groups[1], groups[0], groups[2]];
+    assert_eq!(run(&mut Ledger::new(), &swapped, &batch(&records)), Err("InvalidAccountData"));
+
+    // Empty, oversized, truncated or under-accounted batches are refused
+    assert_eq!(run(&mut Ledger::new(), &groups, &batch(&[])), Err("InvalidInstructionData"));
+    let nine = vec![record([2u8; 32], 1, 0); MAX_BATCH_CREATE + 1];
+    assert_eq!(run(&mut Ledger::new(), &groups, &batch(&nine)), Err("InvalidInstructionData"));
+    let truncated = batch(&records);
+    assert_eq!(
+        run(&mut Ledger::new(), &groups, &truncated[..truncated.len() - 1]),
+        Err("InvalidInstructionData")
+    );
+    assert_eq!(run(&mut Ledger::new(), &groups[..2], &batch(&records)), Err("NotEnoughAccountKeys"));
+}